@@ -0,0 +1,305 @@
+use lexer::Span;
+
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+pub const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+pub const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+pub const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+pub const OWL_SAME_AS: &str = "http://www.w3.org/2002/07/owl#sameAs";
+pub const LOG_IMPLIES: &str = "http://www.w3.org/2000/10/swap/log#implies";
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Term {
+	Iri(String),
+	// prefix:local as written; the prefix is resolved against the document's @prefix list
+	Name(String, String),
+	// ?x, universally quantified over the formula the rule is written in
+	Variable(String),
+	// _:b; labels made up by Formula::triples start with '#', which no written label can
+	Blank(String),
+	Text(String, Option<String>),
+	// lexical form and datatype
+	Typed(String, Box<Term>),
+	Integer(i64),
+	Decimal(f64),
+	Boolean(bool),
+	// ( ... ), an rdf:first / rdf:rest list
+	Collection(Vec<Term>),
+	// [ ... ], a blank node described in place
+	BlankNode(Vec<PredicateObjects>),
+	// { ... }, a quoted graph
+	Formula(Formula),
+	// << s p o >>, a triple as a term without asserting it, as RDF-star writes one
+	Quoted(Box<Triple>)
+}
+
+// A predicate, with `inverse` set when subject and object swap places, as they do for `<=`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Verb {
+	pub predicate: Term,
+	pub inverse: bool
+}
+
+// verb object, object, ...
+#[derive(Clone, PartialEq, Debug)]
+pub struct PredicateObjects {
+	pub verb: Verb,
+	pub objects: Vec<Term>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Statement {
+	Prefix(String, String),
+	Base(String),
+	ForAll(Vec<Term>),
+	ForSome(Vec<Term>),
+	// subject verb object, ...; verb object, ... where the list is only empty for a
+	// bare `[ ... ] .`
+	Triples(Term, Vec<PredicateObjects>),
+	// name { ... }, a named graph as TriG writes one; only at the top level
+	Graph(Term, Formula)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Triple {
+	pub subject: Term,
+	pub predicate: Term,
+	pub object: Term
+}
+
+// Two formulas are equal when their statements are; where they were written does not count.
+#[derive(Clone, Debug, Default)]
+pub struct Formula {
+	pub statements: Vec<Statement>,
+	// where each statement starts, when the formula was parsed
+	pub spans: Vec<Span>
+}
+
+impl PartialEq for Formula {
+	fn eq(&self, other: &Formula) -> bool {
+		self.statements == other.statements
+	}
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Document {
+	pub formula: Formula
+}
+
+// `{ body } => { head }`. Universals are the @forAll terms in scope where the rule is
+// written; ?variables are universal without being declared.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rule {
+	pub body: Formula,
+	pub head: Formula,
+	pub universals: Vec<Term>
+}
+
+impl Term {
+	pub fn is_implication(&self) -> bool {
+		*self == Term::Iri(LOG_IMPLIES.to_string())
+	}
+
+	fn variables(&self, names: &mut Vec<String>) {
+		match *self {
+			Term::Variable(ref name) if !names.contains(name) => names.push(name.clone()),
+			Term::Collection(ref items) => for item in items.iter() {
+				item.variables(names);
+			},
+			Term::BlankNode(ref list) => for po in list.iter() {
+				po.variables(names);
+			},
+			Term::Formula(ref inner) => for statement in inner.statements.iter() {
+				statement.variables(names);
+			},
+			Term::Quoted(ref triple) => {
+				triple.subject.variables(names);
+				triple.predicate.variables(names);
+				triple.object.variables(names);
+			}
+			_ => {}
+		}
+	}
+}
+
+impl PredicateObjects {
+	fn variables(&self, names: &mut Vec<String>) {
+		self.verb.predicate.variables(names);
+		for object in self.objects.iter() {
+			object.variables(names);
+		}
+	}
+}
+
+impl Statement {
+	fn variables(&self, names: &mut Vec<String>) {
+		if let Statement::Triples(ref subject, ref list) = *self {
+			subject.variables(names);
+			for po in list.iter() {
+				po.variables(names);
+			}
+		}
+	}
+}
+
+// Turns collections and described blank nodes into plain triples, handing out fresh labels.
+struct Flattener {
+	triples: Vec<Triple>,
+	fresh: usize
+}
+
+impl Flattener {
+	fn blank(&mut self) -> Term {
+		self.fresh += 1;
+		Term::Blank(format!("#{}", self.fresh))
+	}
+
+	fn describe(&mut self, subject: &Term, list: &[PredicateObjects]) {
+		for po in list {
+			let predicate = self.node(&po.verb.predicate);
+			for object in po.objects.iter() {
+				let object = self.node(object);
+				self.triples.push(match po.verb.inverse {
+					true => Triple { subject: object, predicate: predicate.clone(), object: subject.clone() },
+					false => Triple { subject: subject.clone(), predicate: predicate.clone(), object }
+				});
+			}
+		}
+	}
+
+	// The term standing for `term` in a triple.
+	fn node(&mut self, term: &Term) -> Term {
+		match *term {
+			Term::Collection(ref items) => {
+				let mut list = Term::Iri(RDF_NIL.to_string());
+				let cells: Vec<Term> = items.iter().map(|_| self.blank()).collect();
+				for (cell, item) in cells.iter().zip(items.iter()).rev() {
+					let first = self.node(item);
+					self.triples.push(Triple { subject: cell.clone(), predicate: Term::Iri(RDF_FIRST.to_string()), object: first });
+					self.triples.push(Triple { subject: cell.clone(), predicate: Term::Iri(RDF_REST.to_string()), object: list });
+					list = cell.clone();
+				}
+				list
+			}
+			Term::BlankNode(ref list) => {
+				let blank = self.blank();
+				self.describe(&blank, list);
+				blank
+			}
+			_ => term.clone()
+		}
+	}
+}
+
+impl Formula {
+	// What @forAll declares in this formula.
+	pub fn universals(&self) -> Vec<&Term> {
+		self.statements.iter().flat_map(|s| match *s {
+			Statement::ForAll(ref terms) => terms.iter().collect(),
+			_ => Vec::new()
+		}).collect()
+	}
+
+	// What @forSome declares in this formula.
+	pub fn existentials(&self) -> Vec<&Term> {
+		self.statements.iter().flat_map(|s| match *s {
+			Statement::ForSome(ref terms) => terms.iter().collect(),
+			_ => Vec::new()
+		}).collect()
+	}
+
+	// The statements as subject, predicate, object triples, in the order written. Nested
+	// formulas stay whole; their own triples() flattens them, and so do named graphs.
+	pub fn triples(&self) -> Vec<Triple> {
+		self.triples_with_spans().into_iter().map(|(triple, _)| triple).collect()
+	}
+
+	// triples(), each with the span of the statement it came from, or a default span
+	// for formulas built by hand.
+	pub fn triples_with_spans(&self) -> Vec<(Triple, Span)> {
+		let mut flattener = Flattener { triples: Vec::new(), fresh: 0 };
+		let mut spans = Vec::new();
+		for (index, statement) in self.statements.iter().enumerate() {
+			if let Statement::Triples(ref subject, ref list) = *statement {
+				let subject = flattener.node(subject);
+				flattener.describe(&subject, list);
+				let span = self.spans.get(index).cloned().unwrap_or_default();
+				spans.resize(flattener.triples.len(), span);
+			}
+		}
+		flattener.triples.into_iter().zip(spans).collect()
+	}
+
+	// The named graphs in this formula, each with its name, in the order written.
+	pub fn graphs(&self) -> Vec<(&Term, &Formula)> {
+		self.statements.iter().filter_map(|s| match *s {
+			Statement::Graph(ref name, ref graph) => Some((name, graph)),
+			_ => None
+		}).collect()
+	}
+
+	// The ?variables used anywhere in the formula, nested formulas included, first use first.
+	pub fn variables(&self) -> Vec<String> {
+		let mut names = Vec::new();
+		for statement in self.statements.iter() {
+			statement.variables(&mut names);
+		}
+		names
+	}
+
+	// The implications stated directly in this formula.
+	pub fn rules(&self) -> Vec<Rule> {
+		let universals: Vec<Term> = self.universals().into_iter().cloned().collect();
+		self.triples().into_iter().filter_map(|t| match (t.subject, t.object) {
+			(Term::Formula(body), Term::Formula(head)) if t.predicate.is_implication() => Some(Rule {
+				body,
+				head,
+				universals: universals.clone()
+			}),
+			_ => None
+		}).collect()
+	}
+}
+
+impl Document {
+	pub fn rules(&self) -> Vec<Rule> {
+		self.formula.rules()
+	}
+
+	pub fn triples(&self) -> Vec<Triple> {
+		self.formula.triples()
+	}
+
+	pub fn graphs(&self) -> Vec<(&Term, &Formula)> {
+		self.formula.graphs()
+	}
+
+	// (prefix, namespace) in the order declared.
+	pub fn prefixes(&self) -> Vec<(&str, &str)> {
+		self.formula.statements.iter().filter_map(|s| match *s {
+			Statement::Prefix(ref prefix, ref namespace) => Some((&prefix[..], &namespace[..])),
+			_ => None
+		}).collect()
+	}
+
+	// The namespace bound to `prefix`, the last binding winning.
+	pub fn namespace(&self, prefix: &str) -> Option<&str> {
+		self.prefixes().into_iter().rev().find(|p| p.0 == prefix).map(|p| p.1)
+	}
+
+	// The last @base, if any.
+	pub fn base(&self) -> Option<&str> {
+		self.formula.statements.iter().rev().filter_map(|s| match *s {
+			Statement::Base(ref base) => Some(&base[..]),
+			_ => None
+		}).next()
+	}
+}
+
+impl Rule {
+	pub fn is_universal(&self, term: &Term) -> bool {
+		match *term {
+			Term::Variable(_) => true,
+			_ => self.universals.contains(term)
+		}
+	}
+}
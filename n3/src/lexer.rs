@@ -0,0 +1,386 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Token {
+	// <http://example.org/x>
+	Iri(String),
+	// prefix:local, with an empty prefix for :local
+	Name(String, String),
+	// ?x
+	Variable(String),
+	// _:b
+	Blank(String),
+	// "text" or 'text', long or short, with an optional @language
+	Text(String, Option<String>),
+	Integer(i64),
+	Decimal(f64),
+	Boolean(bool),
+	// @prefix, @base, @forAll, @forSome without the @
+	Keyword(String),
+	// a, short for rdf:type
+	A,
+	OpenBrace,
+	CloseBrace,
+	OpenParen,
+	CloseParen,
+	OpenBracket,
+	CloseBracket,
+	// << and >> around a quoted triple
+	OpenQuoted,
+	CloseQuoted,
+	Dot,
+	Semicolon,
+	Comma,
+	Carets,
+	Implies,
+	ImpliedBy,
+	Equals
+}
+
+// Where a token or an error sits: byte offsets into the text, and the line and column
+// (both counted from 1, columns in characters) of its first character.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+	pub line: usize,
+	pub column: usize
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Error {
+	pub span: Span,
+	pub message: String
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line {}, column {}: {}", self.span.line, self.span.column, self.message)
+	}
+}
+
+pub struct Lexer<'t> {
+	text: &'t str,
+	chars: Peekable<CharIndices<'t>>,
+	line: usize,
+	column: usize,
+	// where the token being read began
+	start: Span
+}
+
+fn is_name_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+impl<'t> Lexer<'t> {
+	pub fn new(text: &'t str) -> Lexer<'t> {
+		Lexer { text, chars: text.char_indices().peekable(), line: 1, column: 1, start: Span::default() }
+	}
+
+	fn offset(&mut self) -> usize {
+		self.chars.peek().map_or(self.text.len(), |&(at, _)| at)
+	}
+
+	fn here(&mut self) -> Span {
+		let at = self.offset();
+		Span { start: at, end: at, line: self.line, column: self.column }
+	}
+
+	// From the start of the current token up to where the lexer is now.
+	fn span(&mut self) -> Span {
+		Span { end: self.offset(), ..self.start }
+	}
+
+	fn error<T>(&mut self, message: &str) -> Result<T, Error> {
+		Err(Error { span: self.span(), message: message.to_string() })
+	}
+
+	fn peek(&mut self) -> Option<char> {
+		self.chars.peek().map(|&(_, c)| c)
+	}
+
+	fn bump(&mut self) -> Option<char> {
+		let next = self.chars.next().map(|(_, c)| c);
+		match next {
+			Some('\n') => {
+				self.line += 1;
+				self.column = 1;
+			}
+			Some(_) => { self.column += 1; }
+			None => {}
+		}
+		next
+	}
+
+	fn bump_n(&mut self, n: usize) {
+		for _ in 0..n {
+			self.bump();
+		}
+	}
+
+	fn rest(&mut self) -> &'t str {
+		let at = self.offset();
+		&self.text[at..]
+	}
+
+	fn skip_space(&mut self) {
+		loop {
+			match self.peek() {
+				Some(c) if c.is_whitespace() => { self.bump(); }
+				Some('#') => {
+					while self.peek().is_some_and(|c| c != '\n') {
+						self.bump();
+					}
+				}
+				_ => { return; }
+			}
+		}
+	}
+
+	fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> String {
+		let mut taken = String::new();
+		while let Some(c) = self.peek() {
+			if !f(c) {
+				break;
+			}
+			taken.push(c);
+			self.bump();
+		}
+		taken
+	}
+
+	// A local name may contain dots, but not end with one: that dot ends the statement.
+	fn local_name(&mut self) -> String {
+		let rest = self.rest();
+		let end = rest.find(|c: char| !is_name_char(c) && c != '.').unwrap_or(rest.len());
+		let name = rest[..end].trim_end_matches('.');
+		self.bump_n(name.chars().count());
+		name.to_string()
+	}
+
+	fn escape(&mut self) -> Result<char, Error> {
+		let c = match self.bump() {
+			Some('n') => '\n',
+			Some('r') => '\r',
+			Some('t') => '\t',
+			Some('b') => '\u{8}',
+			Some('f') => '\u{c}',
+			Some(c @ '"') | Some(c @ '\'') | Some(c @ '\\') => c,
+			Some(u @ 'u') | Some(u @ 'U') => {
+				let digits = if u == 'u' { 4 } else { 8 };
+				let hex: String = (0..digits).filter_map(|_| self.bump()).collect();
+				match u32::from_str_radix(&hex, 16).ok().and_then(::std::char::from_u32) {
+					Some(c) => c,
+					None => { return self.error("bad unicode escape"); }
+				}
+			}
+			_ => { return self.error("bad escape sequence"); }
+		};
+		Ok(c)
+	}
+
+	// "short", 'short', """long""" or '''long'''; only long strings may span lines.
+	fn string(&mut self, quote: char) -> Result<String, Error> {
+		let triple: String = vec![quote; 3].into_iter().collect();
+		let long = self.rest().starts_with(&triple[..]);
+		self.bump_n(if long { 3 } else { 1 });
+		let mut value = String::new();
+		loop {
+			if long && self.rest().starts_with(&triple[..]) {
+				self.bump_n(3);
+				return Ok(value);
+			}
+			match self.bump() {
+				None => { return self.error("unterminated string"); }
+				Some(c) if c == quote && !long => { return Ok(value); }
+				Some('\n') if !long => { return self.error("line break in a short string"); }
+				Some('\\') => value.push(self.escape()?),
+				Some(c) => value.push(c)
+			}
+		}
+	}
+
+	fn number(&mut self) -> Result<Token, Error> {
+		let mut text = String::new();
+		if let Some(sign) = self.peek().filter(|&c| c == '-' || c == '+') {
+			text.push(sign);
+			self.bump();
+		}
+		text.push_str(&self.take_while(|c| c.is_ascii_digit()));
+		let mut decimal = false;
+		let rest = self.rest();
+		if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+			self.bump();
+			text.push('.');
+			text.push_str(&self.take_while(|c| c.is_ascii_digit()));
+			decimal = true;
+		}
+		if self.peek() == Some('e') || self.peek() == Some('E') {
+			text.push('e');
+			self.bump();
+			if let Some(sign) = self.peek().filter(|&c| c == '-' || c == '+') {
+				text.push(sign);
+				self.bump();
+			}
+			text.push_str(&self.take_while(|c| c.is_ascii_digit()));
+			decimal = true;
+		}
+		match decimal {
+			true => text.parse().map(Token::Decimal).or_else(|_| self.error(&format!("bad number {}", text))),
+			false => text.parse().map(Token::Integer).or_else(|_| self.error(&format!("bad number {}", text)))
+		}
+	}
+
+	// The next token and where it is, or None at the end of the text.
+	pub fn next_token(&mut self) -> Result<Option<(Token, Span)>, Error> {
+		self.skip_space();
+		self.start = self.here();
+		let rest = self.rest();
+		let c = match self.peek() {
+			Some(c) => c,
+			None => { return Ok(None); }
+		};
+		let starts_number = |at: usize| rest[at..].starts_with(|c: char| c.is_ascii_digit());
+		let token = match c {
+			'{' => { self.bump(); Token::OpenBrace }
+			'}' => { self.bump(); Token::CloseBrace }
+			'(' => { self.bump(); Token::OpenParen }
+			')' => { self.bump(); Token::CloseParen }
+			'[' => { self.bump(); Token::OpenBracket }
+			']' => { self.bump(); Token::CloseBracket }
+			'.' if starts_number(1) => self.number()?,
+			'.' => { self.bump(); Token::Dot }
+			';' => { self.bump(); Token::Semicolon }
+			',' => { self.bump(); Token::Comma }
+			'=' if rest.starts_with("=>") => { self.bump_n(2); Token::Implies }
+			'=' => { self.bump(); Token::Equals }
+			'^' if rest.starts_with("^^") => { self.bump_n(2); Token::Carets }
+			'<' if rest.starts_with("<<") => { self.bump_n(2); Token::OpenQuoted }
+			'>' if rest.starts_with(">>") => { self.bump_n(2); Token::CloseQuoted }
+			'<' if rest.starts_with("<=") && rest[2..].starts_with(|c: char| c.is_whitespace() || c == '{') => {
+				self.bump_n(2);
+				Token::ImpliedBy
+			}
+			'<' => {
+				self.bump();
+				let iri = self.take_while(|c| c != '>' && !c.is_whitespace());
+				if self.bump() != Some('>') {
+					return self.error("unterminated IRI");
+				}
+				Token::Iri(iri)
+			}
+			'"' | '\'' => {
+				let value = self.string(c)?;
+				let language = match self.peek() {
+					Some('@') => {
+						self.bump();
+						let language = self.take_while(is_name_char);
+						if language.is_empty() {
+							return self.error("empty language tag");
+						}
+						Some(language)
+					}
+					_ => None
+				};
+				Token::Text(value, language)
+			}
+			'?' => {
+				self.bump();
+				let name = self.take_while(is_name_char);
+				if name.is_empty() {
+					return self.error("variable without a name");
+				}
+				Token::Variable(name)
+			}
+			'_' if rest.starts_with("_:") => {
+				self.bump_n(2);
+				let label = self.local_name();
+				if label.is_empty() {
+					return self.error("blank node without a label");
+				}
+				Token::Blank(label)
+			}
+			'@' => {
+				self.bump();
+				let keyword = self.take_while(char::is_alphabetic);
+				match &keyword[..] {
+					"prefix" | "base" | "forAll" | "forSome" => Token::Keyword(keyword),
+					_ => { return self.error(&format!("unknown keyword @{}", keyword)); }
+				}
+			}
+			c if c.is_ascii_digit() || ((c == '-' || c == '+') && (starts_number(1) || (rest[1..].starts_with('.') && starts_number(2)))) => self.number()?,
+			c if is_name_char(c) || c == ':' => {
+				let prefix = self.take_while(is_name_char);
+				if self.peek() == Some(':') {
+					self.bump();
+					Token::Name(prefix, self.local_name())
+				} else {
+					match &prefix[..] {
+						"a" => Token::A,
+						"true" => Token::Boolean(true),
+						"false" => Token::Boolean(false),
+						_ => { return self.error(&format!("unexpected name {}", prefix)); }
+					}
+				}
+			}
+			c => {
+				self.bump();
+				return self.error(&format!("unexpected character '{}'", c));
+			}
+		};
+		Ok(Some((token, self.span())))
+	}
+
+	pub fn tokens(mut self) -> Result<Vec<(Token, Span)>, Error> {
+		let mut tokens = Vec::new();
+		while let Some(token) = self.next_token()? {
+			tokens.push(token);
+		}
+		Ok(tokens)
+	}
+}
+
+#[cfg(test)]
+fn kinds(text: &str) -> Vec<Token> {
+	Lexer::new(text).tokens().unwrap().into_iter().map(|(t, _)| t).collect()
+}
+
+#[test]
+fn splits_rules_into_tokens() {
+	assert_eq!(vec![
+		Token::Keyword("prefix".to_string()), Token::Name("".to_string(), "".to_string()), Token::Iri("urn:x#".to_string()), Token::Dot,
+		Token::OpenBrace, Token::Variable("a".to_string()), Token::Name("".to_string(), "p".to_string()),
+		Token::Text("b".to_string(), Some("en".to_string())), Token::CloseBrace, Token::Implies,
+		Token::OpenBrace, Token::Variable("a".to_string()), Token::Name("".to_string(), "q".to_string()),
+		Token::Decimal(1.5), Token::Comma, Token::Integer(-2), Token::CloseBrace, Token::Dot
+	], kinds("@prefix : <urn:x#> .\n{ ?a :p \"b\"@en } => { ?a :q 1.5, -2 } ."));
+}
+
+#[test]
+fn keeps_the_final_dot_out_of_names() {
+	let tokens = kinds(":a :b :c.d.");
+	assert_eq!(Token::Name("".to_string(), "c.d".to_string()), tokens[2]);
+	assert_eq!(Token::Dot, tokens[3]);
+}
+
+#[test]
+fn reads_collections_quotes_and_escapes() {
+	assert_eq!(vec![
+		Token::OpenBracket, Token::A, Token::Blank("b1".to_string()), Token::CloseBracket,
+		Token::OpenParen, Token::Boolean(true), Token::Decimal(0.5), Token::Decimal(-2e3), Token::CloseParen,
+		Token::Text("it's\n\"here\"".to_string(), None), Token::Text("é".to_string(), None)
+	], kinds("[ a _:b1 ] ( true .5 -2E3 ) '''it's\n\"here\"''' \"\\u00E9\""));
+}
+
+#[test]
+fn spans_cover_tokens_and_errors() {
+	let tokens = Lexer::new("<urn:a>\n  \"two\nlines\"").tokens();
+	assert_eq!(Span { start: 10, end: 15, line: 2, column: 3 }, tokens.unwrap_err().span);
+
+	let tokens = Lexer::new("#note\n :a\t<urn:b>").tokens().unwrap();
+	assert_eq!(Span { start: 7, end: 9, line: 2, column: 2 }, tokens[0].1);
+	assert_eq!(Span { start: 10, end: 17, line: 2, column: 5 }, tokens[1].1);
+	assert_eq!("line 1, column 7: unexpected character '!'", Lexer::new(":a :b !").tokens().unwrap_err().to_string());
+}
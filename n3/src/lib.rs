@@ -1,32 +1,128 @@
-struct Literal {
+use std::fmt;
+
+pub mod ast;
+pub mod lexer;
+pub mod names;
+pub mod namespace;
+pub mod parser;
+pub mod writer;
+
+pub use ast::{Document, Formula, PredicateObjects, Rule, Statement, Term, Triple, Verb};
+pub use lexer::{Error, Lexer, Span, Token};
+pub use names::{NameId, NameTree};
+pub use namespace::Namespaces;
+pub use parser::parse;
+
+// A name in a dotted hierarchy: `root.verb.past` is the child `past` of `root.verb`. A
+// prefixed name `ex:verb.past` is the chain root.ex.verb.past, the prefix as the first step.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Literal {
 	parent: Option<Box<Literal>>,
 	name: String
 }
 
+// A name's parent, grandparent and so on up to the root.
+pub struct Ancestors<'l> {
+	next: Option<&'l Literal>
+}
+
+impl<'l> Iterator for Ancestors<'l> {
+	type Item = &'l Literal;
+
+	fn next(&mut self) -> Option<&'l Literal> {
+		let current = self.next.take();
+		self.next = current.and_then(|l| l.parent());
+		current
+	}
+}
+
 impl Literal {
-	fn new(parent: Literal, name: &str) -> Literal {
+	pub fn new(parent: Literal, name: &str) -> Literal {
 		Literal { parent: Some(Box::new(parent)), name: name.to_string() }
 	}
 
-	fn root() -> Literal {
+	pub fn root() -> Literal {
 		Literal { parent: None, name: "".to_string() }
 	}
 
-    fn to_string (&self) -> String {
-		match self.parent {
-			Some(ref p) => p.to_string() + "." + &*self.name,
-			None => "root".to_string()
+	// `root.a.b` or just `a.b`, or a prefixed `ex:a.b`. None when a step is empty.
+	pub fn parse(text: &str) -> Option<Literal> {
+		let (prefix, path) = match text.find(':') {
+			Some(colon) => (Some(&text[..colon]), &text[colon + 1..]),
+			None => (None, text)
+		};
+		let mut steps: Vec<&str> = path.split('.').collect();
+		match prefix {
+			// the empty prefix adds no step, so `:a` is root.a
+			Some(prefix) if !prefix.is_empty() => steps.insert(0, prefix),
+			Some(_) => {}
+			None if steps[0] == "root" => { steps.remove(0); }
+			None => {}
+		}
+		if steps.iter().any(|step| step.is_empty() || step.contains(':')) {
+			return None;
+		}
+		Some(steps.into_iter().fold(Literal::root(), Literal::new))
+	}
+
+	pub fn child(&self, name: &str) -> Literal {
+		Literal::new(self.clone(), name)
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn parent(&self) -> Option<&Literal> {
+		self.parent.as_deref()
+	}
+
+	pub fn is_root(&self) -> bool {
+		self.parent.is_none()
+	}
+
+	pub fn ancestors<'l>(&'l self) -> Ancestors<'l> {
+		Ancestors { next: self.parent() }
+	}
+
+	// Steps below the root; the root itself has depth 0.
+	pub fn depth(&self) -> usize {
+		self.ancestors().count()
+	}
+
+	pub fn is_ancestor_of(&self, other: &Literal) -> bool {
+		other.ancestors().any(|a| a == self)
+	}
+
+	// The names of the steps from the root down, the root left out.
+	pub fn steps(&self) -> Vec<&str> {
+		let mut steps: Vec<&str> = self.ancestors().filter(|a| !a.is_root()).map(|a| a.name()).collect();
+		steps.reverse();
+		if !self.is_root() {
+			steps.push(&self.name);
 		}
+		steps
 	}
+}
 
+impl fmt::Display for Literal {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.parent {
+			Some(ref p) => write!(f, "{}.{}", p, self.name),
+			None => write!(f, "root")
+		}
+	}
 }
 
+// the two tests below are kept as first written, to show old callers still work
 #[test]
+#[allow(unused_variables)]
 fn can_create_root_literal() {
 	let root = Literal::root();
 }
 
 #[test]
+#[allow(clippy::to_string_in_format_args)]
 fn can_create_nested() {
 	let root = Literal::root();
 	let verb = Literal::new(root, "verb");
@@ -34,3 +130,35 @@ fn can_create_nested() {
 	println!("{}", verb.to_string());
 }
 
+#[test]
+fn writes_names_from_the_root() {
+	assert_eq!("root", Literal::root().to_string());
+	assert_eq!("root.verb", Literal::new(Literal::root(), "verb").to_string());
+	assert_eq!("root.verb.past", Literal::new(Literal::new(Literal::root(), "verb"), "past").to_string());
+}
+
+#[test]
+fn parses_names_back_into_chains() {
+	let past = Literal::parse("root.verb.past").unwrap();
+	assert_eq!(Literal::root().child("verb").child("past"), past);
+	assert_eq!(past, Literal::parse("verb.past").unwrap());
+	assert_eq!(Literal::parse("ex:verb.past").unwrap().to_string(), "root.ex.verb.past");
+	assert_eq!(Literal::parse(":verb").unwrap(), Literal::parse("verb").unwrap());
+	assert_eq!(Some(Literal::root()), Literal::parse("root"));
+	assert!(Literal::parse("verb..past").is_none());
+	assert!(Literal::parse("a:b:c").is_none());
+}
+
+#[test]
+fn walks_ancestors() {
+	let past = Literal::parse("verb.past").unwrap();
+	let verb = past.parent().unwrap().clone();
+	assert_eq!(vec!["root.verb", "root"], past.ancestors().map(|a| a.to_string()).collect::<Vec<_>>());
+	assert_eq!(2, past.depth());
+	assert_eq!(vec!["verb", "past"], past.steps());
+	assert!(verb.is_ancestor_of(&past));
+	assert!(Literal::root().is_ancestor_of(&past));
+	assert!(!past.is_ancestor_of(&verb));
+	assert!(!past.is_ancestor_of(&past));
+	assert!(!Literal::parse("noun").unwrap().is_ancestor_of(&past));
+}
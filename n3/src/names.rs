@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use Literal;
+
+// A node of a NameTree. Ids are handed out in insertion order and never reused, so one
+// stays valid, and means the same name, for as long as its tree lives.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct NameId(u32);
+
+impl NameId {
+	pub fn index(self) -> usize {
+		self.0 as usize
+	}
+}
+
+// Fact subjects are i64s; a name used as a subject is its id.
+impl From<NameId> for i64 {
+	fn from(id: NameId) -> i64 {
+		id.0 as i64
+	}
+}
+
+struct Node {
+	parent: Option<NameId>,
+	name: String,
+	// `root.a.b`, built once when the node is made
+	full: String,
+	depth: usize,
+	children: Vec<NameId>
+}
+
+// Every name once: children share their parent's node, and a full name leads straight to
+// its node. Nodes are only ever added.
+pub struct NameTree {
+	nodes: Vec<Node>,
+	by_full: HashMap<String, NameId>
+}
+
+// A name's parent, grandparent and so on up to the root.
+pub struct TreeAncestors<'t> {
+	tree: &'t NameTree,
+	next: Option<NameId>
+}
+
+impl<'t> Iterator for TreeAncestors<'t> {
+	type Item = NameId;
+
+	fn next(&mut self) -> Option<NameId> {
+		let current = self.next.take();
+		self.next = current.and_then(|id| self.tree.parent(id));
+		current
+	}
+}
+
+impl Default for NameTree {
+	fn default() -> NameTree {
+		NameTree::new()
+	}
+}
+
+impl NameTree {
+	pub fn new() -> NameTree {
+		let root = Node { parent: None, name: "".to_string(), full: "root".to_string(), depth: 0, children: Vec::new() };
+		let mut by_full = HashMap::new();
+		by_full.insert(root.full.clone(), NameId(0));
+		NameTree { nodes: vec![root], by_full }
+	}
+
+	pub fn root(&self) -> NameId {
+		NameId(0)
+	}
+
+	fn node(&self, id: NameId) -> &Node {
+		&self.nodes[id.index()]
+	}
+
+	// The child `name` of `parent`, made if it is not there yet. A dotted name walks down
+	// one step per part; empty parts are skipped.
+	pub fn child(&mut self, parent: NameId, name: &str) -> NameId {
+		let mut at = parent;
+		for step in name.split('.').filter(|step| !step.is_empty()) {
+			let full = format!("{}.{}", self.node(at).full, step);
+			if let Some(&id) = self.by_full.get(&full) {
+				at = id;
+				continue;
+			}
+			let id = NameId(self.nodes.len() as u32);
+			let depth = self.node(at).depth + 1;
+			self.nodes.push(Node { parent: Some(at), name: step.to_string(), full: full.clone(), depth, children: Vec::new() });
+			self.nodes[at.index()].children.push(id);
+			self.by_full.insert(full, id);
+			at = id;
+		}
+		at
+	}
+
+	// Makes every name along `literal` and gives the last.
+	pub fn insert(&mut self, literal: &Literal) -> NameId {
+		let root = self.root();
+		literal.steps().iter().fold(root, |at, step| self.child(at, step))
+	}
+
+	// Makes the name written as `root.a.b`, `a.b` or `ex:a.b`, as Literal::parse reads it.
+	pub fn insert_path(&mut self, path: &str) -> Option<NameId> {
+		Literal::parse(path).map(|literal| self.insert(&literal))
+	}
+
+	pub fn find_child(&self, parent: NameId, name: &str) -> Option<NameId> {
+		self.by_full.get(&format!("{}.{}", self.node(parent).full, name)).cloned()
+	}
+
+	// The name written as `root.a.b`, `a.b` or `ex:a.b`, if it has been made.
+	pub fn lookup(&self, path: &str) -> Option<NameId> {
+		Literal::parse(path).and_then(|literal| self.by_full.get(&literal.to_string()).cloned())
+	}
+
+	// The node with this index, for ids kept elsewhere as numbers.
+	pub fn get(&self, index: usize) -> Option<NameId> {
+		match index < self.nodes.len() {
+			true => Some(NameId(index as u32)),
+			false => None
+		}
+	}
+
+	pub fn name(&self, id: NameId) -> &str {
+		&self.node(id).name
+	}
+
+	pub fn full_name(&self, id: NameId) -> &str {
+		&self.node(id).full
+	}
+
+	pub fn parent(&self, id: NameId) -> Option<NameId> {
+		self.node(id).parent
+	}
+
+	pub fn depth(&self, id: NameId) -> usize {
+		self.node(id).depth
+	}
+
+	// In the order they were made.
+	pub fn children(&self, id: NameId) -> &[NameId] {
+		&self.node(id).children
+	}
+
+	pub fn ancestors<'t>(&'t self, id: NameId) -> TreeAncestors<'t> {
+		TreeAncestors { tree: self, next: self.parent(id) }
+	}
+
+	pub fn is_ancestor_of(&self, ancestor: NameId, id: NameId) -> bool {
+		self.depth(ancestor) < self.depth(id) && self.ancestors(id).any(|a| a == ancestor)
+	}
+
+	// `id` and everything below it, parents before their children.
+	pub fn descendants(&self, id: NameId) -> Vec<NameId> {
+		let mut found = vec![id];
+		let mut at = 0;
+		while at < found.len() {
+			found.extend_from_slice(self.children(found[at]));
+			at += 1;
+		}
+		found
+	}
+
+	pub fn literal(&self, id: NameId) -> Literal {
+		match self.parent(id) {
+			Some(parent) => Literal::new(self.literal(parent), self.name(id)),
+			None => Literal::root()
+		}
+	}
+
+	// Names made so far, the root included.
+	pub fn len(&self) -> usize {
+		self.nodes.len()
+	}
+
+	// Never true: the root is always there.
+	pub fn is_empty(&self) -> bool {
+		self.nodes.is_empty()
+	}
+}
+
+#[test]
+fn shares_parents_and_finds_children() {
+	let mut tree = NameTree::new();
+	let root = tree.root();
+	let position = tree.child(root, "position");
+	let dist = tree.child(position, "dist");
+	let offset = tree.insert_path("position.offset").unwrap();
+	let node = tree.insert_path("root.link.node").unwrap();
+
+	assert_eq!(position, tree.child(root, "position"));
+	assert_eq!(dist, tree.insert(&Literal::parse("position.dist").unwrap()));
+	assert_eq!(6, tree.len());
+	assert_eq!(&[dist, offset], tree.children(position));
+	assert_eq!(Some(offset), tree.find_child(position, "offset"));
+	assert_eq!(Some(node), tree.lookup("link.node"));
+	assert_eq!(None, tree.lookup("link.document"));
+	assert_eq!("root.position.offset", tree.full_name(offset));
+	assert_eq!("offset", tree.name(offset));
+	assert_eq!(Literal::parse("link.node").unwrap(), tree.literal(node));
+	assert_eq!(Some(dist), tree.get(i64::from(dist) as usize));
+}
+
+#[test]
+fn walks_up_and_down() {
+	let mut tree = NameTree::new();
+	let dist = tree.insert_path("position.dist").unwrap();
+	let offset = tree.insert_path("position.offset").unwrap();
+	let node = tree.insert_path("link.node").unwrap();
+	let position = tree.parent(dist).unwrap();
+
+	assert_eq!(vec![position, tree.root()], tree.ancestors(dist).collect::<Vec<_>>());
+	assert_eq!(2, tree.depth(offset));
+	assert!(tree.is_ancestor_of(position, offset));
+	assert!(tree.is_ancestor_of(tree.root(), node));
+	assert!(!tree.is_ancestor_of(position, node));
+	assert!(!tree.is_ancestor_of(dist, dist));
+	assert_eq!(vec![position, dist, offset], tree.descendants(position));
+}
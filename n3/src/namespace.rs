@@ -0,0 +1,271 @@
+use ast::{Document, Statement, Term};
+use Literal;
+
+// Prefixes and a base IRI, as a document declares them, for turning names into full IRIs
+// and back.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Namespaces {
+	prefixes: Vec<(String, String)>,
+	base: Option<String>
+}
+
+// The parts of an IRI reference, as RFC 3986 splits them.
+struct Parts<'i> {
+	scheme: Option<&'i str>,
+	authority: Option<&'i str>,
+	path: &'i str,
+	query: Option<&'i str>,
+	fragment: Option<&'i str>
+}
+
+fn split_at(text: &str, mark: char) -> (&str, Option<&str>) {
+	match text.find(mark) {
+		Some(at) => (&text[..at], Some(&text[at + 1..])),
+		None => (text, None)
+	}
+}
+
+fn parts<'i>(iri: &'i str) -> Parts<'i> {
+	let (rest, fragment) = split_at(iri, '#');
+	let (mut rest, query) = split_at(rest, '?');
+	let scheme = match rest.find(':') {
+		Some(colon) if rest[..colon].starts_with(|c: char| c.is_ascii_alphabetic())
+			&& rest[..colon].chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') => {
+			let scheme = &rest[..colon];
+			rest = &rest[colon + 1..];
+			Some(scheme)
+		}
+		_ => None
+	};
+	let mut authority = None;
+	if rest.starts_with("//") {
+		let end = rest[2..].find('/').map_or(rest.len(), |at| at + 2);
+		authority = Some(&rest[2..end]);
+		rest = &rest[end..];
+	}
+	Parts { scheme, authority, path: rest, query, fragment }
+}
+
+// RFC 3986 section 5.2.4: drops `.` steps and lets `..` take back the step before it.
+fn remove_dots(path: &str) -> String {
+	let mut output: Vec<&str> = Vec::new();
+	let mut input = path;
+	while !input.is_empty() {
+		if input.starts_with("../") {
+			input = &input[3..];
+		} else if input.starts_with("./") || input.starts_with("/./") {
+			input = &input[2..];
+		} else if input == "/." {
+			input = "/";
+		} else if input.starts_with("/../") || input == "/.." {
+			input = if input == "/.." { "/" } else { &input[3..] };
+			output.pop();
+		} else if input == "." || input == ".." {
+			input = "";
+		} else {
+			let skip = if input.starts_with('/') { 1 } else { 0 };
+			let end = input[skip..].find('/').map_or(input.len(), |at| at + skip);
+			output.push(&input[..end]);
+			input = &input[end..];
+		}
+	}
+	output.concat()
+}
+
+fn compose(scheme: Option<&str>, authority: Option<&str>, path: &str, query: Option<&str>, fragment: Option<&str>) -> String {
+	let mut iri = String::new();
+	if let Some(scheme) = scheme {
+		iri.push_str(scheme);
+		iri.push(':');
+	}
+	if let Some(authority) = authority {
+		iri.push_str("//");
+		iri.push_str(authority);
+	}
+	iri.push_str(path);
+	if let Some(query) = query {
+		iri.push('?');
+		iri.push_str(query);
+	}
+	if let Some(fragment) = fragment {
+		iri.push('#');
+		iri.push_str(fragment);
+	}
+	iri
+}
+
+// What may follow the colon of a prefixed name: name characters and inner dots.
+fn is_local_name(local: &str) -> bool {
+	local.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+		&& !local.starts_with('.') && !local.ends_with('.') && !local.contains("..")
+}
+
+impl Namespaces {
+	pub fn new() -> Namespaces {
+		Namespaces::default()
+	}
+
+	// The @base and @prefix declarations of `document`, each IRI resolved against the base
+	// in effect where it is written.
+	pub fn from_document(document: &Document) -> Namespaces {
+		let mut namespaces = Namespaces::new();
+		for statement in document.formula.statements.iter() {
+			match *statement {
+				Statement::Base(ref base) => namespaces.set_base(base),
+				Statement::Prefix(ref prefix, ref namespace) => namespaces.register(prefix, namespace),
+				_ => {}
+			}
+		}
+		namespaces
+	}
+
+	pub fn base(&self) -> Option<&str> {
+		self.base.as_ref().map(|b| &b[..])
+	}
+
+	// A relative base is taken relative to the current one.
+	pub fn set_base(&mut self, base: &str) {
+		self.base = Some(self.resolve(base));
+	}
+
+	// Binds `prefix`, replacing what it was bound to before.
+	pub fn register(&mut self, prefix: &str, namespace: &str) {
+		let namespace = self.resolve(namespace);
+		match self.prefixes.iter_mut().find(|p| p.0 == prefix) {
+			Some(binding) => { binding.1 = namespace; }
+			None => self.prefixes.push((prefix.to_string(), namespace))
+		}
+	}
+
+	pub fn namespace(&self, prefix: &str) -> Option<&str> {
+		self.prefixes.iter().find(|p| p.0 == prefix).map(|p| &p.1[..])
+	}
+
+	pub fn prefixes(&self) -> &[(String, String)] {
+		&self.prefixes
+	}
+
+	// Resolves `reference` against the base as RFC 3986 section 5.2 does. Without a base a
+	// relative reference comes back as it is.
+	pub fn resolve(&self, reference: &str) -> String {
+		let r = parts(reference);
+		let base = match self.base {
+			Some(ref base) if r.scheme.is_none() => parts(base),
+			_ => { return compose(r.scheme, r.authority, &remove_dots(r.path), r.query, r.fragment); }
+		};
+		if r.authority.is_some() {
+			return compose(base.scheme, r.authority, &remove_dots(r.path), r.query, r.fragment);
+		}
+		if r.path.is_empty() {
+			return compose(base.scheme, base.authority, base.path, r.query.or(base.query), r.fragment);
+		}
+		let path = match r.path.starts_with('/') {
+			true => remove_dots(r.path),
+			false if base.authority.is_some() && base.path.is_empty() => remove_dots(&format!("/{}", r.path)),
+			false => {
+				let directory = base.path.rfind('/').map_or("", |at| &base.path[..at + 1]);
+				remove_dots(&format!("{}{}", directory, r.path))
+			}
+		};
+		compose(base.scheme, base.authority, &path, r.query, r.fragment)
+	}
+
+	// `ex:local` through its prefix or `<iri>` through the base. None for an unbound prefix.
+	pub fn expand(&self, name: &str) -> Option<String> {
+		if name.starts_with('<') && name.ends_with('>') && name.len() > 1 {
+			return Some(self.resolve(&name[1..name.len() - 1]));
+		}
+		let (prefix, local) = split_at(name, ':');
+		self.namespace(prefix).and_then(|namespace| local.map(|local| format!("{}{}", namespace, local)))
+	}
+
+	// The IRI an IRI or prefixed-name term stands for.
+	pub fn term(&self, term: &Term) -> Option<String> {
+		match *term {
+			Term::Iri(ref iri) => Some(self.resolve(iri)),
+			Term::Name(ref prefix, ref local) => self.namespace(prefix).map(|namespace| format!("{}{}", namespace, local)),
+			_ => None
+		}
+	}
+
+	// The shortest prefixed name for `iri`, or `<iri>` when no namespace covers it.
+	pub fn compact(&self, iri: &str) -> String {
+		let best = self.prefixes.iter()
+			.filter(|p| iri.starts_with(&p.1[..]) && is_local_name(&iri[p.1.len()..]))
+			.max_by_key(|p| p.1.len());
+		match best {
+			Some((prefix, namespace)) => format!("{}:{}", prefix, &iri[namespace.len()..]),
+			None => format!("<{}>", iri)
+		}
+	}
+
+	// `iri` as a name chain: the prefix, then the dotted steps of the local name.
+	pub fn literal(&self, iri: &str) -> Option<Literal> {
+		let name = self.compact(iri);
+		match name.starts_with('<') {
+			true => None,
+			false => Literal::parse(&name)
+		}
+	}
+
+	// The IRI of a name chain. The first step is taken as a prefix when one is bound to it,
+	// else the whole chain is a local name in the empty prefix's namespace.
+	pub fn iri(&self, literal: &Literal) -> Option<String> {
+		let steps = literal.steps();
+		if steps.is_empty() {
+			return None;
+		}
+		match self.namespace(steps[0]) {
+			Some(namespace) if !steps[0].is_empty() && steps.len() > 1 => Some(format!("{}{}", namespace, steps[1..].join("."))),
+			_ => self.namespace("").map(|namespace| format!("{}{}", namespace, steps.join(".")))
+		}
+	}
+}
+
+#[test]
+fn resolves_references_as_rfc_3986_does() {
+	let mut namespaces = Namespaces::new();
+	assert_eq!("g;x", namespaces.resolve("g;x"));
+	namespaces.set_base("http://a/b/c/d;p?q");
+	for &(reference, resolved) in [
+		("g:h", "g:h"), ("g", "http://a/b/c/g"), ("./g", "http://a/b/c/g"), ("g/", "http://a/b/c/g/"),
+		("/g", "http://a/g"), ("//g", "http://g"), ("?y", "http://a/b/c/d;p?y"), ("#s", "http://a/b/c/d;p?q#s"),
+		("", "http://a/b/c/d;p?q"), (".", "http://a/b/c/"), ("..", "http://a/b/"), ("../g", "http://a/b/g"),
+		("../../../g", "http://a/g"), ("/./g", "http://a/g"), ("g.", "http://a/b/c/g."), ("./g/.", "http://a/b/c/g/"),
+		("g;x=1/../y", "http://a/b/c/y")
+	].iter() {
+		assert_eq!(resolved, namespaces.resolve(reference), "{}", reference);
+	}
+}
+
+#[test]
+fn expands_and_compacts_names() {
+	let document = ::parse("@base <http://ex.org/data/> . @prefix : <vocab#> . @prefix w: <http://ex.org/data/vocab#word/> .").unwrap();
+	let namespaces = Namespaces::from_document(&document);
+	assert_eq!(Some("http://ex.org/data/vocab#"), namespaces.namespace(""));
+
+	assert_eq!(Some("http://ex.org/data/vocab#follows".to_string()), namespaces.expand(":follows"));
+	assert_eq!(Some("http://ex.org/data/x".to_string()), namespaces.expand("<x>"));
+	assert_eq!(None, namespaces.expand("nope:x"));
+	assert_eq!(Some("http://ex.org/data/vocab#dist".to_string()), namespaces.term(&Term::Name("".to_string(), "dist".to_string())));
+
+	assert_eq!("w:cat", namespaces.compact("http://ex.org/data/vocab#word/cat"));
+	assert_eq!(":link.node", namespaces.compact("http://ex.org/data/vocab#link.node"));
+	assert_eq!("<http://ex.org/other>", namespaces.compact("http://ex.org/other"));
+}
+
+#[test]
+fn maps_name_chains_to_iris() {
+	let mut namespaces = Namespaces::new();
+	namespaces.register("", "urn:search:");
+	namespaces.register("w", "urn:word:");
+
+	let node = namespaces.literal("urn:search:link.node").unwrap();
+	assert_eq!("root.link.node", node.to_string());
+	assert_eq!(Some("urn:search:link.node".to_string()), namespaces.iri(&node));
+	let cat = namespaces.literal("urn:word:cat").unwrap();
+	assert_eq!(Some("urn:word:cat".to_string()), namespaces.iri(&cat));
+	assert!(Literal::parse("link").unwrap().is_ancestor_of(&node));
+	assert_eq!(None, namespaces.literal("urn:other:x"));
+	assert_eq!(None, namespaces.iri(&Literal::root()));
+}
@@ -0,0 +1,384 @@
+use ast::{Document, Formula, PredicateObjects, Statement, Term, Triple, Verb, OWL_SAME_AS, RDF_TYPE, LOG_IMPLIES};
+use lexer::{Error, Lexer, Span, Token};
+
+struct Parser {
+	tokens: Vec<(Token, Span)>,
+	at: usize,
+	// where the input ends, for errors past the last token
+	end: Span
+}
+
+impl Parser {
+	fn span(&self) -> Span {
+		self.tokens.get(self.at).map_or(self.end, |t| t.1)
+	}
+
+	fn error<T>(&self, message: &str) -> Result<T, Error> {
+		Err(Error { span: self.span(), message: message.to_string() })
+	}
+
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.at).map(|t| &t.0)
+	}
+
+	fn next(&mut self) -> Option<Token> {
+		let token = self.tokens.get(self.at).map(|t| t.0.clone());
+		self.at += 1;
+		token
+	}
+
+	fn eat(&mut self, token: &Token) -> bool {
+		if self.peek() == Some(token) {
+			self.at += 1;
+			return true;
+		}
+		false
+	}
+
+	fn term(&mut self) -> Result<Term, Error> {
+		let token = match self.next() {
+			Some(token) => token,
+			None => {
+				self.at -= 1;
+				return self.error("unexpected end of input");
+			}
+		};
+		let term = match token {
+			Token::Iri(iri) => Term::Iri(iri),
+			Token::Name(prefix, local) => Term::Name(prefix, local),
+			Token::Variable(name) => Term::Variable(name),
+			Token::Blank(name) => Term::Blank(name),
+			Token::Integer(i) => Term::Integer(i),
+			Token::Decimal(d) => Term::Decimal(d),
+			Token::Boolean(b) => Term::Boolean(b),
+			Token::Text(value, language) => {
+				if language.is_none() && self.eat(&Token::Carets) {
+					let datatype = self.term()?;
+					match datatype {
+						Term::Iri(_) | Term::Name(_, _) => {}
+						_ => {
+							self.at -= 1;
+							return self.error("a datatype must be an IRI");
+						}
+					}
+					return Ok(Term::Typed(value, Box::new(datatype)));
+				}
+				Term::Text(value, language)
+			}
+			Token::OpenParen => {
+				let mut items = Vec::new();
+				while !self.eat(&Token::CloseParen) {
+					items.push(self.term()?);
+				}
+				Term::Collection(items)
+			}
+			Token::OpenBracket => {
+				if self.eat(&Token::CloseBracket) {
+					return Ok(Term::BlankNode(Vec::new()));
+				}
+				let list = self.predicate_objects()?;
+				if !self.eat(&Token::CloseBracket) {
+					return self.error("expected ']'");
+				}
+				Term::BlankNode(list)
+			}
+			Token::OpenBrace => Term::Formula(self.formula(true)?),
+			Token::OpenQuoted => {
+				let subject = self.quoted_term()?;
+				let predicate = self.verb()?;
+				if predicate.inverse {
+					self.at -= 1;
+					return self.error("a quoted triple cannot use '<='");
+				}
+				let object = self.quoted_term()?;
+				if !self.eat(&Token::CloseQuoted) {
+					return self.error("expected '>>'");
+				}
+				Term::Quoted(Box::new(Triple { subject, predicate: predicate.predicate, object }))
+			}
+			_ => {
+				self.at -= 1;
+				return self.error("expected a term");
+			}
+		};
+		Ok(term)
+	}
+
+	// A term inside `<< >>`: collections, blank node descriptions and formulas would need
+	// triples of their own, so only plain terms and other quoted triples are allowed.
+	fn quoted_term(&mut self) -> Result<Term, Error> {
+		let start = self.at;
+		let term = self.term()?;
+		match term {
+			Term::Collection(_) | Term::BlankNode(_) | Term::Formula(_) => {
+				self.at = start;
+				self.error("a quoted triple holds only plain terms")
+			}
+			_ => Ok(term)
+		}
+	}
+
+	fn verb(&mut self) -> Result<Verb, Error> {
+		let (predicate, inverse) = match self.peek() {
+			Some(&Token::A) => (RDF_TYPE, false),
+			Some(&Token::Implies) => (LOG_IMPLIES, false),
+			Some(&Token::ImpliedBy) => (LOG_IMPLIES, true),
+			Some(&Token::Equals) => (OWL_SAME_AS, false),
+			_ => { return Ok(Verb { predicate: self.term()?, inverse: false }); }
+		};
+		self.at += 1;
+		Ok(Verb { predicate: Term::Iri(predicate.to_string()), inverse })
+	}
+
+	// verb object (, object)* (; verb object (, object)*)*
+	fn predicate_objects(&mut self) -> Result<Vec<PredicateObjects>, Error> {
+		let mut list = Vec::new();
+		loop {
+			let verb = self.verb()?;
+			let mut objects = vec![self.term()?];
+			while self.eat(&Token::Comma) {
+				objects.push(self.term()?);
+			}
+			list.push(PredicateObjects { verb, objects });
+			if !self.eat(&Token::Semicolon) {
+				return Ok(list);
+			}
+			while self.eat(&Token::Semicolon) {}
+			// a trailing `;` is allowed
+			match self.peek() {
+				Some(&Token::Dot) | Some(&Token::CloseBrace) | Some(&Token::CloseBracket) | None => { return Ok(list); }
+				_ => {}
+			}
+		}
+	}
+
+	fn statement(&mut self) -> Result<Statement, Error> {
+		let subject = self.term()?;
+		let bare = match subject {
+			Term::BlankNode(ref list) => !list.is_empty(),
+			_ => false
+		};
+		match self.peek() {
+			Some(&Token::Dot) | Some(&Token::CloseBrace) | None if bare => Ok(Statement::Triples(subject, Vec::new())),
+			_ => Ok(Statement::Triples(subject, self.predicate_objects()?))
+		}
+	}
+
+	fn quantified(&mut self) -> Result<Vec<Term>, Error> {
+		let mut terms = vec![self.term()?];
+		while self.eat(&Token::Comma) {
+			terms.push(self.term()?);
+		}
+		Ok(terms)
+	}
+
+	fn iri(&mut self, what: &str) -> Result<String, Error> {
+		match self.next() {
+			Some(Token::Iri(iri)) => Ok(iri),
+			_ => {
+				self.at -= 1;
+				self.error(&format!("expected {}", what))
+			}
+		}
+	}
+
+	fn directive(&mut self, keyword: &str) -> Result<Statement, Error> {
+		match keyword {
+			"forAll" => Ok(Statement::ForAll(self.quantified()?)),
+			"forSome" => Ok(Statement::ForSome(self.quantified()?)),
+			"base" => Ok(Statement::Base(self.iri("a base IRI")?)),
+			_ => {
+				let prefix = match self.next() {
+					Some(Token::Name(ref prefix, ref local)) if local.is_empty() => prefix.clone(),
+					_ => {
+						self.at -= 1;
+						return self.error("expected a prefix like ex:");
+					}
+				};
+				Ok(Statement::Prefix(prefix, self.iri("a namespace IRI")?))
+			}
+		}
+	}
+
+	// Whether a named graph starts here: an IRI, name or blank node right before '{'.
+	fn at_graph(&self) -> bool {
+		let name = matches!(self.peek(), Some(&Token::Iri(_)) | Some(&Token::Name(_, _)) | Some(&Token::Blank(_)));
+		name && self.tokens.get(self.at + 1).map(|t| &t.0) == Some(&Token::OpenBrace)
+	}
+
+	// Statements up to the closing brace when `nested`, else to the end of the input. The
+	// dot after the last statement of a quoted formula may be left out.
+	fn formula(&mut self, nested: bool) -> Result<Formula, Error> {
+		let mut formula = Formula::default();
+		loop {
+			let span = self.span();
+			let statement = match self.peek().cloned() {
+				None if nested => { return self.error("unterminated '{'"); }
+				None => { return Ok(formula); }
+				Some(Token::CloseBrace) if nested => {
+					self.at += 1;
+					return Ok(formula);
+				}
+				Some(Token::Keyword(ref keyword)) if nested && (keyword == "prefix" || keyword == "base") => {
+					return self.error(&format!("@{} is only allowed at the top level", keyword));
+				}
+				Some(Token::Keyword(ref keyword)) => {
+					self.at += 1;
+					self.directive(keyword)?
+				}
+				_ if !nested && self.at_graph() => {
+					let name = self.term()?;
+					self.at += 1;
+					Statement::Graph(name, self.formula(true)?)
+				}
+				_ => self.statement()?
+			};
+			let graph = matches!(statement, Statement::Graph(_, _));
+			formula.statements.push(statement);
+			formula.spans.push(span);
+			// TriG ends a graph with its brace alone
+			if !self.eat(&Token::Dot) && !graph {
+				match self.peek() {
+					Some(&Token::CloseBrace) if nested => {}
+					_ => { return self.error("expected '.'"); }
+				}
+			}
+		}
+	}
+}
+
+pub fn parse(text: &str) -> Result<Document, Error> {
+	let tokens = Lexer::new(text).tokens()?;
+	let last_line = &text[text.rfind('\n').map_or(0, |at| at + 1)..];
+	let end = Span { start: text.len(), end: text.len(), line: text.matches('\n').count() + 1, column: last_line.chars().count() + 1 };
+	let mut parser = Parser { tokens, at: 0, end };
+	Ok(Document { formula: parser.formula(false)? })
+}
+
+#[test]
+fn parses_rules_with_quantifiers() {
+	let document = parse("
+		@prefix : <urn:search:> .
+		@forAll :x .
+		{ :x :node ?i . ?b :node ?i ; :dist 1 } => { :x :follows ?b } .
+		{ ?a :follows ?b } <= { ?b :precedes ?a } .
+	").unwrap();
+
+	assert_eq!(Some("urn:search:"), document.namespace(""));
+	let rules = document.rules();
+	assert_eq!(2, rules.len());
+	assert_eq!(3, rules[0].body.triples().len());
+	assert!(rules[0].is_universal(&Term::Name("".to_string(), "x".to_string())));
+	assert_eq!(vec!["i".to_string(), "b".to_string()], rules[0].body.variables());
+	assert_eq!(Term::Name("".to_string(), "precedes".to_string()), rules[1].body.triples()[0].predicate);
+}
+
+#[test]
+fn parses_existentials_literals_and_nesting() {
+	let document = parse("
+		@prefix x: <urn:x#> .
+		x:doc x:said { @forSome x:w . x:w x:is \"late\"@en, 2.5, true } ; a x:Claim .
+		x:doc x:on \"2020-01-01\"^^x:date .
+	").unwrap();
+
+	let triples = document.triples();
+	match triples[0].object {
+		Term::Formula(ref quoted) => {
+			assert_eq!(1, quoted.existentials().len());
+			assert_eq!(3, quoted.triples().len());
+			assert_eq!(Term::Text("late".to_string(), Some("en".to_string())), quoted.triples()[0].object);
+		}
+		_ => panic!("object is not a formula")
+	}
+	assert_eq!(Term::Iri(RDF_TYPE.to_string()), triples[1].predicate);
+	assert_eq!(Term::Typed("2020-01-01".to_string(), Box::new(Term::Name("x".to_string(), "date".to_string()))), triples[2].object);
+	assert!(document.rules().is_empty());
+}
+
+#[test]
+fn parses_collections_and_blank_node_descriptions() {
+	let document = parse("
+		@base <urn:base/> .
+		@prefix : <urn:x#> .
+		:list :items ( 1 [ :p :q ] ) .
+		[ :name 'anon' ; ] .
+		[] :knows :me .
+	").unwrap();
+
+	assert_eq!(Some("urn:base/"), document.base());
+	assert_eq!(vec![("", "urn:x#")], document.prefixes());
+	match document.formula.statements[2] {
+		Statement::Triples(_, ref list) => match list[0].objects[0] {
+			Term::Collection(ref items) => assert_eq!(2, items.len()),
+			_ => panic!("object is not a collection")
+		},
+		_ => panic!("not a triples statement")
+	}
+	match document.formula.statements[3] {
+		Statement::Triples(Term::BlankNode(ref list), ref rest) => {
+			assert_eq!(1, list.len());
+			assert!(rest.is_empty());
+		}
+		_ => panic!("not a described blank node")
+	}
+
+	// two list cells with a first and a rest each, the [ :p :q ] inside, the :items triple,
+	// the :name triple and the :knows triple
+	let triples = document.triples();
+	assert_eq!(8, triples.len());
+	let items = triples.iter().find(|t| t.predicate == Term::Name("".to_string(), "items".to_string())).unwrap();
+	let first = triples.iter().find(|t| t.subject == items.object && t.predicate == Term::Iri(::ast::RDF_FIRST.to_string())).unwrap();
+	assert_eq!(Term::Integer(1), first.object);
+	assert!(triples.iter().any(|t| t.predicate == Term::Iri(::ast::RDF_REST.to_string()) && t.object == Term::Iri(::ast::RDF_NIL.to_string())));
+}
+
+#[test]
+fn reports_where_errors_are() {
+	let err = parse("@prefix : <urn:x#> .\n:a :b :c .\n{ :a :b :c } => { :a :b }").unwrap_err();
+	assert_eq!((3, 25), (err.span.line, err.span.column));
+	let err = parse("{ :a :b :c } => { :a :b :c ").unwrap_err();
+	assert_eq!((1, 28), (err.span.line, err.span.column));
+	let err = parse(":a :b ( :c ] .").unwrap_err();
+	assert_eq!(Span { start: 11, end: 12, line: 1, column: 12 }, err.span);
+	assert!(parse("{ @prefix : <urn:y#> . }").is_err());
+	assert!(parse("[ :p :q ").is_err());
+}
+
+#[test]
+fn parses_named_graphs_at_the_top_level() {
+	let document = parse("
+		@prefix : <urn:x#> .
+		:a :b :c .
+		:g { :d :e :f . :h :i [ :j :k ] }
+		<urn:y> { }
+		:l :m { :n :o :p } .
+	").unwrap();
+	let graphs = document.graphs();
+	assert_eq!(2, graphs.len());
+	assert_eq!(&Term::Name("".to_string(), "g".to_string()), graphs[0].0);
+	assert_eq!(3, graphs[0].1.triples().len());
+	assert_eq!(&Term::Iri("urn:y".to_string()), graphs[1].0);
+	assert_eq!(2, document.triples().len());
+	assert_eq!(4, document.formula.spans[2].line);
+	assert!(parse("{ :g { :a :b :c } } .").is_err());
+}
+
+#[test]
+fn parses_quoted_triples_as_terms() {
+	let document = parse("
+		@prefix : <urn:x#> .
+		<< :a :b << :c :d 1 >> >> :e :f .
+		:g :h << _:x a :i >> .
+	").unwrap();
+	let triples = document.triples();
+	let name = |local: &str| Term::Name("".to_string(), local.to_string());
+	let inner = Triple { subject: name("c"), predicate: name("d"), object: Term::Integer(1) };
+	assert_eq!(Term::Quoted(Box::new(Triple { subject: name("a"), predicate: name("b"), object: Term::Quoted(Box::new(inner)) })), triples[0].subject);
+	match triples[1].object {
+		Term::Quoted(ref triple) => assert_eq!(Term::Iri(RDF_TYPE.to_string()), triple.predicate),
+		ref other => panic!("read as {}", other)
+	}
+	assert_eq!(2, triples.len());
+	assert!(parse("<< :a :b ( 1 ) >> :c :d .").is_err());
+	assert!(parse("<< :a :b :c :e :f .").is_err());
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+
+use ast::{Document, Formula, PredicateObjects, Statement, Term, Triple, Verb, LOG_IMPLIES};
+
+const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+
+// The body of a quoted string, with what the lexer reads as escapes escaped.
+pub fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len() + 2);
+	for c in text.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+			c => escaped.push(c)
+		}
+	}
+	escaped
+}
+
+fn join<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T], separator: &str) -> fmt::Result {
+	for (i, item) in items.iter().enumerate() {
+		if i > 0 {
+			write!(f, "{}", separator)?;
+		}
+		write!(f, "{}", item)?;
+	}
+	Ok(())
+}
+
+// Terms are written so that the parser reads them back as the same term.
+impl fmt::Display for Term {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Term::Iri(ref iri) => write!(f, "<{}>", iri),
+			Term::Name(ref prefix, ref local) => write!(f, "{}:{}", prefix, local),
+			Term::Variable(ref name) => write!(f, "?{}", name),
+			Term::Blank(ref label) => write!(f, "_:{}", label),
+			Term::Text(ref text, None) => write!(f, "\"{}\"", escape(text)),
+			Term::Text(ref text, Some(ref language)) => write!(f, "\"{}\"@{}", escape(text), language),
+			Term::Typed(ref lexical, ref datatype) => write!(f, "\"{}\"^^{}", escape(lexical), datatype),
+			Term::Integer(i) => write!(f, "{}", i),
+			// the exponent keeps a whole number from reading back as an integer
+			Term::Decimal(d) if d.is_finite() => write!(f, "{:e}", d),
+			Term::Decimal(d) if d.is_nan() => write!(f, "\"NaN\"^^<{}>", XSD_DOUBLE),
+			Term::Decimal(d) => write!(f, "\"{}INF\"^^<{}>", if d < 0.0 { "-" } else { "" }, XSD_DOUBLE),
+			Term::Boolean(b) => write!(f, "{}", b),
+			Term::Collection(ref items) if items.is_empty() => write!(f, "()"),
+			Term::Collection(ref items) => {
+				write!(f, "( ")?;
+				join(f, items, " ")?;
+				write!(f, " )")
+			}
+			Term::BlankNode(ref list) if list.is_empty() => write!(f, "[]"),
+			Term::BlankNode(ref list) => {
+				write!(f, "[ ")?;
+				join(f, list, " ; ")?;
+				write!(f, " ]")
+			}
+			Term::Formula(ref formula) => write!(f, "{}", formula),
+			Term::Quoted(ref triple) => write!(f, "<< {} >>", triple)
+		}
+	}
+}
+
+// Reading only makes inverse verbs from `<=`, so that is how one is written.
+impl fmt::Display for Verb {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.predicate {
+			Term::Iri(ref iri) if iri == LOG_IMPLIES => write!(f, "{}", if self.inverse { "<=" } else { "=>" }),
+			ref predicate => write!(f, "{}", predicate)
+		}
+	}
+}
+
+impl fmt::Display for PredicateObjects {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} ", self.verb)?;
+		join(f, &self.objects, ", ")
+	}
+}
+
+// Without the final dot.
+impl fmt::Display for Statement {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Statement::Prefix(ref prefix, ref namespace) => write!(f, "@prefix {}: <{}>", prefix, namespace),
+			Statement::Base(ref base) => write!(f, "@base <{}>", base),
+			Statement::ForAll(ref terms) => {
+				write!(f, "@forAll ")?;
+				join(f, terms, ", ")
+			}
+			Statement::ForSome(ref terms) => {
+				write!(f, "@forSome ")?;
+				join(f, terms, ", ")
+			}
+			Statement::Triples(ref subject, ref list) => {
+				write!(f, "{}", subject)?;
+				for (i, po) in list.iter().enumerate() {
+					write!(f, "{}{}", if i == 0 { " " } else { " ; " }, po)?;
+				}
+				Ok(())
+			}
+			Statement::Graph(ref name, ref graph) => write!(f, "{} {}", name, graph)
+		}
+	}
+}
+
+// Without the final dot.
+impl fmt::Display for Triple {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} {} {}", self.subject, self.predicate, self.object)
+	}
+}
+
+// `{ ... }` on one line.
+impl fmt::Display for Formula {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.statements.is_empty() {
+			return write!(f, "{{}}");
+		}
+		write!(f, "{{ ")?;
+		join(f, &self.statements, " . ")?;
+		write!(f, " }}")
+	}
+}
+
+// One statement per line; named graphs end at their brace, as in TriG.
+impl fmt::Display for Document {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for statement in self.formula.statements.iter() {
+			match *statement {
+				Statement::Graph(_, _) => writeln!(f, "{}", statement)?,
+				_ => writeln!(f, "{} .", statement)?
+			}
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn writes_documents_that_read_back_the_same() {
+	let text = "
+		@prefix : <urn:x#> .
+		@forAll :x .
+		:x :says { ?a :is \"a \\\"quoted\\\"\\nline\"@en, 2.5, -3, true ; a :Claim } .
+		{ ?a :follows ?b } <= { ?b :precedes ?a } .
+		:list :items ( 1 [ :p \"2020-01-01\"^^<urn:x#date> ] () ), [] .
+		:graph { :x :in :graph }
+		<< :x :says << :a :b 1 >> >> :source <urn:x#page> .
+	";
+	let document = ::parse(text).unwrap();
+	let written = document.to_string();
+	assert_eq!(document, ::parse(&written).unwrap());
+	assert!(written.contains("<= { ?b :precedes ?a }"));
+	assert!(written.contains(":graph { :x :in :graph }\n"));
+	assert!(written.contains("<< :x :says << :a :b 1 >> >> :source <urn:x#page> ."));
+	assert_eq!(written, ::parse(&written).unwrap().to_string());
+}
+
+#[test]
+fn writes_numbers_so_they_keep_their_kind() {
+	let triple = |object: Term| Triple { subject: Term::Blank("b".to_string()), predicate: Term::Name("".to_string(), "p".to_string()), object };
+	assert_eq!("_:b :p 1e0", triple(Term::Decimal(1.0)).to_string());
+	assert_eq!("_:b :p 1", triple(Term::Integer(1)).to_string());
+	assert_eq!(format!("_:b :p \"-INF\"^^<{}>", XSD_DOUBLE), triple(Term::Decimal(f64::NEG_INFINITY)).to_string());
+	assert_eq!(Term::Decimal(1.0), ::parse(&format!("{} .", triple(Term::Decimal(1.0)))).unwrap().triples()[0].object);
+}
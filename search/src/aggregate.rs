@@ -0,0 +1,304 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use query::{Binding, Value};
+use {Fact, FactSpace, LiteralValue, ObjectValue};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Function {
+    Count,
+    CountDistinct,
+    Min,
+    Max,
+    Sum,
+    Avg
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Function> {
+        return match name {
+            "count" => Some(Function::Count),
+            "distinct" => Some(Function::CountDistinct),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            "sum" => Some(Function::Sum),
+            "avg" => Some(Function::Avg),
+            _ => None
+        }
+    }
+}
+
+// What rows are keyed on. `Variable` only applies to query results and the fact positions
+// only to facts; aggregating with the other kind is an error.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Group {
+    All,
+    Subject,
+    Predicate,
+    Object,
+    Variable(String)
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Order {
+    // ascending by group key
+    Key,
+    // largest aggregate first, ties broken by key
+    Descending
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Row<'a> {
+    pub key: Option<Value<'a>>,
+    pub value: LiteralValue<'a>
+}
+
+impl<'a> fmt::Display for Row<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = ::literal_to_string(self.value);
+        return match self.key {
+            Some(key) => write!(f, "{}\t{}", key, value),
+            None => write!(f, "{}", value)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Aggregation {
+    pub function: Function,
+    pub group: Group,
+    pub order: Order,
+    pub limit: Option<usize>
+}
+
+struct Accumulator<'a> {
+    count: i64,
+    distinct: HashSet<Value<'a>>,
+    // None once the exact sum no longer fits, leaving `float_sum`
+    integer_sum: Option<i64>,
+    float_sum: f64,
+    numbers: usize,
+    any_float: bool,
+    min: Option<LiteralValue<'a>>,
+    max: Option<LiteralValue<'a>>
+}
+
+fn numeric(literal: LiteralValue) -> Option<f64> {
+    return match literal {
+        LiteralValue::Integer(i) => Some(i as f64),
+        LiteralValue::Float(f) => Some(f),
+        _ => None
+    }
+}
+
+impl<'a> Accumulator<'a> {
+    fn new() -> Accumulator<'a> {
+        return Accumulator {
+            count: 0, distinct: HashSet::new(), integer_sum: Some(0), float_sum: 0.0,
+            numbers: 0, any_float: false, min: None, max: None
+        }
+    }
+
+    fn add(&mut self, value: Value<'a>, function: Function) {
+        self.count += 1;
+        if function == Function::CountDistinct {
+            self.distinct.insert(value);
+        }
+        let literal = match value {
+            Value::Object(ObjectValue::Literal(literal)) => literal,
+            _ => { return; }
+        };
+        let number = match numeric(literal) {
+            Some(number) => number,
+            None => { return; }
+        };
+        self.numbers += 1;
+        match literal {
+            LiteralValue::Integer(i) => { self.integer_sum = self.integer_sum.and_then(|sum| sum.checked_add(i)); }
+            _ => { self.any_float = true; }
+        }
+        self.float_sum += number;
+        if self.min.is_none_or(|m| number < numeric(m).unwrap()) {
+            self.min = Some(literal);
+        }
+        if self.max.is_none_or(|m| number > numeric(m).unwrap()) {
+            self.max = Some(literal);
+        }
+    }
+
+    // Min, max and avg have no value for a group without numbers; sum is zero. A sum of
+    // integers that overflows is given as a float.
+    fn finish(&self, function: Function) -> Option<LiteralValue<'a>> {
+        return match function {
+            Function::Count => Some(LiteralValue::Integer(self.count)),
+            Function::CountDistinct => Some(LiteralValue::Integer(self.distinct.len() as i64)),
+            Function::Min => self.min,
+            Function::Max => self.max,
+            Function::Sum => match self.integer_sum {
+                Some(sum) if !self.any_float => Some(LiteralValue::Integer(sum)),
+                _ => Some(LiteralValue::Float(self.float_sum))
+            },
+            Function::Avg if self.numbers == 0 => None,
+            Function::Avg => Some(LiteralValue::Float(self.float_sum / self.numbers as f64))
+        }
+    }
+}
+
+fn compare_numbers(a: LiteralValue, b: LiteralValue) -> ::std::cmp::Ordering {
+    return match (numeric(a), numeric(b)) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        _ => a.cmp(&b)
+    }
+}
+
+impl Aggregation {
+    pub fn new(function: Function) -> Aggregation {
+        return Aggregation { function: function, group: Group::All, order: Order::Key, limit: None };
+    }
+
+    pub fn group_by(mut self, group: Group) -> Aggregation {
+        self.group = group;
+        return self;
+    }
+
+    pub fn order_by(mut self, order: Order) -> Aggregation {
+        self.order = order;
+        return self;
+    }
+
+    pub fn limit(mut self, limit: usize) -> Aggregation {
+        self.limit = Some(limit);
+        return self;
+    }
+
+    fn rows<'a, I>(&self, keyed: I) -> Vec<Row<'a>> where I: Iterator<Item = (Option<Value<'a>>, Value<'a>)> {
+        let mut groups: HashMap<Option<Value<'a>>, Accumulator<'a>> = HashMap::new();
+        for (key, value) in keyed {
+            groups.entry(key).or_insert_with(Accumulator::new).add(value, self.function);
+        }
+        // without grouping there is one row even for no input, so counting nothing gives 0
+        if self.group == Group::All && groups.is_empty() {
+            groups.insert(None, Accumulator::new());
+        }
+        let mut rows: Vec<Row<'a>> = groups.iter()
+            .filter_map(|(key, acc)| acc.finish(self.function).map(|value| Row { key: *key, value: value }))
+            .collect();
+        match self.order {
+            Order::Key => rows.sort_by(|a, b| a.key.cmp(&b.key)),
+            Order::Descending => rows.sort_by(|a, b| compare_numbers(b.value, a.value).then(a.key.cmp(&b.key)))
+        }
+        if let Some(limit) = self.limit {
+            rows.truncate(limit);
+        }
+        return rows;
+    }
+
+    // Aggregates the objects of `facts`; they cannot be grouped by a variable.
+    pub fn over_facts<'f, 'a: 'f, I>(&self, facts: I) -> Result<Vec<Row<'a>>, String> where I: IntoIterator<Item = &'f Fact<'a>> {
+        if let Group::Variable(ref name) = self.group {
+            return Err(format!("facts cannot be grouped by ?{}", name));
+        }
+        let group = self.group.clone();
+        return Ok(self.rows(facts.into_iter().map(move |fact| {
+            let key = match group {
+                Group::Subject => Some(Value::Object(ObjectValue::Id(fact.subject))),
+                Group::Predicate => Some(Value::Predicate(fact.predicate)),
+                Group::Object => Some(Value::Object(fact.object)),
+                Group::All | Group::Variable(_) => None
+            };
+            (key, Value::Object(fact.object))
+        })));
+    }
+
+    // Aggregates the values bound to `variable`; results that leave it unbound are skipped.
+    // Query results can only be grouped by a variable.
+    pub fn over_bindings<'a>(&self, bindings: &[Binding<'a>], variable: &str) -> Result<Vec<Row<'a>>, String> {
+        let group = match self.group {
+            Group::Variable(ref name) => Some(name.clone()),
+            Group::All => None,
+            ref other => { return Err(format!("query results cannot be grouped by {:?}", other)); }
+        };
+        return Ok(self.rows(bindings.iter().filter_map(|binding| {
+            let key = match group {
+                Some(ref name) => Some(*binding.get(name)?),
+                None => None
+            };
+            binding.get(variable).map(|value| (key, *value))
+        })));
+    }
+}
+
+impl<'a> FactSpace<'a> {
+    pub fn aggregate(&self, aggregation: &Aggregation) -> Result<Vec<Row<'a>>, String> {
+        return aggregation.over_facts(self.facts().iter());
+    }
+}
+
+#[test]
+fn counts_words_after_the_and_islands_per_word() {
+    let space = FactSpace::with_facts(::parse("a dog saw the cat and the cat saw the mat today too"));
+    let patterns = ::query::parse_patterns("
+        ?a :word \"the\" . ?a :dist 0 . ?a :node ?island .
+        ?b :node ?island . ?b :dist 1 . ?b :word ?next .
+    ").unwrap();
+    let results = ::query::evaluate(&space, &patterns);
+
+    let distribution = Aggregation::new(Function::Count)
+        .group_by(Group::Variable("next".to_string()))
+        .order_by(Order::Descending)
+        .limit(1)
+        .over_bindings(&results, "next").unwrap();
+    assert_eq!(1, distribution.len());
+    assert_eq!("\"cat\"\t2", distribution[0].to_string());
+
+    let islands = Aggregation::new(Function::CountDistinct).over_bindings(&results, "island").unwrap();
+    assert_eq!(LiteralValue::Integer(3), islands[0].value);
+
+    let per_word = space.aggregate(&Aggregation::new(Function::Count).group_by(Group::Object)).unwrap();
+    assert!(per_word.windows(2).all(|w| w[0].key < w[1].key));
+}
+
+#[test]
+fn numeric_aggregates_skip_text_and_keep_integer_sums_exact() {
+    let facts = [
+        Fact::new_integer_fact(1, ::Predicate::Distance, -2),
+        Fact::new_integer_fact(1, ::Predicate::Distance, 5),
+        Fact::new_float_fact(2, ::Predicate::Distance, 0.5),
+        Fact::new_text_fact(2, ::Predicate::Word, "dad")
+    ];
+
+    let sums = Aggregation::new(Function::Sum).group_by(Group::Subject).over_facts(facts.iter()).unwrap();
+    assert_eq!(LiteralValue::Integer(3), sums[0].value);
+    assert_eq!(LiteralValue::Float(0.5), sums[1].value);
+    assert_eq!(LiteralValue::Integer(-2), Aggregation::new(Function::Min).over_facts(facts.iter()).unwrap()[0].value);
+    assert_eq!(LiteralValue::Integer(5), Aggregation::new(Function::Max).over_facts(facts.iter()).unwrap()[0].value);
+    assert_eq!(LiteralValue::Float(3.5 / 3.0), Aggregation::new(Function::Avg).over_facts(facts.iter()).unwrap()[0].value);
+    assert!(Aggregation::new(Function::Max).over_facts(facts[3..].iter()).unwrap().is_empty());
+}
+
+#[test]
+fn overflowing_integer_sums_become_floats_and_mismatched_groups_are_rejected() {
+    let facts = [
+        Fact::new_integer_fact(1, ::Predicate::Distance, i64::MAX),
+        Fact::new_integer_fact(1, ::Predicate::Distance, 1)
+    ];
+    let sum = Aggregation::new(Function::Sum).over_facts(facts.iter()).unwrap();
+    assert_eq!(LiteralValue::Float(i64::MAX as f64 + 1.0), sum[0].value);
+
+    let by_variable = Aggregation::new(Function::Count).group_by(Group::Variable("x".to_string()));
+    assert!(by_variable.over_facts(facts.iter()).is_err());
+    assert!(Aggregation::new(Function::Count).group_by(Group::Subject).over_bindings(&[], "x").is_err());
+}
+
+#[test]
+fn aggregating_nothing_without_groups_gives_one_row() {
+    let none: Vec<Fact> = Vec::new();
+    let count = Aggregation::new(Function::Count).over_facts(none.iter()).unwrap();
+    assert_eq!(1, count.len());
+    assert_eq!(None, count[0].key);
+    assert_eq!(LiteralValue::Integer(0), count[0].value);
+    assert_eq!(LiteralValue::Integer(0), Aggregation::new(Function::CountDistinct).over_bindings(&[], "x").unwrap()[0].value);
+    assert_eq!(LiteralValue::Integer(0), Aggregation::new(Function::Sum).over_facts(none.iter()).unwrap()[0].value);
+    assert!(Aggregation::new(Function::Avg).over_facts(none.iter()).unwrap().is_empty());
+    assert!(Aggregation::new(Function::Count).group_by(Group::Subject).over_facts(none.iter()).unwrap().is_empty());
+}
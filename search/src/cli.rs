@@ -0,0 +1,594 @@
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use search::aggregate::{Aggregation, Function, Group, Order};
+use search::format::{self, Format};
+use search::identity::FactIds;
+use search::island::IslandGeometry;
+use search::mapped::{self, MappedStore};
+use search::naming::SubjectNames;
+use search::pool::StringPool;
+use search::predict::Islands;
+use search::query;
+use search::repl::Repl;
+use search::rules;
+use search::snapshot;
+use search::store::{FactStore, IndexedStore};
+use search::stream::{self, ParseOptions};
+use search::tokenize::Tokenizer;
+use search::wal::{self, DurableStore};
+use search::{Fact, FactSpace};
+
+// Exit codes follow sysexits(3).
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_USAGE: i32 = 64;
+pub const EXIT_DATA: i32 = 65;
+pub const EXIT_NO_INPUT: i32 = 66;
+pub const EXIT_IO: i32 = 74;
+
+pub const USAGE: &'static str = "\
+usage: search <command> [options]
+
+commands:
+  ingest <files...> [-o model]      parse text files into a binary model snapshot
+  predict <model> <words...>        rank words likely to follow <words>
+  query <model> <query-file>        evaluate triple patterns against a model
+  stats <model>                     print counts, frequent words and memory use
+  infer <model> <rules-file>        write the facts that N3-style rules derive
+  export <model> --format <format>  write a model as n3, ntriples, json, trig or nquads
+  repl <files...>                   explore text files or .model/.nt models interactively
+
+options:
+  -o, --output <path>        output file (default: stdout)
+  --radius <n>               island radius (default: 2)
+  --size <n>                 island size (default: 2 * radius + 1)
+  --tokenizer <name>         whitespace or words (default: whitespace)
+  --limit <n>                number of predictions (default: 5) or frequent
+                             words in stats (default: 10) to print
+  --previous                 predict the word before <words> instead
+  --format <format>          n3, ntriples, json, trig or nquads; makes ingest
+                             write text and sets the output of infer (default: n3)
+  --columns                  make ingest write a memory-mappable column file
+  --graph <name>             make ingest put its facts in the named graph <name>
+  --graph-per-file           make ingest put each input in a graph named after it
+  --store <dir>              make ingest append to a durable store; a store
+                             directory can be used wherever a model is expected
+  --aggregate <fn>:<var>     make query print count, distinct, min, max, sum or
+                             avg of ?var, largest first, instead of every result
+  --group <var>              with --aggregate, print one row per value of ?var
+
+Use - as a path to read from stdin.";
+
+#[derive(Debug)]
+pub struct CliError {
+    pub code: i32,
+    pub message: String
+}
+
+impl CliError {
+    fn usage(message: &str) -> CliError {
+        return CliError { code: EXIT_USAGE, message: message.to_string() };
+    }
+
+    fn data(message: String) -> CliError {
+        return CliError { code: EXIT_DATA, message: message };
+    }
+
+    fn io(path: &str, err: io::Error) -> CliError {
+        let code = match err.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => EXIT_NO_INPUT,
+            _ => EXIT_IO
+        };
+        return CliError { code: code, message: format!("{}: {}", path, err) };
+    }
+}
+
+// The graphs ingest puts facts in.
+#[derive(Debug, PartialEq)]
+pub enum Graphs {
+    Default,
+    Named(String),
+    // each input in a graph named after its path
+    PerFile
+}
+
+impl Graphs {
+    fn of<'g>(&'g self, input: &'g str) -> Option<&'g str> {
+        return match *self {
+            Graphs::Default => None,
+            Graphs::Named(ref name) => Some(name),
+            Graphs::PerFile => Some(input)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Ingest { inputs: Vec<String>, output: String, geometry: (usize, usize), tokenizer: Tokenizer, format: Option<Format>, columns: bool, store: Option<String>, graphs: Graphs },
+    Predict { model: String, words: Vec<String>, limit: usize, previous: bool },
+    Query { model: String, query: String, aggregate: Option<(Function, String)>, group: Option<String>, limit: Option<usize> },
+    Stats { model: String, top: usize },
+    Infer { model: String, rules: String, format: Format, output: String },
+    Export { model: String, format: Format, output: String },
+    Repl { inputs: Vec<String>, geometry: (usize, usize), tokenizer: Tokenizer },
+    Help
+}
+
+fn parse_number(name: &str, value: Option<String>) -> Result<usize, CliError> {
+    return match value.as_ref().map(|v| v.parse::<usize>()) {
+        Some(Ok(n)) if n > 0 => Ok(n),
+        _ => Err(CliError::usage(&format!("{} expects a positive number", name)))
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, CliError> {
+    let mut positional: Vec<String> = Vec::new();
+    let mut output = "-".to_string();
+    let mut radius = search::ISLAND_RADIUS;
+    let mut size: Option<usize> = None;
+    let mut tokenizer = Tokenizer::Whitespace;
+    let mut limit: Option<usize> = None;
+    let mut previous = false;
+    let mut format: Option<Format> = None;
+    let mut columns = false;
+    let mut store: Option<String> = None;
+    let mut aggregate: Option<(Function, String)> = None;
+    let mut group: Option<String> = None;
+    let mut graphs = Graphs::Default;
+
+    let mut rest = args.iter().cloned();
+    while let Some(arg) = rest.next() {
+        match &arg[..] {
+            "-o" | "--output" => match rest.next() {
+                Some(path) => { output = path; }
+                None => { return Err(CliError::usage("--output expects a path")); }
+            },
+            "--radius" => { radius = parse_number("--radius", rest.next())?; }
+            "--size" => { size = Some(parse_number("--size", rest.next())?); }
+            "--limit" => { limit = Some(parse_number("--limit", rest.next())?); }
+            "--previous" => { previous = true; }
+            "--columns" => { columns = true; }
+            "--graph" => match rest.next() {
+                Some(name) => { graphs = Graphs::Named(name); }
+                None => { return Err(CliError::usage("--graph expects a name")); }
+            },
+            "--graph-per-file" => { graphs = Graphs::PerFile; }
+            "--store" => match rest.next() {
+                Some(path) => { store = Some(path); }
+                None => { return Err(CliError::usage("--store expects a directory")); }
+            },
+            "--tokenizer" => match rest.next().as_ref().and_then(|name| Tokenizer::from_name(name)) {
+                Some(t) => { tokenizer = t; }
+                None => { return Err(CliError::usage("--tokenizer expects whitespace or words")); }
+            },
+            "--format" => match rest.next().as_ref().and_then(|name| Format::from_name(name)) {
+                Some(f) => { format = Some(f); }
+                None => { return Err(CliError::usage("--format expects n3, ntriples, json, trig or nquads")); }
+            },
+            "--aggregate" => match rest.next().as_ref().and_then(|spec| parse_aggregate(spec)) {
+                Some(a) => { aggregate = Some(a); }
+                None => { return Err(CliError::usage("--aggregate expects <function>:<variable>")); }
+            },
+            "--group" => match rest.next() {
+                Some(variable) => { group = Some(variable.trim_start_matches('?').to_string()); }
+                None => { return Err(CliError::usage("--group expects a variable")); }
+            },
+            "-h" | "--help" => { return Ok(Command::Help); }
+            _ if arg.len() > 1 && arg.starts_with('-') => {
+                return Err(CliError::usage(&format!("unknown option {}", arg)));
+            }
+            _ => positional.push(arg)
+        }
+    }
+
+    let size = size.unwrap_or(2 * radius + 1);
+    if size < 2 * radius {
+        return Err(CliError::usage("--size must be at least twice the radius"));
+    }
+
+    if positional.is_empty() {
+        return Err(CliError::usage("missing command"));
+    }
+    let command = positional.remove(0);
+    let arity = |n: usize, what: &str| match positional.len() >= n {
+        true => Ok(()),
+        false => Err(CliError::usage(&format!("{} expects {}", command, what)))
+    };
+
+    return match &command[..] {
+        "ingest" => {
+            arity(1, "at least one input file")?;
+            if columns && graphs != Graphs::Default {
+                return Err(CliError::usage("column files do not keep graphs"));
+            }
+            Ok(Command::Ingest { inputs: positional, output: output, geometry: (radius, size), tokenizer: tokenizer, format: format, columns: columns, store: store, graphs: graphs })
+        }
+        "predict" => {
+            arity(2, "a model and at least one word")?;
+            let model = positional.remove(0);
+            Ok(Command::Predict { model: model, words: positional, limit: limit.unwrap_or(5), previous: previous })
+        }
+        "query" => {
+            arity(2, "a model and a query file")?;
+            if group.is_some() && aggregate.is_none() {
+                return Err(CliError::usage("--group needs --aggregate"));
+            }
+            Ok(Command::Query { model: positional[0].clone(), query: positional[1].clone(), aggregate: aggregate, group: group, limit: limit })
+        }
+        "stats" => {
+            arity(1, "a model")?;
+            Ok(Command::Stats { model: positional[0].clone(), top: limit.unwrap_or(10) })
+        }
+        "infer" => {
+            arity(2, "a model and a rules file")?;
+            Ok(Command::Infer { model: positional[0].clone(), rules: positional[1].clone(), format: format.unwrap_or(Format::N3), output: output })
+        }
+        "export" => {
+            arity(1, "a model")?;
+            match format {
+                Some(format) => Ok(Command::Export { model: positional[0].clone(), format: format, output: output }),
+                None => Err(CliError::usage("export expects --format"))
+            }
+        }
+        "repl" => {
+            arity(1, "at least one text file or model")?;
+            Ok(Command::Repl { inputs: positional, geometry: (radius, size), tokenizer: tokenizer })
+        }
+        "help" => Ok(Command::Help),
+        _ => Err(CliError::usage(&format!("unknown command {}", command)))
+    }
+}
+
+fn parse_aggregate(spec: &str) -> Option<(Function, String)> {
+    let mut parts = spec.splitn(2, ':');
+    let function = Function::from_name(parts.next()?)?;
+    let variable = parts.next()?.trim_start_matches('?');
+    if variable.is_empty() {
+        return None;
+    }
+    return Some((function, variable.to_string()));
+}
+
+fn open_input(path: &str) -> Result<Box<dyn BufRead>, CliError> {
+    if path == "-" {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+    return match File::open(path) {
+        Ok(file) => Ok(Box::new(BufReader::new(file))),
+        Err(err) => Err(CliError::io(path, err))
+    }
+}
+
+fn open_output(path: &str) -> Result<Box<dyn Write>, CliError> {
+    if path == "-" {
+        return Ok(Box::new(BufWriter::new(io::stdout())));
+    }
+    return match File::create(path) {
+        Ok(file) => Ok(Box::new(BufWriter::new(file))),
+        Err(err) => Err(CliError::io(path, err))
+    }
+}
+
+fn read_bytes(path: &str) -> Result<Vec<u8>, CliError> {
+    let mut bytes = Vec::new();
+    open_input(path)?.read_to_end(&mut bytes).map_err(|err| CliError::io(path, err))?;
+    return Ok(bytes);
+}
+
+fn read_text(path: &str) -> Result<String, CliError> {
+    return String::from_utf8(read_bytes(path)?).map_err(|_| CliError::data(format!("{}: not UTF-8 text", path)));
+}
+
+fn is_column_file(path: &str) -> bool {
+    let mut magic = [0u8; 8];
+    return match File::open(path) {
+        Ok(mut file) => file.read_exact(&mut magic).is_ok() && mapped::is_column_file(&magic),
+        Err(_) => false
+    }
+}
+
+// Column files are read in place by the commands that only look at facts; the others
+// copy them into a space through `load_space`.
+fn open_columns(path: &str) -> Result<Option<MappedStore>, CliError> {
+    if path == "-" || !is_column_file(path) {
+        return Ok(None);
+    }
+    return MappedStore::open(path).map(Some).map_err(|err| CliError::io(path, err));
+}
+
+// Models are binary snapshots, column files or store directories, which are read without being
+// changed; N3, N-Triples, TriG and N-Quads written by `export` are accepted as well.
+fn load_space<'a>(path: &str, pool: &'a StringPool) -> Result<FactSpace<'a>, CliError> {
+    if wal::is_store(Path::new(path)) {
+        return wal::open_read_only(path, pool).map_err(|err| CliError::io(path, err));
+    }
+    if path != "-" && is_column_file(path) {
+        let store = MappedStore::open(path).map_err(|err| CliError::io(path, err))?;
+        return Ok(store.copy_into(pool));
+    }
+    let bytes = read_bytes(path)?;
+    if snapshot::is_snapshot(&bytes) {
+        return snapshot::read_snapshot(&bytes[..], pool).map_err(|err| CliError::data(format!("{}: {}", path, err)));
+    }
+    let text = String::from_utf8(bytes).map_err(|_| CliError::data(format!("{}: not a model", path)))?;
+    return format::read_text(&text, pool).map_err(|err| CliError::data(format!("{}: {}", path, err)));
+}
+
+fn load_model<'a>(path: &str, pool: &'a StringPool) -> Result<Vec<Fact<'a>>, CliError> {
+    return Ok(load_space(path, pool)?.into_facts());
+}
+
+fn write_model(path: &str, facts: &[Fact], format: Format) -> Result<(), CliError> {
+    return write_named_model(path, facts, format, &SubjectNames::new(), &FactIds::new());
+}
+
+fn write_named_model(path: &str, facts: &[Fact], format: Format, names: &SubjectNames, ids: &FactIds) -> Result<(), CliError> {
+    let mut output = open_output(path)?;
+    format::write_named_facts(&mut output, facts.iter(), format, names, ids).map_err(|err| CliError::io(path, err))?;
+    return output.flush().map_err(|err| CliError::io(path, err));
+}
+
+fn ingest_into_store(inputs: &[String], directory: &str, options: ParseOptions, graphs: &Graphs) -> Result<(), CliError> {
+    let pool = StringPool::new();
+    let mut store = DurableStore::open(directory, &pool).map_err(|err| CliError::io(directory, err))?;
+    if store.truncated > 0 {
+        eprintln!("{}: dropped {} bytes of an incomplete log record", directory, store.truncated);
+    }
+    for input in inputs {
+        let mut reader = open_input(input)?;
+        let status = store.ingest_reader_into(&mut reader, options, graphs.of(input)).map_err(|err| CliError::io(input, err))?;
+        eprintln!("{}: {} words, {} facts", input, status.words, status.facts);
+    }
+    return Ok(());
+}
+
+fn ingest(inputs: &[String], output: &str, options: ParseOptions, format: Option<Format>, columns: bool, graphs: &Graphs) -> Result<(), CliError> {
+    let pool = StringPool::new();
+    let mut space = FactSpace::new();
+    for input in inputs {
+        let mut reader = open_input(input)?;
+        let graph = graphs.of(input).map(|graph| pool.intern(graph));
+        let (_, status) = space.ingest_reader_into(&mut reader, &pool, options, graph)
+            .map_err(|err| CliError::io(input, err))?;
+        eprintln!("{}: {} words, {} facts", input, status.words, status.facts);
+    }
+    if let Some(format) = format {
+        return write_model(output, space.facts(), format);
+    }
+    let mut output_stream = open_output(output)?;
+    if columns {
+        return mapped::write_columns(&mut output_stream, space.facts()).map_err(|err| CliError::io(output, err));
+    }
+    return snapshot::write_snapshot(output_stream, &space).map_err(|err| CliError::io(output, err));
+}
+
+fn predict(model: &str, words: &[String], limit: usize, previous: bool) -> Result<(), CliError> {
+    let pool = StringPool::new();
+    let columns = open_columns(model)?;
+    let facts;
+    let islands = match columns {
+        Some(ref store) => Islands::from_store(&store),
+        None => {
+            facts = load_model(model, &pool)?;
+            Islands::from_store(&facts)
+        }
+    };
+    let context: Vec<&str> = words.iter().map(|w| &w[..]).collect();
+
+    let ranked = match previous {
+        true => islands.previous_words(&context, limit),
+        false => islands.next_words(&context, limit)
+    };
+    if ranked.is_empty() {
+        return Err(CliError::data(format!("no island contains '{}'", words.join(" "))));
+    }
+    for (word, count) in ranked {
+        println!("{}\t{}", word, count);
+    }
+    return Ok(());
+}
+
+fn run_query(model: &str, query_path: &str, aggregate: Option<(Function, String)>, group: Option<String>, limit: Option<usize>) -> Result<(), CliError> {
+    let patterns = query::parse_patterns(&read_text(query_path)?)
+        .map_err(|err| CliError::data(format!("{}: {}", query_path, err)))?;
+    if let Some(store) = open_columns(model)? {
+        return answer(&&store, query_path, &patterns, aggregate, group, limit);
+    }
+    let pool = StringPool::new();
+    let store = IndexedStore::from_facts(load_model(model, &pool)?);
+    return answer(&store, query_path, &patterns, aggregate, group, limit);
+}
+
+fn answer<'a, S: FactStore<'a>>(store: &S, query_path: &str, patterns: &[query::Pattern], aggregate: Option<(Function, String)>, group: Option<String>, limit: Option<usize>) -> Result<(), CliError> {
+    let variables = query::variables(patterns);
+    for variable in aggregate.iter().map(|a| &a.1).chain(group.iter()) {
+        if !variables.contains(variable) {
+            return Err(CliError::usage(&format!("?{} does not appear in {}", variable, query_path)));
+        }
+    }
+    let results = query::evaluate(store, patterns);
+
+    if let Some((function, variable)) = aggregate {
+        let mut aggregation = Aggregation::new(function).order_by(Order::Descending);
+        if let Some(ref name) = group {
+            aggregation = aggregation.group_by(Group::Variable(name.clone()));
+        }
+        if let Some(limit) = limit {
+            aggregation = aggregation.limit(limit);
+        }
+        for row in aggregation.over_bindings(&results, &variable).map_err(|err| CliError::usage(&err))? {
+            println!("{}", row);
+        }
+        return Ok(());
+    }
+
+    println!("{}", variables.iter().map(|v| format!("?{}", v)).collect::<Vec<String>>().join("\t"));
+    for binding in results.iter().take(limit.unwrap_or(results.len())) {
+        let row: Vec<String> = variables.iter().map(|v| binding[v].to_string()).collect();
+        println!("{}", row.join("\t"));
+    }
+    return Ok(());
+}
+
+fn stats(model: &str, top: usize) -> Result<(), CliError> {
+    if let Some(store) = open_columns(model)? {
+        println!("{}", store.statistics(top));
+        return Ok(());
+    }
+    let pool = StringPool::new();
+    let space = load_space(model, &pool)?;
+    println!("{}", space.statistics(top));
+    return Ok(());
+}
+
+fn infer(model: &str, rules_path: &str, format: Format, output: &str) -> Result<(), CliError> {
+    let rules = rules::parse_rules(&read_text(rules_path)?)
+        .map_err(|err| CliError::data(format!("{}: {}", rules_path, err)))?;
+    let pool = StringPool::new();
+    let space = load_space(model, &pool)?;
+    let inference = space.infer(&rules, &pool);
+    for index in 0..rules.len() {
+        eprintln!("rule {}: {} facts", index + 1, inference.from_rule(index).len());
+    }
+    eprintln!("{} facts derived in {} rounds", inference.derived.len(), inference.rounds);
+    return write_model(output, &inference.facts(), format);
+}
+
+fn repl(inputs: &[String], options: ParseOptions) -> Result<(), CliError> {
+    let pool = StringPool::new();
+    let mut facts: Vec<Fact> = Vec::new();
+    for input in inputs {
+        if input.ends_with(".nt") || input.ends_with(".model") || is_column_file(input) || wal::is_store(Path::new(input)) {
+            facts.extend(load_model(input, &pool)?);
+        } else {
+            let mut reader = open_input(input)?;
+            stream::parse_stream(&mut reader, &pool, options, &mut facts, |_| {})
+                .map_err(|err| CliError::io(input, err))?;
+        }
+    }
+    eprintln!("loaded {} facts, type help for commands", facts.len());
+
+    let mut shell = Repl::new(&facts);
+    if let Some(home) = env::var_os("HOME") {
+        shell = shell.with_history_file(PathBuf::from(home).join(".search_history"));
+    }
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    return shell.run(&mut stdin.lock(), &mut stdout.lock()).map_err(|err| CliError::io("-", err));
+}
+
+pub fn execute(command: Command) -> Result<(), CliError> {
+    return match command {
+        Command::Ingest { inputs, output, geometry, tokenizer, format, columns, store, graphs } => {
+            let options = ParseOptions { geometry: IslandGeometry { radius: geometry.0, size: geometry.1 }, tokenizer: tokenizer };
+            match store {
+                Some(directory) => ingest_into_store(&inputs, &directory, options, &graphs),
+                None => ingest(&inputs, &output, options, format, columns, &graphs)
+            }
+        }
+        Command::Predict { model, words, limit, previous } => predict(&model, &words, limit, previous),
+        Command::Query { model, query, aggregate, group, limit } => run_query(&model, &query, aggregate, group, limit),
+        Command::Stats { model, top } => stats(&model, top),
+        Command::Infer { model, rules, format, output } => infer(&model, &rules, format, &output),
+        Command::Export { model, format, output } => {
+            let pool = StringPool::new();
+            let space = load_space(&model, &pool)?;
+            write_named_model(&output, space.facts(), format, &space.names, &space.fact_ids)
+        }
+        Command::Repl { inputs, geometry, tokenizer } => {
+            let options = ParseOptions { geometry: IslandGeometry { radius: geometry.0, size: geometry.1 }, tokenizer: tokenizer };
+            repl(&inputs, options)
+        }
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    return match parse_args(args).and_then(execute) {
+        Ok(()) => EXIT_OK,
+        Err(err) => {
+            eprintln!("search: {}", err.message);
+            if err.code == EXIT_USAGE {
+                eprintln!("\n{}", USAGE);
+            }
+            err.code
+        }
+    }
+}
+
+#[cfg(test)]
+fn args(line: &str) -> Vec<String> {
+    return line.split_whitespace().map(|s| s.to_string()).collect();
+}
+
+#[test]
+fn parses_ingest_with_geometry_and_tokenizer() {
+    let command = parse_args(&args("ingest a.txt - -o model.nt --radius 3 --tokenizer words")).unwrap();
+    assert_eq!(Command::Ingest {
+        inputs: vec!["a.txt".to_string(), "-".to_string()],
+        output: "model.nt".to_string(),
+        geometry: (3, 7),
+        tokenizer: Tokenizer::Words,
+        format: None,
+        columns: false,
+        store: None,
+        graphs: Graphs::Default
+    }, command);
+}
+
+#[test]
+fn parses_ingest_into_graphs() {
+    match parse_args(&args("ingest a.txt --graph words --tokenizer words --format trig")).unwrap() {
+        Command::Ingest { graphs, format, .. } => {
+            assert_eq!(Graphs::Named("words".to_string()), graphs);
+            assert_eq!(Some(Format::TriG), format);
+        }
+        other => panic!("parsed as {:?}", other)
+    }
+    match parse_args(&args("ingest a.txt b.txt --graph-per-file")).unwrap() {
+        Command::Ingest { graphs, .. } => assert_eq!(Some("b.txt"), graphs.of("b.txt")),
+        other => panic!("parsed as {:?}", other)
+    }
+    assert_eq!(EXIT_USAGE, parse_args(&args("ingest a.txt --graph-per-file --columns")).unwrap_err().code);
+}
+
+#[test]
+fn reports_usage_errors_with_exit_code() {
+    assert_eq!(EXIT_USAGE, parse_args(&args("predict model.nt")).unwrap_err().code);
+    assert_eq!(EXIT_USAGE, parse_args(&args("export model.nt --format xml")).unwrap_err().code);
+    assert_eq!(EXIT_USAGE, parse_args(&args("frobnicate")).unwrap_err().code);
+    assert_eq!(EXIT_USAGE, run(&args("ingest a.txt --radius 3 --size 4")));
+}
+
+#[test]
+fn parses_query_aggregation() {
+    let command = parse_args(&args("query model q.txt --aggregate count:?next --group next --limit 3")).unwrap();
+    assert_eq!(Command::Query {
+        model: "model".to_string(),
+        query: "q.txt".to_string(),
+        aggregate: Some((Function::Count, "next".to_string())),
+        group: Some("next".to_string()),
+        limit: Some(3)
+    }, command);
+    assert_eq!(EXIT_USAGE, parse_args(&args("query model q.txt --aggregate median:x")).unwrap_err().code);
+    assert_eq!(EXIT_USAGE, parse_args(&args("query model q.txt --group x")).unwrap_err().code);
+}
+
+#[test]
+fn parses_infer_with_n3_by_default() {
+    let command = parse_args(&args("infer model rules.n3")).unwrap();
+    assert_eq!(Command::Infer { model: "model".to_string(), rules: "rules.n3".to_string(), format: Format::N3, output: "-".to_string() }, command);
+    assert_eq!(EXIT_USAGE, parse_args(&args("infer model")).unwrap_err().code);
+}
+
+#[test]
+fn missing_model_is_no_input() {
+    assert_eq!(EXIT_NO_INPUT, run(&args("stats /nonexistent/model.nt")));
+}
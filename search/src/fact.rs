@@ -0,0 +1,392 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicI64, Ordering as AtomicOrdering};
+
+use literal;
+
+// given: dad went fishing
+// :o1 :node :n1,
+//           :n2,
+//           :n3.
+// 
+// :n1 :word "dad";
+//     :dist 0.
+// :n2 :word "went";
+//     :dist 1.
+// :n3 :word "fishing";
+//     :dist 2.
+//
+// :o2 :node :n4,
+//           :n5,
+//           :n6.
+//
+// :n4 :word "dad";
+//     :dist -1.
+// :n5 :word "went";
+//     :dist 0.
+// :n6 :word "fishing";
+//     :dist 1.
+// (c) Denis Dyatlov
+
+/// What a fact says about its subject.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Predicate {
+    Word,
+    Distance,
+    Node,
+    // derived by rules: the object word comes right after the subject word
+    Follows,
+    // said of a fact id: how sure whoever made the fact is of it, from 0 to 1
+    Confidence,
+    // said of a fact id: the byte offset in the source text the fact came from
+    Offset,
+    // said of a fact id: the name of the rule that derived the fact
+    Rule,
+    // said of a fact id: when the fact was inserted
+    Inserted
+}
+
+impl Predicate {
+    /// The position of the predicate in the canonical fact order.
+    pub fn order(&self) -> i64 {
+        return match *self {
+                Predicate::Word => 10,
+                Predicate::Distance => 20,
+                Predicate::Node => 30,
+                Predicate::Follows => 40,
+                Predicate::Confidence => 50,
+                Predicate::Offset => 60,
+                Predicate::Rule => 70,
+                Predicate::Inserted => 80
+            }
+    }
+}
+
+/// A value a fact can hold; text borrows from a `StringPool` or the parsed input.
+#[derive(Copy, Clone, Debug)]
+pub enum LiteralValue<'a> {
+    Integer(i64),
+    Float(f64),
+    Text(&'a str),
+    Boolean(bool),
+    // days since 1970-01-01
+    Date(i64),
+    // milliseconds since 1970-01-01T00:00:00Z
+    DateTime(i64),
+    // text and its language tag
+    LangText(&'a str, &'a str),
+    // lexical form and datatype IRI
+    Typed(&'a str, &'a str)
+}
+
+/// The object of a fact: another subject, a literal, or another fact quoted by its fact id.
+#[derive(Copy, Clone, Debug)]
+pub enum ObjectValue<'a> {
+    Id(i64),
+    Literal(LiteralValue<'a>),
+    // a fact as a value without asserting it, as an RDF-star quoted triple; the id is
+    // the one `FactIds` gave the fact
+    Quoted(i64)
+}
+
+/// A `(subject, predicate, object)` triple, optionally in a named graph. Field order
+/// gives the canonical sort: subject, then predicate, then object, then graph.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Fact<'a> {
+    pub subject: i64,
+    pub predicate: Predicate,
+    pub object: ObjectValue<'a>,
+    // the named graph the fact belongs to; None for the default graph
+    pub graph: Option<&'a str>
+}
+
+impl PartialOrd for Predicate {
+    fn partial_cmp(&self, other: &Predicate) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Predicate {
+    fn cmp(&self, other: &Predicate) -> Ordering {
+        return self.order().cmp(&other.order());
+    }
+}
+
+impl<'a> LiteralValue<'a> {
+    // Integers sort before floats, floats before text; the later types follow text.
+    fn rank(&self) -> u8 {
+        return match *self {
+            LiteralValue::Integer(_) => 0,
+            LiteralValue::Float(_) => 1,
+            LiteralValue::Text(_) => 2,
+            LiteralValue::Boolean(_) => 3,
+            LiteralValue::Date(_) => 4,
+            LiteralValue::DateTime(_) => 5,
+            LiteralValue::LangText(_, _) => 6,
+            LiteralValue::Typed(_, _) => 7
+        }
+    }
+}
+
+// Floats use the IEEE total order, so NaN equals itself and -0.0 sorts before 0.0;
+// equality and hashing both follow the bit pattern to stay consistent with it.
+impl<'a> PartialEq for LiteralValue<'a> {
+    fn eq(&self, other: &LiteralValue<'a>) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl<'a> Eq for LiteralValue<'a> {}
+
+impl<'a> Hash for LiteralValue<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match *self {
+            LiteralValue::Integer(i) => i.hash(state),
+            LiteralValue::Float(f) => f.to_bits().hash(state),
+            LiteralValue::Text(s) => s.hash(state),
+            LiteralValue::Boolean(b) => b.hash(state),
+            LiteralValue::Date(days) => days.hash(state),
+            LiteralValue::DateTime(millis) => millis.hash(state),
+            LiteralValue::LangText(s, language) => { s.hash(state); language.hash(state); }
+            LiteralValue::Typed(lexical, datatype) => { lexical.hash(state); datatype.hash(state); }
+        }
+    }
+}
+
+impl<'a> PartialOrd for LiteralValue<'a> {
+    fn partial_cmp(&self, other: &LiteralValue<'a>) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl<'a> Ord for LiteralValue<'a> {
+    fn cmp(&self, other: &LiteralValue<'a>) -> Ordering {
+        return match (*self, *other) {
+            (LiteralValue::Integer(a), LiteralValue::Integer(b)) => a.cmp(&b),
+            (LiteralValue::Float(a), LiteralValue::Float(b)) => a.total_cmp(&b),
+            (LiteralValue::Text(a), LiteralValue::Text(b)) => a.cmp(b),
+            (LiteralValue::Boolean(a), LiteralValue::Boolean(b)) => a.cmp(&b),
+            (LiteralValue::Date(a), LiteralValue::Date(b)) => a.cmp(&b),
+            (LiteralValue::DateTime(a), LiteralValue::DateTime(b)) => a.cmp(&b),
+            (LiteralValue::LangText(a, x), LiteralValue::LangText(b, y)) => (a, x).cmp(&(b, y)),
+            (LiteralValue::Typed(a, x), LiteralValue::Typed(b, y)) => (x, a).cmp(&(y, b)),
+            (a, b) => a.rank().cmp(&b.rank())
+        }
+    }
+}
+
+// Literals sort before ids, ids before quoted facts.
+impl<'a> PartialEq for ObjectValue<'a> {
+    fn eq(&self, other: &ObjectValue<'a>) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl<'a> Eq for ObjectValue<'a> {}
+
+impl<'a> Hash for ObjectValue<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            ObjectValue::Literal(literal) => literal.hash(state),
+            ObjectValue::Id(id) => { 3u8.hash(state); id.hash(state); }
+            ObjectValue::Quoted(id) => { 4u8.hash(state); id.hash(state); }
+        }
+    }
+}
+
+impl<'a> ObjectValue<'a> {
+    fn rank(&self) -> u8 {
+        return match *self {
+            ObjectValue::Literal(_) => 0,
+            ObjectValue::Id(_) => 1,
+            ObjectValue::Quoted(_) => 2
+        }
+    }
+}
+
+impl<'a> PartialOrd for ObjectValue<'a> {
+    fn partial_cmp(&self, other: &ObjectValue<'a>) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl<'a> Ord for ObjectValue<'a> {
+    fn cmp(&self, other: &ObjectValue<'a>) -> Ordering {
+        return match (*self, *other) {
+            (ObjectValue::Literal(a), ObjectValue::Literal(b)) => a.cmp(&b),
+            (ObjectValue::Id(a), ObjectValue::Id(b)) => a.cmp(&b),
+            (ObjectValue::Quoted(a), ObjectValue::Quoted(b)) => a.cmp(&b),
+            (a, b) => a.rank().cmp(&b.rank())
+        }
+    }
+}
+
+
+// the last id handed out, shared by every space in the process
+static COUNTER: AtomicI64 = AtomicI64::new(0);
+
+impl<'a> Fact<'a> {
+    pub fn new_object_fact(subject_id: i64, predicate: Predicate, object_id: i64) -> Fact<'a> {
+        return Fact { subject: subject_id, predicate: predicate, object: ObjectValue::Id(object_id), graph: None };
+    }
+
+    pub fn new_literal_fact(subject_id: i64, predicate: Predicate, literal: LiteralValue<'a>) -> Fact<'a> {
+        return Fact { subject: subject_id, predicate: predicate, object: ObjectValue::Literal(literal), graph: None };
+    }
+
+    pub fn new_integer_fact(subject_id: i64, predicate: Predicate, value: i64) -> Fact<'a> {
+        return Fact { subject: subject_id, predicate: predicate, object: ObjectValue::Literal(LiteralValue::Integer(value)), graph: None };
+    }
+
+    pub fn new_float_fact(subject_id: i64, predicate: Predicate, value: f64) -> Fact<'a> {
+        return Fact { subject: subject_id, predicate: predicate, object: ObjectValue::Literal(LiteralValue::Float(value)), graph: None };
+    }
+
+    pub fn new_text_fact(subject_id: i64, predicate: Predicate, value: &'a str) -> Fact<'a> {
+        return Fact { subject: subject_id, predicate: predicate, object: ObjectValue::Literal(LiteralValue::Text(value)), graph: None };
+    }
+
+    /// The same fact in `graph`, or in the default graph for None.
+    pub fn in_graph(self, graph: Option<&'a str>) -> Fact<'a> {
+        return Fact { graph: graph, ..self };
+    }
+
+    pub fn get_object_id(&self) -> i64 {
+        match self.object {
+                ObjectValue::Literal(_) => { panic!("literal is of text value"); }
+                ObjectValue::Id(id) => { return id; }
+                ObjectValue::Quoted(_) => { panic!("object is a quoted fact"); }
+            }
+    }
+
+    /// The fact id of a quoted fact object, as in `:n3 :node << :n2 :follows :n1 >>`.
+    pub fn get_quoted_id(&self) -> i64 {
+        match self.object {
+            ObjectValue::Quoted(id) => { return id; }
+            ObjectValue::Id(_) => { panic!("object is an identifier, not a quoted fact"); }
+            ObjectValue::Literal(_) => { panic!("object is a literal, not a quoted fact"); }
+        }
+    }
+
+    pub fn get_integer_literal(&self) -> i64 {
+        match self.object {
+                ObjectValue::Literal(ref literal) => match *literal {
+                    LiteralValue::Integer(i) => { return i }
+                    LiteralValue::Float(f) => { return f as i64 }
+                    _ => { panic!("literal is not a number"); }
+                },
+                ObjectValue::Id(_) => { panic!("literal is the identifier"); }
+                ObjectValue::Quoted(_) => { panic!("literal is a quoted fact"); }
+            }
+    }
+
+    pub fn get_float_literal(&self) -> f64 {
+        match self.object {
+            ObjectValue::Literal(ref literal) => match *literal {
+                LiteralValue::Integer(i) => { return i as f64 }
+                LiteralValue::Float(f) => { return f }
+                _ => { panic!("literal is not a number"); }
+            },
+            ObjectValue::Id(_) => { panic!("literal is the identifier"); }
+            ObjectValue::Quoted(_) => { panic!("literal is a quoted fact"); }
+        }
+    }
+
+    pub fn get_text_literal(&self) -> String {
+        match self.object {
+                ObjectValue::Literal(ref literal) => match *literal {
+                    LiteralValue::Text(s) => { return s.to_string(); }
+                    LiteralValue::Integer(i) => {
+                        let integer_representation = i.to_string();
+                        return integer_representation;
+                    }
+                    LiteralValue::Float(f) => {
+                        let float_representation = f.to_string();
+                        return float_representation;
+                    }
+                    other => { return literal_to_string(other); }
+                },
+                ObjectValue::Id(_) => { panic!("literal is the identifier"); }
+                ObjectValue::Quoted(_) => { panic!("literal is a quoted fact"); }
+            }
+    }
+
+    /// Hands out the next subject id from the process-wide counter.
+    pub fn new_id() -> i64  {
+        return COUNTER.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+    }
+
+    pub fn last_id() -> i64 {
+        return COUNTER.load(AtomicOrdering::SeqCst);
+    }
+
+    /// Makes sure ids up to `id` are never handed out again, e.g. after loading saved facts.
+    pub fn reserve_ids(id: i64) {
+        COUNTER.fetch_max(id, AtomicOrdering::SeqCst);
+    }
+}
+
+pub fn literal_to_string(literal: LiteralValue) -> String {
+    return match literal {
+        LiteralValue::Text(s) => s.to_string(),
+        LiteralValue::Integer(i) => i.to_string(),
+        LiteralValue::Float(f) => f.to_string(),
+        LiteralValue::Boolean(b) => b.to_string(),
+        LiteralValue::Date(days) => literal::format_date(days),
+        LiteralValue::DateTime(millis) => literal::format_date_time(millis),
+        LiteralValue::LangText(s, language) => format!("{}@{}", s, language),
+        LiteralValue::Typed(lexical, datatype) => format!("{}^^<{}>", lexical, datatype)
+    };
+}
+
+pub fn object_to_string(object_value: &ObjectValue) -> String {
+    return match *object_value {
+        ObjectValue::Literal(literal) => literal_to_string(literal),
+        ObjectValue::Id(id) => id.to_string(),
+        ObjectValue::Quoted(id) => format!("<<{}>>", id)
+    }
+}
+
+impl<'a> fmt::Display for Fact<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let predicate = match self.predicate {
+                Predicate::Distance => "dist",
+                Predicate::Node => "node",
+                Predicate::Word => "word",
+                Predicate::Follows => "follows",
+                Predicate::Confidence => "confidence",
+                Predicate::Offset => "offset",
+                Predicate::Rule => "rule",
+                Predicate::Inserted => "inserted"
+            };
+
+        let subject = self.subject;
+
+        let object = object_to_string(&self.object);
+
+        // facts in a named graph show it as a fourth component
+        match self.graph {
+            Some(graph) => write!(f, "({}, :{}, {}, {})", subject, predicate, object, graph),
+            None => write!(f, "({}, :{}, {})", subject, predicate, object)
+        }
+    }
+}
+
+#[test]
+fn can_return_object_value_of_float_from_fact_with_literal() {
+    let f = Fact::new_float_fact(1231, Predicate::Distance, 10.0);
+    let object_value = f.get_float_literal();
+    assert_eq!(10.0, object_value);
+}
+
+#[test]
+fn ids_stay_unique_across_threads() {
+    let threads: Vec<_> = (0..4).map(|_| ::std::thread::spawn(|| (0..1000).map(|_| Fact::new_id()).collect::<Vec<i64>>())).collect();
+    let mut ids: Vec<i64> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(4000, ids.len());
+    assert!(Fact::last_id() >= ids[3999]);
+}
@@ -0,0 +1,764 @@
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+
+use n3::{self, Namespaces, Statement, Term, Token};
+
+use hierarchy::PredicateHierarchy;
+use identity::FactIds;
+use literal::{self, XSD_BOOLEAN, XSD_DATE, XSD_DATE_TIME, XSD_DOUBLE, XSD_INTEGER};
+use naming::SubjectNames;
+use pool::StringPool;
+use {Fact, FactSpace, LiteralValue, ObjectValue, Predicate};
+
+pub const NAMESPACE: &'static str = "urn:search:";
+// named subjects, as `name:people.dad` for the subject named people.dad
+pub const NAMES: &'static str = "urn:search:name:";
+// named graphs, as <urn:search:graph:corpus/a.txt> for the graph named corpus/a.txt
+pub const GRAPHS: &'static str = "urn:search:graph:";
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Format {
+    N3,
+    NTriples,
+    Json,
+    // N3 with a `<graph> { ... }` block per named graph
+    TriG,
+    // N-Triples with the graph as a fourth term
+    NQuads
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        return match name {
+            "n3" => Some(Format::N3),
+            "ntriples" | "nt" => Some(Format::NTriples),
+            "json" => Some(Format::Json),
+            "trig" => Some(Format::TriG),
+            "nquads" | "nq" => Some(Format::NQuads),
+            _ => None
+        }
+    }
+
+    // Whether terms are written with prefixes, as in N3, rather than in full.
+    fn is_prefixed(&self) -> bool {
+        return *self == Format::N3 || *self == Format::TriG;
+    }
+}
+
+pub fn predicate_name(predicate: Predicate) -> &'static str {
+    return match predicate {
+        Predicate::Word => "word",
+        Predicate::Distance => "dist",
+        Predicate::Node => "node",
+        Predicate::Follows => "follows",
+        Predicate::Confidence => "confidence",
+        Predicate::Offset => "offset",
+        Predicate::Rule => "rule",
+        Predicate::Inserted => "inserted"
+    }
+}
+
+pub fn predicate_from_name(name: &str) -> Option<Predicate> {
+    return match name {
+        "word" => Some(Predicate::Word),
+        "dist" => Some(Predicate::Distance),
+        "node" => Some(Predicate::Node),
+        "follows" => Some(Predicate::Follows),
+        "confidence" => Some(Predicate::Confidence),
+        "offset" => Some(Predicate::Offset),
+        "rule" => Some(Predicate::Rule),
+        "inserted" => Some(Predicate::Inserted),
+        _ => None
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    return escaped;
+}
+
+// The IRI naming `graph`, with what an IRI cannot hold percent-encoded.
+fn graph_iri(graph: &str) -> String {
+    let mut iri = GRAPHS.to_string();
+    for c in graph.chars() {
+        match c {
+            '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' | '%' => iri.push_str(&format!("%{:02X}", c as u32)),
+            c if (c as u32) <= 0x20 => iri.push_str(&format!("%{:02X}", c as u32)),
+            c => iri.push(c)
+        }
+    }
+    return iri;
+}
+
+// The graph an IRI names: what follows `graph:` decoded, or any other IRI as it is.
+fn graph_name(iri: &str) -> Option<String> {
+    if !iri.starts_with(GRAPHS) {
+        return Some(iri.to_string());
+    }
+    let encoded = &iri.as_bytes()[GRAPHS.len()..];
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut at = 0;
+    while at < encoded.len() {
+        if encoded[at] == b'%' {
+            let hex = ::std::str::from_utf8(encoded.get(at + 1..at + 3)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            at = at + 3;
+        } else {
+            bytes.push(encoded[at]);
+            at = at + 1;
+        }
+    }
+    return String::from_utf8(bytes).ok();
+}
+
+fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "INF".to_string() } else { "-INF".to_string() };
+    }
+    return format!("{:e}", value);
+}
+
+fn parse_float(text: &str) -> Option<f64> {
+    return match text {
+        "NaN" => Some(f64::NAN),
+        "INF" | "+INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        _ => text.parse().ok()
+    }
+}
+
+fn typed(lexical: &str, datatype: &str) -> Term {
+    return Term::Typed(lexical.to_string(), Box::new(Term::Iri(datatype.to_string())));
+}
+
+// A literal as an n3 term. N3 and TriG write numbers and booleans bare where they read
+// back the same; N-Triples types every literal but plain and language-tagged text.
+fn literal_term(literal: LiteralValue, format: Format) -> Term {
+    let n3 = format.is_prefixed();
+    return match literal {
+        LiteralValue::Integer(i) if n3 => Term::Integer(i),
+        LiteralValue::Float(f) if n3 && f.is_finite() => Term::Decimal(f),
+        LiteralValue::Boolean(b) if n3 => Term::Boolean(b),
+        LiteralValue::Integer(i) => typed(&i.to_string(), XSD_INTEGER),
+        LiteralValue::Float(f) => typed(&format_float(f), XSD_DOUBLE),
+        LiteralValue::Text(s) => Term::Text(s.to_string(), None),
+        LiteralValue::Boolean(b) => typed(&b.to_string(), XSD_BOOLEAN),
+        LiteralValue::Date(days) => typed(&literal::format_date(days), XSD_DATE),
+        LiteralValue::DateTime(millis) => typed(&literal::format_date_time(millis), XSD_DATE_TIME),
+        LiteralValue::LangText(s, language) => Term::Text(s.to_string(), Some(language.to_string())),
+        LiteralValue::Typed(lexical, datatype) => typed(lexical, datatype)
+    }
+}
+
+// A subject by its name when it has one, else as the numbered node `:n5`, or `_:n5` in
+// N-Triples.
+fn node_term(id: i64, names: &SubjectNames, format: Format) -> Term {
+    return match (names.name(id), format.is_prefixed()) {
+        (Some(name), true) => Term::Name("name".to_string(), name),
+        (Some(name), false) => Term::Iri(format!("{}{}", NAMES, name)),
+        (None, true) => Term::Name("".to_string(), format!("n{}", id)),
+        (None, false) => Term::Blank(format!("n{}", id))
+    }
+}
+
+// A fact id as `<< s p o >>` for its fact, or a plain node for an id `ids` does not know.
+fn quoted_term(id: i64, names: &SubjectNames, ids: &FactIds, format: Format) -> Term {
+    return match ids.fact(id) {
+        Some(fact) => Term::Quoted(Box::new(fact_triple(&fact, names, ids, format))),
+        None => node_term(id, names, format)
+    }
+}
+
+fn fact_triple(fact: &Fact, names: &SubjectNames, ids: &FactIds, format: Format) -> n3::Triple {
+    let predicate = match format.is_prefixed() {
+        true => Term::Name("".to_string(), predicate_name(fact.predicate).to_string()),
+        false => Term::Iri(format!("{}{}", NAMESPACE, predicate_name(fact.predicate)))
+    };
+    let object = match fact.object {
+        ObjectValue::Id(id) => node_term(id, names, format),
+        ObjectValue::Quoted(id) => quoted_term(id, names, ids, format),
+        ObjectValue::Literal(literal) => literal_term(literal, format)
+    };
+    return n3::Triple { subject: quoted_term(fact.subject, names, ids, format), predicate: predicate, object: object };
+}
+
+// A fact about a fact carries the one it is about as `"quoted"`, beside its id.
+fn json_fact(fact: &Fact, names: &SubjectNames, ids: &FactIds) -> String {
+    let quoted = |id: i64| match ids.fact(id) {
+        Some(quoted) => format!(",\"quoted\":{}", json_fact(&quoted, names, ids)),
+        None => String::new()
+    };
+    let object = match fact.object {
+        ObjectValue::Id(id) => format!("{{\"id\":{}}}", id),
+        ObjectValue::Quoted(id) => format!("{{\"id\":{}{}}}", id, quoted(id)),
+        ObjectValue::Literal(LiteralValue::Integer(i)) => format!("{{\"integer\":{}}}", i),
+        ObjectValue::Literal(LiteralValue::Float(f)) => match f.is_finite() {
+            true => format!("{{\"float\":{:?}}}", f),
+            false => format!("{{\"float\":\"{}\"}}", format_float(f))
+        },
+        ObjectValue::Literal(LiteralValue::Text(s)) => format!("{{\"text\":\"{}\"}}", escape(s)),
+        ObjectValue::Literal(LiteralValue::Boolean(b)) => format!("{{\"boolean\":{}}}", b),
+        ObjectValue::Literal(LiteralValue::Date(days)) => format!("{{\"date\":\"{}\"}}", literal::format_date(days)),
+        ObjectValue::Literal(LiteralValue::DateTime(millis)) => format!("{{\"dateTime\":\"{}\"}}", literal::format_date_time(millis)),
+        ObjectValue::Literal(LiteralValue::LangText(s, language)) => format!("{{\"text\":\"{}\",\"lang\":\"{}\"}}", escape(s), escape(language)),
+        ObjectValue::Literal(LiteralValue::Typed(lexical, datatype)) => format!("{{\"value\":\"{}\",\"datatype\":\"{}\"}}", escape(lexical), escape(datatype))
+    };
+    let name = match names.name(fact.subject) {
+        Some(name) => format!(",\"name\":\"{}\"", escape(&name)),
+        None => String::new()
+    };
+    let graph = match fact.graph {
+        Some(graph) => format!(",\"graph\":\"{}\"", escape(graph)),
+        None => String::new()
+    };
+    return format!("{{\"subject\":{}{}{},\"predicate\":\"{}\",\"object\":{}{}}}", fact.subject, name, quoted(fact.subject), predicate_name(fact.predicate), object, graph);
+}
+
+pub fn write_facts<'a, W, I>(writer: &mut W, facts: I, format: Format) -> io::Result<usize>
+    where W: Write, I: IntoIterator<Item = &'a Fact<'a>>
+{
+    return write_named_facts(writer, facts, format, &SubjectNames::new(), &FactIds::new());
+}
+
+// `write_facts`, writing the subjects that have a name in `names` by that name, and fact
+// ids in `ids` as the `<< s p o >>` of their fact. N3 and N-Triples leave graphs out;
+// TriG, N-Quads and JSON keep them.
+pub fn write_named_facts<'a, W, I>(writer: &mut W, facts: I, format: Format, names: &SubjectNames, ids: &FactIds) -> io::Result<usize>
+    where W: Write, I: IntoIterator<Item = &'a Fact<'a>>
+{
+    let mut written = 0;
+    // TriG facts in named graphs, grouped by graph in the order the graphs first appear
+    let mut graphs: Vec<(&str, Vec<&Fact>)> = Vec::new();
+    match format {
+        Format::N3 | Format::TriG => {
+            writeln!(writer, "@prefix : <{}> .", NAMESPACE)?;
+            if !names.is_empty() {
+                writeln!(writer, "@prefix name: <{}> .", NAMES)?;
+            }
+            writeln!(writer)?;
+        }
+        Format::Json => { write!(writer, "[")?; }
+        Format::NTriples | Format::NQuads => {}
+    }
+    for fact in facts {
+        match (format, fact.graph) {
+            (Format::NQuads, Some(graph)) => writeln!(writer, "{} {} .", fact_triple(fact, names, ids, format), Term::Iri(graph_iri(graph)))?,
+            (Format::TriG, Some(graph)) => match graphs.iter().position(|g| g.0 == graph) {
+                Some(index) => graphs[index].1.push(fact),
+                None => graphs.push((graph, vec![fact]))
+            },
+            (Format::Json, _) => {
+                if written > 0 {
+                    write!(writer, ",")?;
+                }
+                write!(writer, "\n  {}", json_fact(fact, names, ids))?;
+            }
+            _ => writeln!(writer, "{} .", fact_triple(fact, names, ids, format))?
+        }
+        written = written + 1;
+    }
+    for (graph, facts) in graphs {
+        writeln!(writer, "\n{} {{", Term::Iri(graph_iri(graph)))?;
+        for fact in facts {
+            writeln!(writer, "    {} .", fact_triple(fact, names, ids, format))?;
+        }
+        writeln!(writer, "}}")?;
+    }
+    if format == Format::Json {
+        writeln!(writer, "\n]")?;
+    }
+    return Ok(written);
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// Turns the terms of a parsed document into subject ids, predicates and objects.
+struct TermReader<'a> {
+    namespaces: Namespaces,
+    hierarchy: PredicateHierarchy,
+    names: SubjectNames,
+    // quoted triples, which get fresh fact ids as other blank nodes get fresh ids
+    fact_ids: FactIds<'a>,
+    // blank nodes other than `_:n5`, by label
+    blanks: HashMap<String, i64>,
+    // the graph block being read; labels the n3 parser makes up for `[]` start over in each
+    block: usize
+}
+
+impl<'a> TermReader<'a> {
+    fn new(namespaces: Namespaces) -> TermReader<'a> {
+        return TermReader {
+            namespaces: namespaces,
+            hierarchy: PredicateHierarchy::new(),
+            names: SubjectNames::new(),
+            fact_ids: FactIds::new(),
+            blanks: HashMap::new(),
+            block: 0
+        };
+    }
+
+    // The id of a numbered node: `_:n5`, or `:n5` in the search namespace.
+    fn numbered(&self, term: &Term) -> Option<i64> {
+        let label = match *term {
+            Term::Blank(ref label) => label.clone(),
+            _ => match self.namespaces.term(term) {
+                Some(ref iri) if iri.starts_with(NAMESPACE) && !iri.starts_with(NAMES) => iri[NAMESPACE.len()..].to_string(),
+                _ => { return None; }
+            }
+        };
+        return match label.starts_with('n') {
+            true => label[1..].parse().ok(),
+            false => None
+        }
+    }
+
+    // The subject id `term` stands for: a numbered node, a named subject or another blank node.
+    fn node(&mut self, term: &Term) -> Option<i64> {
+        if let Some(id) = self.numbered(term) {
+            return Some(id);
+        }
+        if let Term::Blank(ref label) = *term {
+            let key = match label.starts_with('#') {
+                true => format!("{}{}", self.block, label),
+                false => label.clone()
+            };
+            return Some(*self.blanks.entry(key).or_insert_with(Fact::new_id));
+        }
+        let iri = self.namespaces.term(term)?;
+        if !iri.starts_with(NAMES) {
+            return None;
+        }
+        let literal = n3::Literal::parse(&iri[NAMES.len()..])?;
+        return Some(self.names.id(&literal));
+    }
+
+    // `:word`, or a path through the predicate hierarchy such as `:position.dist`.
+    fn predicate(&self, term: &Term) -> Option<Predicate> {
+        let iri = self.namespaces.term(term)?;
+        if !iri.starts_with(NAMESPACE) {
+            return None;
+        }
+        return self.hierarchy.predicate(&iri[NAMESPACE.len()..]);
+    }
+
+    // The fact id of `<< s p o >>`.
+    fn quoted(&mut self, triple: &n3::Triple, pool: &'a StringPool) -> Result<i64, String> {
+        let fact = self.fact(triple, pool)?;
+        return Ok(self.fact_ids.id(fact));
+    }
+
+    fn object(&mut self, term: &Term, pool: &'a StringPool) -> Result<ObjectValue<'a>, String> {
+        let literal = match *term {
+            Term::Quoted(ref triple) => { return self.quoted(triple, pool).map(ObjectValue::Quoted); }
+            Term::Integer(i) => LiteralValue::Integer(i),
+            Term::Decimal(f) => LiteralValue::Float(f),
+            Term::Boolean(b) => LiteralValue::Boolean(b),
+            Term::Text(ref s, None) => LiteralValue::Text(pool.intern(s)),
+            Term::Text(ref s, Some(ref language)) => LiteralValue::LangText(pool.intern(s), pool.intern(language)),
+            Term::Typed(ref lexical, ref datatype) => {
+                let datatype = match self.namespaces.term(datatype) {
+                    Some(datatype) => datatype,
+                    None => { return Err(format!("unknown prefix in datatype {}", datatype)); }
+                };
+                if datatype == XSD_INTEGER {
+                    return lexical.parse().map(|i| ObjectValue::Literal(LiteralValue::Integer(i))).map_err(|_| "bad integer literal".to_string());
+                }
+                if datatype == XSD_DOUBLE {
+                    return parse_float(lexical).map(|f| ObjectValue::Literal(LiteralValue::Float(f))).ok_or("bad double literal".to_string());
+                }
+                literal::typed_literal(pool.intern(lexical), pool.intern(&datatype))?
+            }
+            _ => match self.node(term) {
+                Some(id) => { return Ok(ObjectValue::Id(id)); }
+                None => { return Err(format!("{} cannot be an object", term)); }
+            }
+        };
+        return Ok(ObjectValue::Literal(literal));
+    }
+
+    // A fact in the default graph.
+    fn fact(&mut self, triple: &n3::Triple, pool: &'a StringPool) -> Result<Fact<'a>, String> {
+        let subject = match triple.subject {
+            Term::Quoted(ref quoted) => self.quoted(quoted, pool)?,
+            ref subject => match self.node(subject) {
+                Some(subject) => subject,
+                None => { return Err(format!("{} is not a node", subject)); }
+            }
+        };
+        let predicate = match self.predicate(&triple.predicate) {
+            Some(predicate) => predicate,
+            None => { return Err(format!("unknown predicate {}", triple.predicate)); }
+        };
+        let object = self.object(&triple.object, pool)?;
+        return Ok(Fact { subject: subject, predicate: predicate, object: object, graph: None });
+    }
+
+    fn graph(&self, term: &Term, pool: &'a StringPool) -> Result<&'a str, String> {
+        return match self.namespaces.term(term).and_then(|iri| graph_name(&iri)) {
+            Some(name) => Ok(pool.intern(&name)),
+            None => Err(format!("{} cannot name a graph", term))
+        }
+    }
+
+    // Keeps fresh ids clear of a numbered node in the text, in a quoted triple or not.
+    fn reserve(&self, term: &Term) {
+        if let Term::Quoted(ref triple) = *term {
+            self.reserve(&triple.subject);
+            self.reserve(&triple.object);
+        }
+        if let Some(id) = self.numbered(term) {
+            Fact::reserve_ids(id);
+        }
+    }
+}
+
+// Reads N3, N-Triples or TriG through the n3 parser: numbered nodes such as `:n5` keep
+// their ids, subjects under `name:` keep their names, and other blank nodes get fresh ids.
+// Facts in a `<graph> { ... }` block go in that graph. Each `<< s p o >>` becomes a fact
+// id of the space.
+pub fn read_n3<'a>(text: &str, pool: &'a StringPool) -> Result<FactSpace<'a>, ParseError> {
+    let document = n3::parse(text).map_err(|err| ParseError { line: err.span.line, message: err.message })?;
+    let mut reader = TermReader::new(Namespaces::from_document(&document));
+    let mut blocks = vec![(None, document.formula.triples_with_spans())];
+    for (statement, span) in document.formula.statements.iter().zip(document.formula.spans.iter()) {
+        if let Statement::Graph(ref name, ref graph) = *statement {
+            let name = reader.graph(name, pool).map_err(|message| ParseError { line: span.line, message: message })?;
+            blocks.push((Some(name), graph.triples_with_spans()));
+        }
+    }
+    // fresh ids must come after every numbered node in the text
+    for (_, triples) in blocks.iter() {
+        for (triple, _) in triples.iter() {
+            reader.reserve(&triple.subject);
+            reader.reserve(&triple.object);
+        }
+    }
+    let mut space = FactSpace::new();
+    for (block, (graph, triples)) in blocks.into_iter().enumerate() {
+        reader.block = block;
+        for (triple, span) in triples {
+            let fact = reader.fact(&triple, pool).map_err(|message| ParseError { line: span.line, message: message })?;
+            space.insert(fact.in_graph(graph));
+        }
+    }
+    space.names = reader.names;
+    space.fact_ids = reader.fact_ids;
+    return Ok(space);
+}
+
+// One N-Quads term starting with `token`, reading on from `tokens` for the rest of it.
+fn quad_term<I: Iterator<Item = Token>>(token: Token, tokens: &mut Peekable<I>) -> Result<Term, String> {
+    return match token {
+        Token::Iri(iri) => Ok(Term::Iri(iri)),
+        Token::Blank(label) => Ok(Term::Blank(label)),
+        Token::Text(value, None) if tokens.peek() == Some(&Token::Carets) => {
+            tokens.next();
+            match tokens.next() {
+                Some(Token::Iri(datatype)) => Ok(Term::Typed(value, Box::new(Term::Iri(datatype)))),
+                _ => Err("a datatype must be an IRI".to_string())
+            }
+        }
+        Token::Text(value, language) => Ok(Term::Text(value, language)),
+        Token::OpenQuoted => {
+            let mut terms = Vec::new();
+            while let Some(token) = tokens.next() {
+                if token == Token::CloseQuoted && terms.len() == 3 {
+                    let object = terms.pop().unwrap();
+                    let predicate = terms.pop().unwrap();
+                    let subject = terms.pop().unwrap();
+                    return Ok(Term::Quoted(Box::new(n3::Triple { subject: subject, predicate: predicate, object: object })));
+                }
+                terms.push(quad_term(token, tokens)?);
+            }
+            Err("expected '>>'".to_string())
+        }
+        _ => Err("expected subject, predicate, object, an optional graph and '.'".to_string())
+    }
+}
+
+// One N-Quads line as terms, without its final dot.
+fn quad_terms(line: &str) -> Result<Vec<Term>, String> {
+    let mut tokens = n3::Lexer::new(line).tokens().map_err(|err| err.message)?.into_iter().map(|t| t.0).peekable();
+    let mut terms = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token == Token::Dot && tokens.peek().is_none() && terms.len() >= 3 {
+            return Ok(terms);
+        }
+        terms.push(quad_term(token, &mut tokens)?);
+    }
+    return match terms.is_empty() {
+        true => Ok(terms),
+        false => Err("expected '.'".to_string())
+    }
+}
+
+// Reads N-Quads: N-Triples lines that may name a graph as a fourth term.
+pub fn read_nquads<'a>(text: &str, pool: &'a StringPool) -> Result<FactSpace<'a>, ParseError> {
+    let mut reader = TermReader::new(Namespaces::new());
+    let mut quads = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let terms = quad_terms(line).map_err(|message| ParseError { line: index + 1, message: message })?;
+        match terms.len() {
+            0 => {}
+            3 | 4 => quads.push((index + 1, terms)),
+            _ => { return Err(ParseError { line: index + 1, message: "expected three or four terms".to_string() }); }
+        }
+    }
+    for (_, terms) in quads.iter() {
+        reader.reserve(&terms[0]);
+        reader.reserve(&terms[2]);
+    }
+    let mut space = FactSpace::new();
+    for (line, mut terms) in quads {
+        let error = |message: String| ParseError { line: line, message: message };
+        let graph = match terms.len() {
+            4 => Some(reader.graph(&terms[3], pool).map_err(&error)?),
+            _ => None
+        };
+        terms.truncate(3);
+        let object = terms.pop().unwrap();
+        let predicate = terms.pop().unwrap();
+        let subject = terms.pop().unwrap();
+        let fact = reader.fact(&n3::Triple { subject: subject, predicate: predicate, object: object }, pool).map_err(&error)?;
+        space.insert(fact.in_graph(graph));
+    }
+    space.names = reader.names;
+    space.fact_ids = reader.fact_ids;
+    return Ok(space);
+}
+
+// A model saved as text: N3, N-Triples or TriG, else N-Quads. Errors are the N3 reader's.
+pub fn read_text<'a>(text: &str, pool: &'a StringPool) -> Result<FactSpace<'a>, ParseError> {
+    return match read_n3(text, pool) {
+        Ok(space) => Ok(space),
+        Err(err) => read_nquads(text, pool).map_err(|_| err)
+    }
+}
+
+// Reads facts written by `write_facts` in N-Triples format back into memory.
+pub fn read_ntriples<'a>(text: &str, pool: &'a StringPool) -> Result<Vec<Fact<'a>>, ParseError> {
+    return read_n3(text, pool).map(|space| space.into_facts());
+}
+
+#[test]
+fn ntriples_round_trip_every_literal() {
+    let facts = vec![
+        Fact::new_object_fact(2, Predicate::Node, 1),
+        Fact::new_text_fact(2, Predicate::Word, "say \"hi\"\n\\"),
+        Fact::new_integer_fact(2, Predicate::Distance, -2),
+        Fact::new_float_fact(3, Predicate::Distance, 0.1),
+        Fact::new_float_fact(3, Predicate::Distance, f64::NEG_INFINITY),
+        Fact::new_literal_fact(4, Predicate::Word, LiteralValue::Boolean(false)),
+        Fact::new_literal_fact(4, Predicate::Word, LiteralValue::Date(-1)),
+        Fact::new_literal_fact(4, Predicate::Word, LiteralValue::DateTime(951782400500)),
+        Fact::new_literal_fact(4, Predicate::Word, LiteralValue::LangText("chat", "fr-CA")),
+        Fact::new_literal_fact(4, Predicate::Word, LiteralValue::Typed("POINT(1 2)", "http://www.opengis.net/ont/geosparql#wktLiteral"))
+    ];
+
+    let mut written = Vec::new();
+    write_facts(&mut written, facts.iter(), Format::NTriples).unwrap();
+    let pool = StringPool::new();
+    let read = read_ntriples(&String::from_utf8(written).unwrap(), &pool).unwrap();
+
+    assert_eq!(facts.len(), read.len());
+    for (original, restored) in facts.iter().zip(read.iter()) {
+        assert_eq!(original.to_string(), restored.to_string());
+    }
+    assert_eq!(&facts[5..], &read[5..]);
+}
+
+#[test]
+fn n3_writes_booleans_bare_and_tags_dates() {
+    let facts = [
+        Fact::new_literal_fact(5, Predicate::Word, LiteralValue::Boolean(true)),
+        Fact::new_literal_fact(5, Predicate::Word, LiteralValue::Date(0)),
+        Fact::new_literal_fact(5, Predicate::Word, LiteralValue::LangText("dad", "en"))
+    ];
+    let mut n3 = Vec::new();
+    write_facts(&mut n3, facts.iter(), Format::N3).unwrap();
+    let n3 = String::from_utf8(n3).unwrap();
+    assert!(n3.contains(":n5 :word true ."));
+    assert!(n3.contains(&format!(":n5 :word \"1970-01-01\"^^<{}> .", XSD_DATE)));
+    assert!(n3.contains(":n5 :word \"dad\"@en ."));
+
+    let pool = StringPool::new();
+    let err = read_ntriples(&format!("_:n1 <urn:search:word> \"maybe\"^^<{}> .", XSD_BOOLEAN), &pool).err().unwrap();
+    assert!(err.message.contains("boolean"));
+}
+
+#[test]
+fn n3_and_json_exports_use_predicate_names() {
+    let facts = [Fact::new_text_fact(5, Predicate::Word, "dad"), Fact::new_integer_fact(5, Predicate::Distance, 1)];
+
+    let mut n3 = Vec::new();
+    write_facts(&mut n3, facts.iter(), Format::N3).unwrap();
+    let n3 = String::from_utf8(n3).unwrap();
+    assert!(n3.contains(":n5 :word \"dad\" ."));
+    assert!(n3.contains(":n5 :dist 1 ."));
+
+    let mut json = Vec::new();
+    write_facts(&mut json, facts.iter(), Format::Json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains("{\"subject\":5,\"predicate\":\"word\",\"object\":{\"text\":\"dad\"}}"));
+}
+
+#[test]
+fn reports_line_of_malformed_triple() {
+    let pool = StringPool::new();
+    let err = read_ntriples("_:n1 <urn:search:word> \"a\" .\n_:n1 <urn:search:colour> \"red\" .", &pool).err().unwrap();
+    assert_eq!(2, err.line);
+}
+
+#[test]
+fn n3_round_trips_named_subjects_and_predicate_paths() {
+    let pool = StringPool::new();
+    let space = read_n3("
+        @prefix : <urn:search:> .
+        @prefix name: <urn:search:name:> .
+        name:people.dad :word \"dad\" ; :position.dist 0 ; :node :n7 .
+        name:people.mom :link.follows name:people.dad .
+        [ :word \"anon\" ] .
+    ", &pool).unwrap();
+
+    let dad = space.names.find("people.dad").unwrap();
+    let mom = space.names.find("people.mom").unwrap();
+    assert_eq!(5, space.facts().len());
+    assert!(space.facts().contains(&Fact::new_integer_fact(dad, Predicate::Distance, 0)));
+    assert!(space.facts().contains(&Fact::new_object_fact(dad, Predicate::Node, 7)));
+    assert!(space.facts().contains(&Fact::new_object_fact(mom, Predicate::Follows, dad)));
+    assert!(dad > 7 && mom > 7);
+
+    let mut written = Vec::new();
+    write_named_facts(&mut written, space.facts().iter(), Format::N3, &space.names, &space.fact_ids).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    assert!(written.contains("name:people.mom :follows name:people.dad ."));
+
+    let again = read_n3(&written, &pool).unwrap();
+    assert_eq!(space.facts().len(), again.facts().len());
+    assert_eq!(again.names.find("people.dad"), again.facts().iter().find(|f| f.predicate == Predicate::Distance).map(|f| f.subject));
+
+    let err = read_n3("@prefix : <urn:search:> .\n:n1 :word \"a\" .\n:n1 :link :n2 .", &pool).err().unwrap();
+    assert_eq!(3, err.line);
+    assert!(err.message.contains("unknown predicate"));
+}
+
+#[test]
+fn trig_and_nquads_keep_graphs() {
+    let pool = StringPool::new();
+    let facts = vec![
+        Fact::new_text_fact(1, Predicate::Word, "dad"),
+        Fact::new_text_fact(2, Predicate::Word, "mom").in_graph(Some("corpus/mom.txt")),
+        Fact::new_integer_fact(2, Predicate::Distance, 0).in_graph(Some("corpus/mom.txt")),
+        Fact::new_text_fact(3, Predicate::Word, "kid").in_graph(Some("odd <name> 100%"))
+    ];
+
+    let mut trig = Vec::new();
+    write_facts(&mut trig, facts.iter(), Format::TriG).unwrap();
+    let trig = String::from_utf8(trig).unwrap();
+    assert!(trig.contains("\n<urn:search:graph:corpus/mom.txt> {\n    :n2 :word \"mom\" .\n    :n2 :dist 0 .\n}\n"));
+    assert!(trig.contains("<urn:search:graph:odd%20%3Cname%3E%20100%25> {"));
+
+    let mut nquads = Vec::new();
+    write_facts(&mut nquads, facts.iter(), Format::NQuads).unwrap();
+    let nquads = String::from_utf8(nquads).unwrap();
+    assert!(nquads.contains("_:n2 <urn:search:word> \"mom\" <urn:search:graph:corpus/mom.txt> .\n"));
+    assert!(nquads.starts_with("_:n1 <urn:search:word> \"dad\" .\n"));
+
+    for text in [trig, nquads].iter() {
+        let space = read_text(text, &pool).unwrap();
+        let mut read = space.facts().to_vec();
+        read.sort();
+        assert_eq!(facts, read);
+        assert_eq!(vec!["corpus/mom.txt", "odd <name> 100%"], space.graphs());
+    }
+
+    let space = read_n3("@prefix : <urn:search:> .\n<urn:x> { [ :word \"a\" ] }\n<urn:y> { [ :word \"b\" ] }", &pool).unwrap();
+    assert!(space.facts()[0].subject != space.facts()[1].subject);
+    assert_eq!(Some("urn:y"), space.facts()[1].graph);
+
+    let err = read_text("_:n1 <urn:search:word> \"a\" <urn:g> .\n_:n1 <urn:search:word> .", &pool).err().unwrap();
+    assert_eq!(1, err.line);
+    assert_eq!(2, read_nquads("_:n1 <urn:search:word> \"a\" <urn:g> .\n_:n1 <urn:search:word> .", &pool).err().unwrap().line);
+}
+
+#[test]
+fn quoted_triples_read_back_as_fact_ids() {
+    let pool = StringPool::new();
+    let mut space = FactSpace::new();
+    let went = Fact::new_text_fact(1, Predicate::Word, "went");
+    let follows = Fact::new_object_fact(2, Predicate::Follows, 1);
+    space.insert(went);
+    let id = space.annotate(went, Predicate::Confidence, ObjectValue::Literal(LiteralValue::Float(0.5)));
+    space.annotate(went, Predicate::Rule, ObjectValue::Literal(LiteralValue::Text("tokenizer")));
+    let quoted = space.fact_ids.id(follows);
+    space.insert(Fact { subject: 3, predicate: Predicate::Node, object: ObjectValue::Quoted(quoted), graph: None });
+
+    let write = |format: Format| {
+        let mut written = Vec::new();
+        write_named_facts(&mut written, space.facts().iter(), format, &space.names, &space.fact_ids).unwrap();
+        return String::from_utf8(written).unwrap();
+    };
+    let n3 = write(Format::N3);
+    assert!(n3.contains("<< :n1 :word \"went\" >> :confidence 5e-1 .\n"));
+    assert!(n3.contains(":n3 :node << :n2 :follows :n1 >> .\n"));
+    let nquads = write(Format::NQuads);
+    assert!(nquads.contains("<< _:n1 <urn:search:word> \"went\" >> <urn:search:rule> \"tokenizer\" .\n"));
+    let json = write(Format::Json);
+    assert!(json.contains(&format!("{{\"subject\":{},\"quoted\":{{\"subject\":1,\"predicate\":\"word\",\"object\":{{\"text\":\"went\"}}}},\"predicate\":\"confidence\"", id)));
+    assert!(json.contains(&format!("\"object\":{{\"id\":{},\"quoted\":{{\"subject\":2,\"predicate\":\"follows\",\"object\":{{\"id\":1}}}}}}", quoted)));
+
+    for text in [n3, nquads, write(Format::TriG)].iter() {
+        let read = read_text(text, &pool).unwrap();
+        assert_eq!(4, read.facts().len());
+        assert_eq!(2, read.fact_ids.len());
+        assert_eq!(2, read.about(&went).len());
+        assert_eq!(1, read.match_quoted(|f| f.predicate == Predicate::Follows).len());
+        assert!(!read.facts().contains(&follows));
+    }
+    assert!(read_n3("@prefix : <urn:search:> .\n<< :n1 :unknown 1 >> :confidence 1 .", &pool).is_err());
+
+    // a fact in a named graph is annotated from the default graph, where its id lives
+    let mut space = FactSpace::new();
+    let kid = Fact::new_text_fact(4, Predicate::Word, "kid").in_graph(Some("corpus/kid.txt"));
+    space.insert(kid);
+    space.annotate(kid, Predicate::Confidence, ObjectValue::Literal(LiteralValue::Float(0.5)));
+    let mut trig = Vec::new();
+    write_named_facts(&mut trig, space.facts().iter(), Format::TriG, &space.names, &space.fact_ids).unwrap();
+    let trig = String::from_utf8(trig).unwrap();
+    assert!(trig.starts_with("@prefix : <urn:search:> .\n\n<< :n4 :word \"kid\" >> :confidence 5e-1 .\n"));
+    assert!(trig.contains("<urn:search:graph:corpus/kid.txt> {\n    :n4 :word \"kid\" .\n}\n"));
+    let mut read = read_text(&trig, &pool).unwrap();
+    assert!(read.fact_ids.find(&kid).is_some());
+    assert_eq!(None, read.about(&kid).single().graph);
+    assert_eq!(1, read.match_quoted(|f| f.subject == 4).len());
+    assert_eq!(1, read.drop_graph(Some("corpus/kid.txt")));
+    assert_eq!(1, read.about(&kid).len());
+}
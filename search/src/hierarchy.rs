@@ -0,0 +1,128 @@
+use n3::{NameId, NameTree};
+
+use format::predicate_from_name;
+use Predicate;
+
+// Every predicate. Logs and column files store a predicate as its place here, so new
+// ones go at the end.
+pub const PREDICATES: [Predicate; 8] = [
+    Predicate::Word, Predicate::Distance, Predicate::Node, Predicate::Follows,
+    Predicate::Confidence, Predicate::Offset, Predicate::Rule, Predicate::Inserted
+];
+
+// Predicates placed in a tree of dotted names, as `link.node` and `link.follows` under
+// `link`. Matching a name matches every predicate at or below it, the way
+// rdfs:subPropertyOf makes a property include its sub-properties.
+pub struct PredicateHierarchy {
+    names: NameTree,
+    places: Vec<(Predicate, NameId)>
+}
+
+impl PredicateHierarchy {
+    // word, position.dist, link.node and link.follows, with the predicates said of fact
+    // ids under meta.
+    pub fn new() -> PredicateHierarchy {
+        let mut hierarchy = PredicateHierarchy { names: NameTree::new(), places: Vec::new() };
+        for &(predicate, path) in [
+            (Predicate::Word, "word"),
+            (Predicate::Distance, "position.dist"),
+            (Predicate::Node, "link.node"),
+            (Predicate::Follows, "link.follows"),
+            (Predicate::Confidence, "meta.confidence"),
+            (Predicate::Offset, "meta.offset"),
+            (Predicate::Rule, "meta.rule"),
+            (Predicate::Inserted, "meta.inserted")
+        ].iter() {
+            hierarchy.place(predicate, path);
+        }
+        return hierarchy;
+    }
+
+    // Moves `predicate` to the dotted `path`, making the names along it. The names it
+    // leaves stay, so queries naming them still parse. False for a malformed path.
+    pub fn place(&mut self, predicate: Predicate, path: &str) -> bool {
+        let name = match self.names.insert_path(path) {
+            Some(name) if name != self.names.root() => name,
+            _ => { return false; }
+        };
+        self.places.retain(|&(p, _)| p != predicate);
+        self.places.push((predicate, name));
+        return true;
+    }
+
+    // Adds a name with no predicate of its own, such as `position.offset`, to group
+    // predicates placed under it later.
+    pub fn declare(&mut self, path: &str) -> Option<NameId> {
+        return self.names.insert_path(path);
+    }
+
+    pub fn names(&self) -> &NameTree {
+        return &self.names;
+    }
+
+    pub fn name_of(&self, predicate: Predicate) -> Option<NameId> {
+        return self.places.iter().find(|&&(p, _)| p == predicate).map(|&(_, name)| name);
+    }
+
+    // `root.link.node` for :node.
+    pub fn path(&self, predicate: Predicate) -> Option<&str> {
+        return self.name_of(predicate).map(|name| self.names.full_name(name));
+    }
+
+    // A predicate's own name as `dist`, or a path through the tree as `position.dist`
+    // or `position`.
+    pub fn lookup(&self, name: &str) -> Option<NameId> {
+        if let Some(predicate) = predicate_from_name(name) {
+            return self.name_of(predicate);
+        }
+        return self.names.lookup(name);
+    }
+
+    // Whether `predicate` sits at `name` or anywhere below it.
+    pub fn is_under(&self, predicate: Predicate, name: NameId) -> bool {
+        return match self.name_of(predicate) {
+            Some(place) => place == name || self.names.is_ancestor_of(name, place),
+            None => false
+        }
+    }
+
+    // The predicates at or below `name`, in predicate order.
+    pub fn below(&self, name: NameId) -> Vec<Predicate> {
+        let mut found: Vec<Predicate> = PREDICATES.iter().cloned().filter(|p| self.is_under(*p, name)).collect();
+        found.sort();
+        return found;
+    }
+
+    // The predicate placed exactly at the name `lookup` finds for `name`.
+    pub fn predicate(&self, name: &str) -> Option<Predicate> {
+        let name = self.lookup(name)?;
+        return self.places.iter().find(|&&(_, place)| place == name).map(|&(p, _)| p);
+    }
+
+    // `below` for a name as `lookup` reads it; None when nothing has that name.
+    pub fn family(&self, name: &str) -> Option<Vec<Predicate>> {
+        return self.lookup(name).map(|name| self.below(name));
+    }
+}
+
+#[test]
+fn names_match_their_descendants() {
+    let mut hierarchy = PredicateHierarchy::new();
+    assert_eq!(Some(vec![Predicate::Node, Predicate::Follows]), hierarchy.family("link"));
+    assert_eq!(Some(vec![Predicate::Distance]), hierarchy.family("position"));
+    assert_eq!(Some(vec![Predicate::Distance]), hierarchy.family("dist"));
+    assert_eq!(hierarchy.lookup("link.node"), hierarchy.lookup("node"));
+    assert_eq!(Some("root.position.dist"), hierarchy.path(Predicate::Distance));
+    assert_eq!(None, hierarchy.family("place"));
+    assert_eq!(Some(Predicate::Distance), hierarchy.predicate("position.dist"));
+    assert_eq!(None, hierarchy.predicate("link"));
+
+    let offset = hierarchy.declare("position.offset").unwrap();
+    assert!(hierarchy.below(offset).is_empty());
+    assert!(hierarchy.place(Predicate::Follows, "position.offset.next"));
+    assert!(!hierarchy.place(Predicate::Follows, "root"));
+    assert_eq!(Some(vec![Predicate::Node]), hierarchy.family("link"));
+    assert_eq!(Some(vec![Predicate::Distance, Predicate::Follows]), hierarchy.family("position"));
+    assert!(hierarchy.is_under(Predicate::Follows, offset));
+    assert!(!hierarchy.is_under(Predicate::Word, offset));
+}
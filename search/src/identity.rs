@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use Fact;
+
+// Ids for facts that other facts talk about, so a fact can be the subject of facts such
+// as `<< dad went fishing >> :confidence 0.9`, or quoted as another fact's object. A
+// fact's id comes from the global id counter the first time it is asked for, so it never
+// collides with a subject. Ids ignore graphs: the same triple in two graphs has one id.
+pub struct FactIds<'a> {
+    ids: HashMap<Fact<'a>, i64>,
+    facts: HashMap<i64, Fact<'a>>
+}
+
+impl<'a> FactIds<'a> {
+    pub fn new() -> FactIds<'a> {
+        return FactIds { ids: HashMap::new(), facts: HashMap::new() };
+    }
+
+    // The id of `fact`, handing out a new one for a fact not seen before.
+    pub fn id(&mut self, fact: Fact<'a>) -> i64 {
+        let fact = fact.in_graph(None);
+        if let Some(&id) = self.ids.get(&fact) {
+            return id;
+        }
+        let id = Fact::new_id();
+        self.bind(fact, id);
+        return id;
+    }
+
+    // Gives `fact` the id `id`, as when loading a space that was saved with fact ids.
+    // False when either already belongs to something else.
+    pub fn assign(&mut self, fact: Fact<'a>, id: i64) -> bool {
+        let fact = fact.in_graph(None);
+        match (self.ids.get(&fact), self.facts.get(&id)) {
+            (Some(&bound), _) if bound != id => { return false; }
+            (_, Some(bound)) if *bound != fact => { return false; }
+            _ => {}
+        }
+        self.bind(fact, id);
+        Fact::reserve_ids(id);
+        return true;
+    }
+
+    fn bind(&mut self, fact: Fact<'a>, id: i64) {
+        self.ids.insert(fact, id);
+        self.facts.insert(id, fact);
+    }
+
+    // The id `fact` has, without handing out one.
+    pub fn find(&self, fact: &Fact<'a>) -> Option<i64> {
+        return self.ids.get(&fact.in_graph(None)).cloned();
+    }
+
+    // The fact with the id `id`, always in the default graph.
+    pub fn fact(&self, id: i64) -> Option<Fact<'a>> {
+        return self.facts.get(&id).cloned();
+    }
+
+    // Every fact with an id, by id.
+    pub fn iter(&self) -> Vec<(i64, Fact<'a>)> {
+        let mut all: Vec<(i64, Fact<'a>)> = self.facts.iter().map(|(&id, &fact)| (id, fact)).collect();
+        all.sort_by_key(|&(id, _)| id);
+        return all;
+    }
+
+    // Copies the ids of `other` in, as when merging spaces; false if one clashes.
+    pub fn extend(&mut self, other: &FactIds<'a>) -> bool {
+        return other.iter().into_iter().all(|(id, fact)| self.assign(fact, id));
+    }
+
+    pub fn len(&self) -> usize {
+        return self.ids.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.ids.is_empty();
+    }
+
+    // A rough count of the bytes held by both maps; the facts' strings live in a pool.
+    pub fn estimated_bytes(&self) -> usize {
+        return (self.ids.capacity() + self.facts.capacity()) * (size_of::<Fact>() + size_of::<i64>());
+    }
+}
+
+#[test]
+fn facts_keep_their_ids_across_graphs() {
+    let mut ids = FactIds::new();
+    let went = Fact::new_text_fact(1, ::Predicate::Word, "went");
+    let id = ids.id(went);
+
+    assert!(id > 1);
+    assert_eq!(id, ids.id(went.in_graph(Some("corpus"))));
+    assert_eq!(Some(id), ids.find(&went));
+    assert_eq!(Some(went), ids.fact(id));
+    assert_eq!(None, ids.find(&Fact::new_text_fact(1, ::Predicate::Word, "fishing")));
+
+    let saved = Fact::new_id() + 10;
+    let fishing = Fact::new_text_fact(2, ::Predicate::Word, "fishing");
+    assert!(ids.assign(fishing, saved));
+    assert!(!ids.assign(fishing, id));
+    assert!(!ids.assign(Fact::new_text_fact(3, ::Predicate::Word, "dad"), id));
+    assert!(Fact::new_id() > saved);
+    assert_eq!(vec![(id, went), (saved, fishing)], ids.iter());
+}
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use stream::FactSink;
+use {Fact, FactSpace, Predicate};
+
+/// Words on each side of an island's centre word.
+pub const ISLAND_RADIUS: usize = 2;
+/// Words in an island: the centre word and `ISLAND_RADIUS` on each side.
+pub const ISLAND_SIZE: usize = 5;
+
+/// How many words an island spans around its centre.
+#[derive(Copy, Clone)]
+pub struct IslandGeometry {
+    pub radius: usize,
+    pub size: usize
+}
+
+impl Default for IslandGeometry {
+    fn default() -> IslandGeometry {
+        return IslandGeometry { radius: ISLAND_RADIUS, size: ISLAND_SIZE };
+    }
+}
+
+/// Builds islands from a sequence of words while holding at most one island of them.
+/// Produces exactly the facts `parse` would for the same words.
+pub struct IslandBuilder<'a> {
+    geometry: IslandGeometry,
+    window: VecDeque<&'a str>,
+    seen: usize
+}
+
+impl<'a> IslandBuilder<'a> {
+    pub fn new(geometry: IslandGeometry) -> IslandBuilder<'a> {
+        return IslandBuilder {
+            geometry: geometry,
+            window: VecDeque::with_capacity(geometry.size),
+            seen: 0
+        }
+    }
+
+    pub fn push<S: FactSink<'a>>(&mut self, word: &'a str, sink: &mut S) -> usize {
+        let mut emitted = 0;
+        self.window.push_back(word);
+        self.seen = self.seen + 1;
+
+        // leading words get islands anchored at the sentence start, with the words after
+        // the centre at positive distances as in every other island
+        if self.seen >= self.geometry.radius && self.seen < 2 * self.geometry.radius {
+            let initial_index = self.seen - self.geometry.radius;
+            let island = Fact::new_id();
+            for word_index in 0..self.seen {
+                let distance = word_index as i64 - initial_index as i64;
+                emitted = emitted + self.emit(island, self.window[word_index], distance, sink);
+            }
+        }
+
+        if self.window.len() == self.geometry.size {
+            let island = Fact::new_id();
+            for word_index in 0..self.geometry.size {
+                let distance = word_index as i64 - self.geometry.radius as i64;
+                emitted = emitted + self.emit(island, self.window[word_index], distance, sink);
+            }
+            self.window.pop_front();
+        }
+
+        return emitted;
+    }
+
+    fn emit<S: FactSink<'a>>(&self, island: i64, word: &'a str, distance: i64, sink: &mut S) -> usize {
+        let island_word = Fact::new_id();
+        sink.accept(Fact::new_object_fact(island_word, Predicate::Node, island));
+        sink.accept(Fact::new_text_fact(island_word, Predicate::Word, word));
+        sink.accept(Fact::new_integer_fact(island_word, Predicate::Distance, distance));
+        return 3;
+    }
+}
+
+/// Splits `text` on single spaces and returns the facts of its islands.
+pub fn parse<'a>(text: &'a str) -> Vec<Fact<'a>> {
+    let mut result:Vec<Fact> = Vec::new();
+    let mut builder = IslandBuilder::new(IslandGeometry::default());
+
+    for word in text.split(" ") {
+        builder.push(word, &mut result);
+    }
+
+    return result;
+}
+
+/// `parse`, with the facts in a space of their own.
+pub fn parse_space<'a>(text: &'a str) -> FactSpace<'a> {
+    let boxed: Box<Vec<Fact>> = Box::new(parse(text));
+    return FactSpace::from_heap(boxed);
+}
+
+#[cfg(test)]
+pub const EXAMPLE: &'static str =
+"\
+In linguistics a word is the smallest element that may be uttered in isolation with semantic or\
+pragmatic content (with literal or practical meaning). This contrasts with a morpheme, which is\
+the smallest unit of meaning but will not necessarily stand on its own.\
+";
+
+//const SHORT_EXAMPLE: &'static str = "The shortest text that parses";
+
+#[test]
+fn it_parses() {
+    let facts = parse(EXAMPLE);
+    assert_eq!(585, facts.len())
+}
+
+#[test]
+fn it_finds_facts_for_contrasts() {
+    let space = parse_space(EXAMPLE);
+    let word_space = space.view().match_subject_with_text(|_, t| t == "contrasts");
+    assert_eq!(ISLAND_SIZE, word_space.len());
+}
@@ -0,0 +1,53 @@
+//! Text as islands of facts.
+//!
+//! Every word of a text sits at the centre of an island that also holds the words
+//! around it, each with its distance from the centre. Islands, words and distances
+//! are stored as `(subject, predicate, object)` facts that can be queried, joined,
+//! reasoned over and saved, and that predict which words come before or after others.
+//!
+//! * `fact`: the fact model, `Fact`, `Predicate`, `ObjectValue` and `LiteralValue`
+//! * `identity`: ids for facts, so facts can be said about other facts
+//! * `space`, `store`, `view`, `wal`, `mapped`, `snapshot`, `range`: holding and indexing facts
+//! * `tokenize`, `island`, `stream`: turning text into islands
+//! * `predict`: next and previous word prediction
+//! * `format`, `naming`: reading and writing facts as N3, N-Triples, TriG, N-Quads and JSON
+//! * `query`, `rules`, `aggregate`, `hierarchy`, `statistics`, `repl`: asking about them
+
+// The code spells out `return`, `field: field`, `x = x + 1` and `&'static str`, makes
+// values with `new` alone and indexes arrays in counted loops, the way it always has.
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::assign_op_pattern)]
+#![allow(clippy::redundant_static_lifetimes, clippy::new_without_default, clippy::needless_range_loop)]
+
+extern crate n3;
+
+pub mod aggregate;
+pub mod fact;
+pub mod format;
+pub mod hierarchy;
+pub mod identity;
+pub mod island;
+pub mod literal;
+pub mod mapped;
+pub mod naming;
+pub mod pool;
+pub mod predict;
+pub mod query;
+pub mod range;
+pub mod repl;
+pub mod rules;
+pub mod snapshot;
+pub mod space;
+pub mod statistics;
+pub mod store;
+pub mod stream;
+pub mod tokenize;
+pub mod view;
+pub mod wal;
+
+pub use fact::{literal_to_string, object_to_string, Fact, LiteralValue, ObjectValue, Predicate};
+pub use island::{parse, parse_space, ISLAND_RADIUS, ISLAND_SIZE};
+pub use predict::find_next_word;
+pub use space::{Document, FactSpace};
+
+#[cfg(test)]
+use island::EXAMPLE;
@@ -0,0 +1,253 @@
+use {LiteralValue, ObjectValue};
+
+pub const XSD_INTEGER: &'static str = "http://www.w3.org/2001/XMLSchema#integer";
+pub const XSD_DOUBLE: &'static str = "http://www.w3.org/2001/XMLSchema#double";
+pub const XSD_BOOLEAN: &'static str = "http://www.w3.org/2001/XMLSchema#boolean";
+pub const XSD_DATE: &'static str = "http://www.w3.org/2001/XMLSchema#date";
+pub const XSD_DATE_TIME: &'static str = "http://www.w3.org/2001/XMLSchema#dateTime";
+
+// Object tags shared by the snapshot, column and log formats. Text-like tags carry their
+// strings separately; the rest fit in the u64 payload.
+pub const TAG_ID: u8 = 0;
+pub const TAG_INTEGER: u8 = 1;
+pub const TAG_FLOAT: u8 = 2;
+pub const TAG_TEXT: u8 = 3;
+pub const TAG_BOOLEAN: u8 = 4;
+pub const TAG_DATE: u8 = 5;
+pub const TAG_DATE_TIME: u8 = 6;
+pub const TAG_LANG_TEXT: u8 = 7;
+pub const TAG_TYPED: u8 = 8;
+// a quoted fact, by its fact id
+pub const TAG_QUOTED: u8 = 9;
+
+// How many strings an object with `tag` carries.
+pub fn tag_strings(tag: u8) -> usize {
+    return match tag {
+        TAG_TEXT => 1,
+        TAG_LANG_TEXT | TAG_TYPED => 2,
+        _ => 0
+    }
+}
+
+pub fn encode_object<'a>(object: ObjectValue<'a>) -> (u8, u64, [&'a str; 2]) {
+    return match object {
+        ObjectValue::Id(id) => (TAG_ID, id as u64, ["", ""]),
+        ObjectValue::Quoted(id) => (TAG_QUOTED, id as u64, ["", ""]),
+        ObjectValue::Literal(literal) => match literal {
+            LiteralValue::Integer(i) => (TAG_INTEGER, i as u64, ["", ""]),
+            LiteralValue::Float(f) => (TAG_FLOAT, f.to_bits(), ["", ""]),
+            LiteralValue::Text(s) => (TAG_TEXT, 0, [s, ""]),
+            LiteralValue::Boolean(b) => (TAG_BOOLEAN, b as u64, ["", ""]),
+            LiteralValue::Date(days) => (TAG_DATE, days as u64, ["", ""]),
+            LiteralValue::DateTime(millis) => (TAG_DATE_TIME, millis as u64, ["", ""]),
+            LiteralValue::LangText(s, language) => (TAG_LANG_TEXT, 0, [s, language]),
+            LiteralValue::Typed(lexical, datatype) => (TAG_TYPED, 0, [lexical, datatype])
+        }
+    }
+}
+
+pub fn decode_object<'a>(tag: u8, payload: u64, strings: [&'a str; 2]) -> Option<ObjectValue<'a>> {
+    let literal = match tag {
+        TAG_ID => { return Some(ObjectValue::Id(payload as i64)); }
+        TAG_QUOTED => { return Some(ObjectValue::Quoted(payload as i64)); }
+        TAG_INTEGER => LiteralValue::Integer(payload as i64),
+        TAG_FLOAT => LiteralValue::Float(f64::from_bits(payload)),
+        TAG_TEXT => LiteralValue::Text(strings[0]),
+        TAG_BOOLEAN => LiteralValue::Boolean(payload != 0),
+        TAG_DATE => LiteralValue::Date(payload as i64),
+        TAG_DATE_TIME => LiteralValue::DateTime(payload as i64),
+        TAG_LANG_TEXT => LiteralValue::LangText(strings[0], strings[1]),
+        TAG_TYPED => LiteralValue::Typed(strings[0], strings[1]),
+        _ => { return None; }
+    };
+    return Some(ObjectValue::Literal(literal));
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar (H. Hinnant's algorithm),
+// or None for a year too far out to count in an i64.
+fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    let y = if month <= 2 { year.checked_sub(1)? } else { year };
+    let era = if y >= 0 { y } else { y.checked_sub(399)? } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    return era.checked_mul(146097)?.checked_add(doe - 719468);
+}
+
+// The year, month and day of a day count. Works in i128, as shifting the count to start
+// in the year 0 overflows an i64 near its ends; the year always fits back.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days as i128 + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    return ((yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i64, month as i64, day as i64);
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    return match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+fn digits(text: &str) -> Option<i64> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    return text.parse().ok();
+}
+
+// Parses `YYYY-MM-DD` into days since the epoch.
+pub fn parse_date(text: &str) -> Option<i64> {
+    let (negative, text) = match text.starts_with('-') {
+        true => (true, &text[1..]),
+        false => (false, text)
+    };
+    let mut parts = text.splitn(3, '-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if year.len() < 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let year = if negative { -digits(year)? } else { digits(year)? };
+    let (month, day) = (digits(month)?, digits(day)?);
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    return days_from_civil(year, month, day);
+}
+
+// Parses `YYYY-MM-DDThh:mm:ss[.fff][Z|±hh:mm]` into milliseconds since the epoch, UTC.
+// A time without a zone is taken as UTC.
+pub fn parse_date_time(text: &str) -> Option<i64> {
+    // everything below slices at byte offsets
+    if !text.is_ascii() {
+        return None;
+    }
+    let t = text.find('T')?;
+    let days = parse_date(&text[..t])?;
+    let time = &text[t + 1..];
+    let (clock, offset) = match time.find(['Z', '+', '-']) {
+        Some(z) if &time[z..] == "Z" => (&time[..z], 0),
+        Some(z) => {
+            let zone = &time[z + 1..];
+            if zone.len() != 5 || &zone[2..3] != ":" {
+                return None;
+            }
+            // zones run from -14:00 to +14:00
+            let (zone_hours, zone_minutes) = (digits(&zone[..2])?, digits(&zone[3..])?);
+            if zone_minutes > 59 || zone_hours * 60 + zone_minutes > 14 * 60 {
+                return None;
+            }
+            let minutes = zone_hours * 60 + zone_minutes;
+            (&time[..z], if &time[z..z + 1] == "-" { -minutes } else { minutes })
+        }
+        None => (time, 0)
+    };
+    if clock.len() < 8 || &clock[2..3] != ":" || &clock[5..6] != ":" {
+        return None;
+    }
+    let (hours, minutes, seconds) = (digits(&clock[..2])?, digits(&clock[3..5])?, digits(&clock[6..8])?);
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    let millis = match &clock[8..] {
+        "" => 0,
+        fraction if fraction.starts_with('.') && fraction.len() > 1 => {
+            let fraction = &fraction[1..];
+            digits(fraction)?;
+            let mut padded = fraction.chars().take(3).collect::<String>();
+            while padded.len() < 3 {
+                padded.push('0');
+            }
+            digits(&padded)?
+        }
+        _ => { return None; }
+    };
+    let seconds = days.checked_mul(86400)?.checked_add(hours * 3600 + minutes * 60 + seconds - offset * 60)?;
+    return seconds.checked_mul(1000)?.checked_add(millis);
+}
+
+pub fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    return match year < 0 {
+        true => format!("-{:04}-{:02}-{:02}", -year, month, day),
+        false => format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+pub fn format_date_time(millis: i64) -> String {
+    let seconds = millis.div_euclid(1000);
+    let fraction = millis.rem_euclid(1000);
+    let days = seconds.div_euclid(86400);
+    let clock = seconds.rem_euclid(86400);
+    let time = format!("{:02}:{:02}:{:02}", clock / 3600, clock / 60 % 60, clock % 60);
+    return match fraction {
+        0 => format!("{}T{}Z", format_date(days), time),
+        _ => format!("{}T{}.{:03}Z", format_date(days), time, fraction)
+    }
+}
+
+// Reads a typed literal back from its lexical form, keeping unknown datatypes as `Typed`.
+// Integers and doubles are left to the caller, which knows its own float spelling.
+pub fn typed_literal<'a>(lexical: &'a str, datatype: &'a str) -> Result<LiteralValue<'a>, String> {
+    let bad = |what: &str| Err(format!("bad {} literal \"{}\"", what, lexical));
+    return match datatype {
+        XSD_BOOLEAN => match lexical {
+            "true" | "1" => Ok(LiteralValue::Boolean(true)),
+            "false" | "0" => Ok(LiteralValue::Boolean(false)),
+            _ => bad("boolean")
+        },
+        XSD_DATE => match parse_date(lexical) {
+            Some(days) => Ok(LiteralValue::Date(days)),
+            None => bad("date")
+        },
+        XSD_DATE_TIME => match parse_date_time(lexical) {
+            Some(millis) => Ok(LiteralValue::DateTime(millis)),
+            None => bad("dateTime")
+        },
+        _ => Ok(LiteralValue::Typed(lexical, datatype))
+    }
+}
+
+#[test]
+fn dates_round_trip_through_the_calendar() {
+    assert_eq!(Some(0), parse_date("1970-01-01"));
+    assert_eq!(Some(11016), parse_date("2000-02-29"));
+    assert_eq!(None, parse_date("2001-02-29"));
+    assert_eq!(None, parse_date("2001-13-01"));
+    for days in [-719468, -1, 0, 11016, 20000, 2932896].iter() {
+        assert_eq!(Some(*days), parse_date(&format_date(*days)));
+    }
+
+    assert_eq!(Some(951782400000), parse_date_time("2000-02-29T00:00:00Z"));
+    assert_eq!(parse_date_time("2000-02-29T01:30:00.5+01:30"), parse_date_time("2000-02-29T00:00:00.500"));
+    assert_eq!("2000-02-29T00:00:00.500Z", format_date_time(parse_date_time("2000-02-29T00:00:00.5Z").unwrap()));
+    assert_eq!("1969-12-31T23:59:59Z", format_date_time(-1000));
+    assert_eq!(None, parse_date_time("2000-02-29T24:00:00Z"));
+    assert_eq!(None, parse_date_time("2000-02-29T00:00:0é"));
+    assert_eq!(None, parse_date_time("2000-02-29T00:00:00+0é:00"));
+    assert_eq!(None, parse_date("9223372036854775807-01-01"));
+    assert_eq!(None, parse_date("-9223372036854775807-01-01"));
+    assert_eq!(None, parse_date_time("100000000000000-01-01T00:00:00Z"));
+    assert!(typed_literal("100000000000000-01-01T00:00:00Z", XSD_DATE_TIME).is_err());
+
+    assert_eq!(parse_date_time("2000-02-28T10:00:00Z"), parse_date_time("2000-02-29T00:00:00+14:00"));
+    assert_eq!(parse_date_time("2000-02-29T14:00:00Z"), parse_date_time("2000-02-29T00:00:00-14:00"));
+    assert_eq!(None, parse_date_time("2000-02-29T00:00:00+14:01"));
+    assert_eq!(None, parse_date_time("2000-02-29T00:00:00+15:00"));
+    assert_eq!(None, parse_date_time("2000-02-29T00:00:00-01:60"));
+    assert_eq!(None, parse_date_time("2000-02-29T00:00:00+99:99"));
+
+    // the ends of the day count still have a date
+    assert_eq!("25252734927768524-07-27", format_date(i64::MAX));
+    assert_eq!("-25252734927764585-06-07", format_date(i64::MIN));
+    assert_eq!("292278994-08-17T07:12:55.807Z", format_date_time(i64::MAX));
+    assert_eq!("-292275055-05-16T16:47:04.192Z", format_date_time(i64::MIN));
+}
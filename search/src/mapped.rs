@@ -0,0 +1,503 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::slice;
+use std::str;
+
+use literal::{self, encode_object, decode_object};
+use pool::StringPool;
+use snapshot::{predicate_code, predicate_from_code};
+use {Fact, FactSpace, ObjectValue, Predicate};
+
+// Column layout, little endian, every column starting at a multiple of 8 bytes:
+//   header: magic "SRCHCOLS", version u32, reserved u32, fact count u64, string count u64,
+//           then the byte offset of each column below (8 × u64)
+//   subjects   i64 × facts, sorted ascending
+//   predicates u8 × facts
+//   tags       u8 × facts (the object tags of `literal`)
+//   payloads   u64 × facts (value, or string numbers packed low then high 32 bits)
+//   strings    u64 × (strings + 1) offsets into the heap, strings sorted bytewise
+//   heap       UTF-8 bytes
+//   postings   u64 × (strings + 1) offsets into the word list
+//   words      u64 × word facts, fact positions grouped by string number
+// Graphs are not kept: every fact reads back in the default graph. Neither are fact ids:
+// facts about facts and quoted objects keep the ids, but not the facts they stand for.
+pub const MAGIC: &'static [u8; 8] = b"SRCHCOLS";
+pub const VERSION: u32 = 2;
+const HEADER_LEN: usize = 8 + 4 + 4 + 8 + 8 + 8 * 8;
+
+fn pad<W: Write>(writer: &mut W, written: &mut usize) -> io::Result<()> {
+    while !written.is_multiple_of(8) {
+        writer.write_all(&[0])?;
+        *written = *written + 1;
+    }
+    return Ok(());
+}
+
+fn put<W: Write>(writer: &mut W, written: &mut usize, bytes: &[u8]) -> io::Result<()> {
+    *written = *written + bytes.len();
+    return writer.write_all(bytes);
+}
+
+fn align(offset: usize) -> usize {
+    return offset.div_ceil(8) * 8;
+}
+
+pub fn write_columns<W: Write>(writer: &mut W, facts: &[Fact]) -> io::Result<()> {
+    let mut order: Vec<usize> = (0..facts.len()).collect();
+    order.sort_by_key(|i| facts[*i].subject);
+
+    let mut strings: Vec<&str> = facts.iter().flat_map(|f| {
+        let (tag, _, texts) = encode_object(f.object);
+        (0..literal::tag_strings(tag)).map(move |i| texts[i])
+    }).collect();
+    strings.sort();
+    strings.dedup();
+
+    let mut postings: Vec<Vec<u64>> = vec![Vec::new(); strings.len()];
+    let mut tags: Vec<u8> = Vec::with_capacity(facts.len());
+    let mut payloads: Vec<u64> = Vec::with_capacity(facts.len());
+    for (position, index) in order.iter().enumerate() {
+        let fact = &facts[*index];
+        let (tag, mut payload, texts) = encode_object(fact.object);
+        for i in 0..literal::tag_strings(tag) {
+            payload = payload | (strings.binary_search(&texts[i]).unwrap() as u64) << (32 * i);
+        }
+        if let (literal::TAG_TEXT, Predicate::Word) = (tag, fact.predicate) {
+            postings[payload as usize].push(position as u64);
+        }
+        tags.push(tag);
+        payloads.push(payload);
+    }
+
+    let n = facts.len();
+    let heap_len: usize = strings.iter().map(|s| s.len()).sum();
+    let word_count: usize = postings.iter().map(|p| p.len()).sum();
+    let mut offsets = [0usize; 8];
+    offsets[0] = HEADER_LEN;
+    offsets[1] = align(offsets[0] + 8 * n);
+    offsets[2] = align(offsets[1] + n);
+    offsets[3] = align(offsets[2] + n);
+    offsets[4] = align(offsets[3] + 8 * n);
+    offsets[5] = align(offsets[4] + 8 * (strings.len() + 1));
+    offsets[6] = align(offsets[5] + heap_len);
+    offsets[7] = align(offsets[6] + 8 * (strings.len() + 1));
+
+    let mut written = 0;
+    put(writer, &mut written, MAGIC)?;
+    put(writer, &mut written, &VERSION.to_le_bytes())?;
+    put(writer, &mut written, &0u32.to_le_bytes())?;
+    put(writer, &mut written, &(n as u64).to_le_bytes())?;
+    put(writer, &mut written, &(strings.len() as u64).to_le_bytes())?;
+    for offset in offsets.iter() {
+        put(writer, &mut written, &(*offset as u64).to_le_bytes())?;
+    }
+
+    for index in order.iter() {
+        put(writer, &mut written, &facts[*index].subject.to_le_bytes())?;
+    }
+    pad(writer, &mut written)?;
+    for index in order.iter() {
+        put(writer, &mut written, &[predicate_code(facts[*index].predicate)])?;
+    }
+    pad(writer, &mut written)?;
+    put(writer, &mut written, &tags)?;
+    pad(writer, &mut written)?;
+    for payload in payloads.iter() {
+        put(writer, &mut written, &payload.to_le_bytes())?;
+    }
+
+    let mut heap_offset = 0u64;
+    put(writer, &mut written, &heap_offset.to_le_bytes())?;
+    for s in strings.iter() {
+        heap_offset = heap_offset + s.len() as u64;
+        put(writer, &mut written, &heap_offset.to_le_bytes())?;
+    }
+    for s in strings.iter() {
+        put(writer, &mut written, s.as_bytes())?;
+    }
+    pad(writer, &mut written)?;
+
+    let mut posting_offset = 0u64;
+    put(writer, &mut written, &posting_offset.to_le_bytes())?;
+    for list in postings.iter() {
+        posting_offset = posting_offset + list.len() as u64;
+        put(writer, &mut written, &posting_offset.to_le_bytes())?;
+    }
+    for list in postings.iter() {
+        for position in list.iter() {
+            put(writer, &mut written, &position.to_le_bytes())?;
+        }
+    }
+    debug_assert_eq!(offsets[7] + 8 * word_count, written);
+    return writer.flush();
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+mod os {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    extern "C" {
+        fn mmap(addr: *mut u8, len: usize, prot: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+        fn munmap(addr: *mut u8, len: usize) -> i32;
+    }
+
+    const PROT_READ: i32 = 1;
+    const MAP_PRIVATE: i32 = 2;
+
+    pub struct Region {
+        ptr: *mut u8,
+        len: usize
+    }
+
+    impl Region {
+        pub fn map(file: &File, len: usize) -> io::Result<Region> {
+            let ptr = unsafe { mmap(::std::ptr::null_mut(), len, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+            if ptr as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            return Ok(Region { ptr: ptr, len: len });
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            return unsafe { ::std::slice::from_raw_parts(self.ptr, self.len) };
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            unsafe { munmap(self.ptr, self.len); }
+        }
+    }
+}
+
+#[cfg(not(all(unix, target_pointer_width = "64")))]
+mod os {
+    use std::fs::File;
+    use std::io;
+    use std::io::prelude::*;
+
+    // Without mmap the file is read into 8-byte aligned memory instead.
+    pub struct Region {
+        words: Vec<u64>,
+        len: usize
+    }
+
+    impl Region {
+        pub fn map(file: &File, len: usize) -> io::Result<Region> {
+            let mut words = vec![0u64; (len + 7) / 8];
+            {
+                let bytes = unsafe { ::std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, len) };
+                (&*file).read_exact(bytes)?;
+            }
+            return Ok(Region { words: words, len: len });
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            return unsafe { ::std::slice::from_raw_parts(self.words.as_ptr() as *const u8, self.len) };
+        }
+    }
+}
+
+// A saved fact space opened in place: columns are read straight from the mapped file.
+// Opening reads only the string table and word postings, to check them; the fact columns
+// are not read until asked for.
+pub struct MappedStore {
+    region: os::Region,
+    facts: usize,
+    strings: usize,
+    offsets: [usize; 8]
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[at..at + 8]);
+    return u64::from_le_bytes(buf);
+}
+
+impl MappedStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedStore> {
+        if cfg!(target_endian = "big") {
+            return Err(io::Error::other("column files can only be mapped on little endian hosts"));
+        }
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len < HEADER_LEN {
+            return Err(invalid("file too short for a column header"));
+        }
+        let region = os::Region::map(&file, len)?;
+
+        let (facts, strings, offsets) = {
+            let bytes = region.bytes();
+            if &bytes[..8] != MAGIC {
+                return Err(invalid("not a column file"));
+            }
+            let version = read_u64(bytes, 8) as u32;
+            if version != VERSION && version != 1 {
+                return Err(invalid("unsupported column file version"));
+            }
+            let facts = read_u64(bytes, 16) as usize;
+            let strings = read_u64(bytes, 24) as usize;
+            let mut offsets = [0usize; 8];
+            for (i, offset) in offsets.iter_mut().enumerate() {
+                *offset = read_u64(bytes, 32 + 8 * i) as usize;
+                if *offset % 8 != 0 || *offset > len {
+                    return Err(invalid("misaligned column"));
+                }
+            }
+            // counts come from the file, so every size and end is checked for overflow
+            let too_big = || invalid("column sizes overflow");
+            let fact_words = facts.checked_mul(8).ok_or_else(too_big)?;
+            let string_words = strings.checked_add(1).and_then(|n| n.checked_mul(8)).ok_or_else(too_big)?;
+            let sizes = [fact_words, facts, facts, fact_words, string_words, 0, string_words, 0];
+            for i in 0..8 {
+                if offsets[i].checked_add(sizes[i]).is_none_or(|end| end > len) {
+                    return Err(invalid("column runs past the end of the file"));
+                }
+            }
+            (facts, strings, offsets)
+        };
+
+        let store = MappedStore { region: region, facts: facts, strings: strings, offsets: offsets };
+        store.check_strings(len)?;
+        store.check_postings(len)?;
+        return Ok(store);
+    }
+
+    // Heap offsets must only grow and stay inside the file, and every string must be
+    // UTF-8, so `string` can slice and read them without checking again.
+    fn check_strings(&self, len: usize) -> io::Result<()> {
+        let offsets = self.heap_offsets();
+        if offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err(invalid("string offsets out of order"));
+        }
+        let heap_end = (offsets[self.strings] as usize).checked_add(self.offsets[5]);
+        if heap_end.is_none_or(|end| end > len) {
+            return Err(invalid("string heap runs past the end of the file"));
+        }
+        let heap = &self.region.bytes()[self.offsets[5]..];
+        for number in 0..self.strings {
+            if str::from_utf8(&heap[offsets[number] as usize..offsets[number + 1] as usize]).is_err() {
+                return Err(invalid("string is not UTF-8"));
+            }
+        }
+        return Ok(());
+    }
+
+    // Posting offsets must only grow and stay inside the file, and every posting must name
+    // a fact.
+    fn check_postings(&self, len: usize) -> io::Result<()> {
+        let offsets = self.posting_offsets();
+        if offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err(invalid("posting offsets out of order"));
+        }
+        let words_end = (offsets[self.strings] as usize).checked_mul(8).and_then(|size| size.checked_add(self.offsets[7]));
+        if words_end.is_none_or(|end| end > len) {
+            return Err(invalid("postings run past the end of the file"));
+        }
+        if self.words().iter().any(|&position| position as usize >= self.facts) {
+            return Err(invalid("posting names a fact past the end"));
+        }
+        return Ok(());
+    }
+
+    fn column<T>(&self, index: usize, count: usize) -> &[T] {
+        let bytes = self.region.bytes();
+        return unsafe { slice::from_raw_parts(bytes.as_ptr().add(self.offsets[index]) as *const T, count) };
+    }
+
+    fn subjects(&self) -> &[i64] {
+        return self.column(0, self.facts);
+    }
+
+    fn predicates(&self) -> &[u8] {
+        return self.column(1, self.facts);
+    }
+
+    fn tags(&self) -> &[u8] {
+        return self.column(2, self.facts);
+    }
+
+    fn payloads(&self) -> &[u64] {
+        return self.column(3, self.facts);
+    }
+
+    fn heap_offsets(&self) -> &[u64] {
+        return self.column(4, self.strings + 1);
+    }
+
+    fn posting_offsets(&self) -> &[u64] {
+        return self.column(6, self.strings + 1);
+    }
+
+    fn words(&self) -> &[u64] {
+        return self.column(7, self.posting_offsets()[self.strings] as usize);
+    }
+
+    fn string(&self, number: usize) -> &str {
+        let offsets = self.heap_offsets();
+        let heap = &self.region.bytes()[self.offsets[5]..];
+        let bytes = &heap[offsets[number] as usize..offsets[number + 1] as usize];
+        // `open` checked that every string is UTF-8
+        return unsafe { str::from_utf8_unchecked(bytes) };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.facts;
+    }
+
+    // The size of the mapped file.
+    pub fn mapped_bytes(&self) -> usize {
+        return self.region.bytes().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.facts == 0;
+    }
+
+    pub fn fact(&self, position: usize) -> Fact<'_> {
+        let payload = self.payloads()[position];
+        let tag = self.tags()[position];
+        let mut texts = ["", ""];
+        for i in 0..literal::tag_strings(tag) {
+            let number = (payload >> (32 * i)) as u32 as usize;
+            texts[i] = if number < self.strings { self.string(number) } else { "" };
+        }
+        let object = decode_object(tag, payload, texts).unwrap_or(ObjectValue::Id(payload as i64));
+        let predicate = predicate_from_code(self.predicates()[position]).unwrap_or(Predicate::Node);
+        return Fact { subject: self.subjects()[position], predicate: predicate, object: object, graph: None };
+    }
+
+    pub fn iter<'m>(&'m self) -> Box<dyn Iterator<Item = Fact<'m>> + 'm> {
+        return Box::new((0..self.facts).map(move |i| self.fact(i)));
+    }
+
+    // Copies every fact into an ordinary space whose strings live in `pool`.
+    pub fn copy_into<'a>(&self, pool: &'a StringPool) -> FactSpace<'a> {
+        let facts = self.iter().map(|fact| {
+            let (tag, payload, texts) = encode_object(fact.object);
+            let object = decode_object(tag, payload, [pool.intern(texts[0]), pool.intern(texts[1])]).unwrap();
+            return Fact { subject: fact.subject, predicate: fact.predicate, object: object, graph: None };
+        }).collect();
+        return FactSpace::with_facts(facts);
+    }
+
+    pub fn match_predicates<F>(&self, f: F) -> FactSpace<'_> where F: Fn(Predicate) -> bool {
+        let mut accepted = [false; 256];
+        for (code, accept) in accepted.iter_mut().enumerate() {
+            if let Some(predicate) = predicate_from_code(code as u8) {
+                *accept = f(predicate);
+            }
+        }
+        let facts = self.predicates().iter().enumerate()
+            .filter(|&(_, code)| accepted[*code as usize])
+            .map(|(i, _)| self.fact(i))
+            .collect();
+        return FactSpace::with_facts(facts);
+    }
+
+    pub fn open_subject(&self, subject: i64) -> FactSpace<'_> {
+        let subjects = self.subjects();
+        let start = lower_bound(subjects, subject);
+        let end = start + subjects[start..].iter().take_while(|s| **s == subject).count();
+        return FactSpace::with_facts((start..end).map(|i| self.fact(i)).collect());
+    }
+
+    // `:word` facts whose text is exactly `word`, found through the sorted string table.
+    pub fn word_facts(&self, word: &str) -> FactSpace<'_> {
+        let mut low = 0;
+        let mut high = self.strings;
+        while low < high {
+            let middle = (low + high) / 2;
+            if self.string(middle) < word { low = middle + 1; } else { high = middle; }
+        }
+        if low == self.strings || self.string(low) != word {
+            return FactSpace::new();
+        }
+        let offsets = self.posting_offsets();
+        let postings = &self.words()[offsets[low] as usize..offsets[low + 1] as usize];
+        return FactSpace::with_facts(postings.iter().map(|p| self.fact(*p as usize)).collect());
+    }
+}
+
+fn lower_bound(sorted: &[i64], value: i64) -> usize {
+    let mut low = 0;
+    let mut high = sorted.len();
+    while low < high {
+        let middle = (low + high) / 2;
+        if sorted[middle] < value { low = middle + 1; } else { high = middle; }
+    }
+    return low;
+}
+
+pub fn is_column_file(bytes: &[u8]) -> bool {
+    return bytes.starts_with(MAGIC);
+}
+
+#[cfg(test)]
+fn temp_path(name: &str) -> ::std::path::PathBuf {
+    return ::std::env::temp_dir().join(format!("search-{}-{}", ::std::process::id(), name));
+}
+
+#[test]
+fn mapped_store_answers_space_queries() {
+    let facts = ::parse(::EXAMPLE);
+    let path = temp_path("columns");
+    write_columns(&mut File::create(&path).unwrap(), &facts).unwrap();
+
+    let store = MappedStore::open(&path).unwrap();
+    assert_eq!(facts.len(), store.len());
+
+    let contrasts = store.word_facts("contrasts");
+    assert_eq!(::ISLAND_SIZE, contrasts.facts().len());
+    assert_eq!(0, store.word_facts("zebra").facts().len());
+
+    let subject = contrasts.facts()[0].subject;
+    let opened = store.open_subject(subject);
+    assert_eq!(3, opened.facts().len());
+    assert!(opened.facts().iter().all(|f| f.subject == subject));
+
+    let words = store.match_predicates(|p| matches!(p, Predicate::Word));
+    assert_eq!(facts.len() / 3, words.facts().len());
+
+    ::std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn mapped_store_rejects_foreign_files() {
+    let path = temp_path("not-columns");
+    File::create(&path).unwrap().write_all(&[7u8; 200]).unwrap();
+    assert!(MappedStore::open(&path).is_err());
+    ::std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn mapped_store_rejects_corrupt_counts_and_offsets() {
+    let facts = ::parse("dad went fishing on sunday");
+    let mut bytes = Vec::new();
+    write_columns(&mut bytes, &facts).unwrap();
+    let column = |i: usize| read_u64(&bytes, 32 + 8 * i) as usize;
+    let (heap, words) = (column(5), column(7));
+
+    let path = temp_path("corrupt-columns");
+    let corrupt = |at: usize, value: &[u8]| {
+        let mut corrupted = bytes.clone();
+        corrupted[at..at + value.len()].copy_from_slice(value);
+        File::create(&path).unwrap().write_all(&corrupted).unwrap();
+        return MappedStore::open(&path).err().map(|err| err.kind());
+    };
+    assert_eq!(None, corrupt(0, MAGIC));
+    // a fact count whose column size wraps around
+    assert_eq!(Some(io::ErrorKind::InvalidData), corrupt(16, &(u64::MAX / 4 + 1).to_le_bytes()));
+    assert_eq!(Some(io::ErrorKind::InvalidData), corrupt(24, &u64::MAX.to_le_bytes()));
+    assert_eq!(Some(io::ErrorKind::InvalidData), corrupt(heap, &[0xff]));
+    assert_eq!(Some(io::ErrorKind::InvalidData), corrupt(words, &(facts.len() as u64).to_le_bytes()));
+    ::std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use n3::{Literal, NameId, NameTree};
+
+use Fact;
+
+// Names for the subjects that have one, such as `people.dad`, kept in an n3 name tree.
+// A name's subject id comes from the global id counter the first time it is used, so
+// named subjects never collide with the numbered nodes of ingested text.
+pub struct SubjectNames {
+    tree: NameTree,
+    ids: HashMap<NameId, i64>,
+    names: HashMap<i64, NameId>
+}
+
+impl SubjectNames {
+    pub fn new() -> SubjectNames {
+        return SubjectNames { tree: NameTree::new(), ids: HashMap::new(), names: HashMap::new() };
+    }
+
+    // The subject id for `literal`, handing out a new one for a name not seen before.
+    pub fn id(&mut self, literal: &Literal) -> i64 {
+        let name = self.tree.insert(literal);
+        if let Some(&id) = self.ids.get(&name) {
+            return id;
+        }
+        let id = Fact::new_id();
+        self.bind(name, id);
+        return id;
+    }
+
+    // Gives `id` the name `literal`, as when loading a space that was saved with names.
+    // False when either already belongs to something else.
+    pub fn assign(&mut self, literal: &Literal, id: i64) -> bool {
+        let name = self.tree.insert(literal);
+        match (self.ids.get(&name), self.names.get(&id)) {
+            (Some(&bound), _) if bound != id => { return false; }
+            (_, Some(&bound)) if bound != name => { return false; }
+            _ => {}
+        }
+        self.bind(name, id);
+        Fact::reserve_ids(id);
+        return true;
+    }
+
+    fn bind(&mut self, name: NameId, id: i64) {
+        self.ids.insert(name, id);
+        self.names.insert(id, name);
+    }
+
+    // The subject named by `path`, written as `a.b` or `root.a.b`, if there is one.
+    pub fn find(&self, path: &str) -> Option<i64> {
+        return self.tree.lookup(path).and_then(|name| self.ids.get(&name).cloned());
+    }
+
+    // `a.b` for the subject named root.a.b.
+    pub fn name(&self, id: i64) -> Option<String> {
+        return self.names.get(&id).map(|&name| self.tree.literal(name).steps().join("."));
+    }
+
+    // Every named subject with its name, by id.
+    pub fn iter(&self) -> Vec<(i64, Literal)> {
+        let mut all: Vec<(i64, Literal)> = self.names.iter().map(|(&id, &name)| (id, self.tree.literal(name))).collect();
+        all.sort_by_key(|&(id, _)| id);
+        return all;
+    }
+
+    // Named subjects at or below `path`, such as every `people.*` under `people`.
+    pub fn below(&self, path: &str) -> Vec<i64> {
+        let start = match self.tree.lookup(path) {
+            Some(start) => start,
+            None => { return Vec::new(); }
+        };
+        return self.tree.descendants(start).iter().filter_map(|name| self.ids.get(name).cloned()).collect();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.ids.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.ids.is_empty();
+    }
+
+    // A rough count of the bytes held: both maps and the text of every name in the tree.
+    pub fn estimated_bytes(&self) -> usize {
+        let entry = size_of::<NameId>() + size_of::<i64>();
+        let text: usize = self.names.values().map(|&name| self.tree.name(name).len() + self.tree.full_name(name).len()).sum();
+        return (self.ids.capacity() + self.names.capacity()) * entry + text;
+    }
+}
+
+#[test]
+fn names_keep_their_ids() {
+    let mut names = SubjectNames::new();
+    let dad = names.id(&Literal::parse("people.dad").unwrap());
+    let mom = names.id(&Literal::parse("people.mom").unwrap());
+
+    assert!(dad != mom);
+    assert_eq!(dad, names.id(&Literal::parse("root.people.dad").unwrap()));
+    assert_eq!(Some(mom), names.find("people.mom"));
+    assert_eq!(Some("people.dad".to_string()), names.name(dad));
+    assert_eq!(vec![dad, mom], names.below("people"));
+    assert_eq!(None, names.find("people"));
+
+    let id = Fact::new_id() + 10;
+    assert!(names.assign(&Literal::parse("people.kid").unwrap(), id));
+    assert!(!names.assign(&Literal::parse("people.kid").unwrap(), dad));
+    assert!(!names.assign(&Literal::parse("people.aunt").unwrap(), dad));
+    assert!(Fact::new_id() > id);
+    assert_eq!(3, names.len());
+}
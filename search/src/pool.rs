@@ -0,0 +1,57 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// Owns every distinct word seen by a streaming source so that facts can keep
+// borrowing `&str` like they do when parsed from an in-memory text.
+// Strings are boxed and never removed, so handed out references stay valid
+// for as long as the pool itself.
+pub struct StringPool {
+    strings: RefCell<Vec<Box<str>>>,
+    index: RefCell<HashMap<&'static str, usize>>
+}
+
+impl StringPool {
+    pub fn new() -> StringPool {
+        return StringPool {
+            strings: RefCell::new(Vec::new()),
+            index: RefCell::new(HashMap::new())
+        }
+    }
+
+    pub fn intern<'a>(&'a self, value: &str) -> &'a str {
+        if let Some(position) = self.index.borrow().get(value) {
+            return self.get(*position);
+        }
+
+        let boxed: Box<str> = value.to_string().into_boxed_str();
+        // the heap allocation behind the box does not move when the vector grows
+        let stable: &'static str = unsafe { &*(&*boxed as *const str) };
+        let mut strings = self.strings.borrow_mut();
+        strings.push(boxed);
+        self.index.borrow_mut().insert(stable, strings.len() - 1);
+        return stable;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.strings.borrow().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    fn get(&self, position: usize) -> &str {
+        let strings = self.strings.borrow();
+        return unsafe { &*(&*strings[position] as *const str) };
+    }
+}
+
+#[test]
+fn pool_interns_equal_strings_once() {
+    let pool = StringPool::new();
+    let first = pool.intern("government");
+    let second = pool.intern(&String::from("government"));
+
+    assert_eq!(1, pool.len());
+    assert_eq!(first.as_ptr(), second.as_ptr());
+}
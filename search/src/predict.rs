@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use store::{FactPattern, FactStore};
+use {literal_to_string, Fact, LiteralValue, ObjectValue, Predicate};
+
+// One word slot of an island: the word and its distance from the island centre.
+#[derive(Copy, Clone)]
+pub struct Slot<'a> {
+    pub word: &'a str,
+    pub distance: i64
+}
+
+// Islands regrouped from flat facts, so that prediction does not rescan the space per word.
+pub struct Islands<'a> {
+    members: HashMap<i64, Vec<Slot<'a>>>,
+    occurrences: HashMap<&'a str, Vec<(i64, i64)>>
+}
+
+impl<'a> Islands<'a> {
+    pub fn from_facts<'f, I>(facts: I) -> Islands<'a> where I: IntoIterator<Item = &'f Fact<'a>>, 'a: 'f {
+        return Islands::build(facts.into_iter().cloned());
+    }
+
+    pub fn from_store<S: FactStore<'a>>(store: &S) -> Islands<'a> {
+        return Islands::build(store.scan());
+    }
+
+    fn build<I: Iterator<Item = Fact<'a>>>(facts: I) -> Islands<'a> {
+        let mut island_of: HashMap<i64, i64> = HashMap::new();
+        let mut word_of: HashMap<i64, &'a str> = HashMap::new();
+        let mut distance_of: HashMap<i64, i64> = HashMap::new();
+
+        for fact in facts {
+            match (fact.predicate, fact.object) {
+                (Predicate::Node, ObjectValue::Id(island)) => { island_of.insert(fact.subject, island); }
+                (Predicate::Word, ObjectValue::Literal(LiteralValue::Text(word))) => { word_of.insert(fact.subject, word); }
+                (Predicate::Distance, ObjectValue::Literal(LiteralValue::Integer(distance))) => { distance_of.insert(fact.subject, distance); }
+                _ => {}
+            }
+        }
+
+        let mut islands = Islands { members: HashMap::new(), occurrences: HashMap::new() };
+        let mut subjects: Vec<&i64> = island_of.keys().collect();
+        subjects.sort();
+        for subject in subjects {
+            let island = island_of[subject];
+            if let (Some(word), Some(distance)) = (word_of.get(subject), distance_of.get(subject)) {
+                islands.members.entry(island).or_default().push(Slot { word: word, distance: *distance });
+                islands.occurrences.entry(*word).or_default().push((island, *distance));
+            }
+        }
+        return islands;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.members.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.members.is_empty();
+    }
+
+    pub fn vocabulary(&self) -> usize {
+        return self.occurrences.len();
+    }
+
+    pub fn members(&self, island: i64) -> &[Slot<'a>] {
+        return match self.members.get(&island) {
+            Some(slots) => slots,
+            None => &[]
+        }
+    }
+
+    // Islands containing `word`, with the distance the word has in each of them.
+    pub fn containing(&self, word: &str) -> Vec<(i64, i64)> {
+        let mut found = self.occurrences.get(word).cloned().unwrap_or_default();
+        found.sort();
+        return found;
+    }
+
+    fn word_at(&self, island: i64, distance: i64) -> Option<&'a str> {
+        return self.members(island).iter().find(|s| s.distance == distance).map(|s| s.word);
+    }
+
+    // Ranks words seen at `step` (+1 or -1) from the anchor, which is the last context word
+    // for `step > 0` and the first one otherwise. Only islands centred on the anchor count,
+    // so each occurrence in the corpus is seen once. The longest context suffix (or prefix)
+    // that occurs anywhere is used; shorter ones only as a fallback.
+    fn rank(&self, context: &[&str], step: i64, limit: usize) -> Vec<(&'a str, usize)> {
+        for used in (1..context.len() + 1).rev() {
+            let (anchor, rest): (&str, Vec<&str>) = match step > 0 {
+                true => {
+                    let window = &context[context.len() - used..];
+                    (window[used - 1], window[..used - 1].iter().rev().cloned().collect())
+                }
+                false => (context[0], context[1..used].to_vec())
+            };
+
+            let mut counts: HashMap<&'a str, usize> = HashMap::new();
+            for &(island, distance) in self.occurrences.get(anchor).map(|o| &o[..]).unwrap_or(&[]) {
+                if distance != 0 {
+                    continue;
+                }
+                let fits = rest.iter().enumerate().all(|(offset, word)| {
+                    self.word_at(island, distance - step * (offset as i64 + 1)) == Some(*word)
+                });
+                if !fits {
+                    continue;
+                }
+                if let Some(candidate) = self.word_at(island, distance + step) {
+                    *counts.entry(candidate).or_insert(0) += 1;
+                }
+            }
+
+            if !counts.is_empty() {
+                let mut ranked: Vec<(&'a str, usize)> = counts.into_iter().collect();
+                ranked.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(y.0)));
+                ranked.truncate(limit);
+                return ranked;
+            }
+        }
+        return Vec::new();
+    }
+
+    pub fn next_words(&self, context: &[&str], limit: usize) -> Vec<(&'a str, usize)> {
+        return self.rank(context, 1, limit);
+    }
+
+    pub fn previous_words(&self, context: &[&str], limit: usize) -> Vec<(&'a str, usize)> {
+        return self.rank(context, -1, limit);
+    }
+}
+
+/// The literal of the one fact about `subject` with `predicate`; panics unless there is exactly one.
+pub fn resolve_literal<'a, S: FactStore<'a>>(facts: &S, subject: i64, predicate: Predicate) -> LiteralValue<'a> {
+    let candidates:Vec<Fact> = facts
+        .match_pattern(&FactPattern { subject: Some(subject), predicate: Some(predicate), object: None, range: None });
+
+    if candidates.len() != 1 {
+        panic!("no subject-predicate pair")
+    }
+
+    let fact = candidates[0];
+
+    match fact.object {
+        ObjectValue::Literal(literal) => { return literal; }
+        _ => { panic!("fact is not defined by literal"); }
+    }
+}
+
+/// The object id of the one fact about `subject` with `predicate`; panics unless there is exactly one.
+pub fn resolve_object<'a, S: FactStore<'a>>(facts: &S, subject: i64, predicate: Predicate) -> i64 {
+    let candidates:Vec<Fact> = facts
+    .match_pattern(&FactPattern { subject: Some(subject), predicate: Some(predicate), object: None, range: None });
+
+    if candidates.len() != 1 {
+        panic!("no subject-predicate pair")
+    }
+
+    let fact = candidates[0];
+
+    match fact.object {
+            ObjectValue::Id(x) => x,
+            _ => { panic!("fact is not defined as object"); }
+        }
+}
+
+/// Facts whose object is the text `word`.
+pub fn collect_word_facts<'a, S: FactStore<'a>> (facts: &S, word: &'a str) -> Vec<Fact<'a>> {
+    return facts.match_pattern(&FactPattern {
+        subject: None,
+        predicate: None,
+        object: Some(ObjectValue::Literal(LiteralValue::Text(word))),
+        range: None
+    });
+}
+
+/// The `:node` facts of the words in `island`.
+pub fn collect_island_facts<'a, S: FactStore<'a>>(facts: &S, island: i64) -> Vec<Fact<'a>> {
+    return facts.match_pattern(&FactPattern {
+        subject: None,
+        predicate: Some(Predicate::Node),
+        object: Some(ObjectValue::Id(island)),
+        range: None
+    });
+}
+
+
+/// The `:dist` fact of an island word.
+pub fn resolve_word_distance<'a, S: FactStore<'a>>(facts: &S, subject: i64) -> Fact<'a> {
+    let facts:Vec<Fact<'a>> = facts
+        .match_pattern(&FactPattern { subject: Some(subject), predicate: Some(Predicate::Distance), object: None, range: None });
+
+    return *(facts.first().unwrap());
+}
+
+/// The word at the centre of the first island in which `word` sits one place after the centre.
+pub fn find_next_word<'a, S: FactStore<'a>>(facts: &S, word: &'a str) -> String {
+    let word_facts: Vec<Fact> = collect_word_facts(facts, word);
+
+    let previous_word_facts:Vec<Fact> = word_facts
+        .iter()
+        .filter(|x| resolve_word_distance(facts, x.subject).get_integer_literal() == 1)
+        .copied()
+        .collect();
+
+    let previous_word_fact:Fact = *(previous_word_facts.first().unwrap());
+
+    let island = resolve_object(facts, previous_word_fact.subject, Predicate::Node);
+
+    let island_facts = collect_island_facts(facts, island);
+
+    let island_dist0_facts:Vec<Fact> = island_facts
+        .iter()
+        .filter(|x| resolve_word_distance(facts, x.subject).get_integer_literal() == 0)
+        .copied()
+        .collect();
+    let island_dist0_fact = *(island_dist0_facts.first().unwrap());
+
+    return literal_to_string(resolve_literal(facts, island_dist0_fact.subject, Predicate::Word));
+}
+
+#[test]
+fn predicts_most_frequent_follower() {
+    let facts = ::parse("the cat sat on the mat and the cat ran off the mat again");
+    let islands = Islands::from_facts(&facts);
+
+    // the text starts with "the cat", so its first island counts too
+    assert_eq!(vec![("cat", 2), ("mat", 2)], islands.next_words(&["the"], 5));
+    assert_eq!("mat", islands.next_words(&["on", "the"], 1)[0].0);
+    assert_eq!("on", islands.previous_words(&["the", "mat", "and"], 1)[0].0);
+    assert!(islands.next_words(&["unicorn"], 1).is_empty());
+}
+
+#[test]
+fn predicts_around_the_first_words_of_a_text() {
+    let facts = ::parse("dad went fishing on sunday and mom went shopping on monday");
+    let islands = Islands::from_facts(&facts);
+
+    assert_eq!(vec![("fishing", 1), ("shopping", 1)], islands.next_words(&["went"], 5));
+    assert_eq!(vec![("dad", 1), ("mom", 1)], islands.previous_words(&["went"], 5));
+    assert_eq!(vec![("went", 1)], islands.next_words(&["dad"], 5));
+    assert_eq!(vec![("fishing", 1)], islands.next_words(&["dad", "went"], 5));
+    assert!(islands.previous_words(&["dad"], 5).is_empty());
+}
+
+#[test]
+fn it_finds_positive_facts_for_contrasts() {
+
+    let space = ::parse_space(::EXAMPLE);
+
+    let contrasts_facts = space.view()
+        .match_subject_with_text(|_, t| t == "contrasts");
+
+    let dist_facts = space.view()
+        .match_objects(|s, p|
+            match p {
+                Predicate::Distance => contrasts_facts.contains_subject(s),
+                _ => false
+            });
+
+    let positive_dist_facts = dist_facts.match_subject_with_integer(|_, i| i > 0);
+
+    for fact in positive_dist_facts.iter() {
+        println!("positive fact: {}", fact);
+    }
+}
+
+#[test]
+fn a_subject_join_finds_the_same_distances_for_contrasts() {
+    let space = ::parse_space(::EXAMPLE);
+    let contrasts_facts = space.view().match_subject_with_text(|_, t| t == "contrasts");
+
+    let filtered = space.view()
+        .match_objects(|s, p| p == Predicate::Distance && contrasts_facts.contains_subject(s));
+    let joined = space.view()
+        .match_predicates(|p| p == Predicate::Distance)
+        .join_subjects(&contrasts_facts);
+
+    assert_eq!(::ISLAND_SIZE, joined.len());
+    assert_eq!(filtered.to_vec(), joined.to_vec());
+    assert_eq!(filtered.match_subject_with_integer(|_, i| i > 0).len(), joined.match_subject_with_integer(|_, i| i > 0).len());
+}
+
+#[test]
+fn it_finds_next_word_for_contrasts() {
+    let facts = ::parse(::EXAMPLE);
+    let word_facts: Vec<Fact> = collect_word_facts(&facts, "contrasts");
+
+    let previous_word_facts:Vec<Fact> = word_facts
+        .iter()
+        .filter(|x| resolve_word_distance(&facts, x.subject).get_integer_literal() == 1)
+        .copied()
+        .collect();
+
+    let previous_word_fact:Fact = *(previous_word_facts.first().unwrap());
+    println!("previous word fact: {}", previous_word_fact);
+
+    let island = resolve_object(&facts, previous_word_fact.subject, Predicate::Node);
+    println!("island: {}", island);
+
+    let island_facts = collect_island_facts(&facts, island);
+    println!("island facts: {}", island_facts.len());
+
+    let island_dist0_facts:Vec<Fact> = island_facts
+        .iter()
+        .filter(|x| resolve_word_distance(&facts, x.subject).get_integer_literal() == 0)
+        .copied()
+        .collect();
+    let island_dist0_fact = *(island_dist0_facts.first().unwrap());
+
+    let previous_word = literal_to_string(resolve_literal(&facts, island_dist0_fact.subject, Predicate::Word));
+    println!("previous word: {}", previous_word);
+}
@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use format::{ParseError, predicate_name};
+use hierarchy::PredicateHierarchy;
+use literal;
+use range::NumericRange;
+use store::{FactPattern, FactStore};
+use {Fact, LiteralValue, ObjectValue, Predicate, object_to_string};
+
+// A position in a triple pattern: either fixed or a `?variable` to bind.
+#[derive(Clone)]
+pub enum Term {
+    Variable(String),
+    Id(i64),
+    Predicate(Predicate),
+    // a name with several predicates at or below it in the hierarchy
+    Predicates(Vec<Predicate>),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Boolean(bool),
+    Date(i64),
+    DateTime(i64),
+    LangText(String, String),
+    Typed(String, String),
+    // any integer or float within the range
+    Range(NumericRange)
+}
+
+#[derive(Clone)]
+pub struct Pattern {
+    pub subject: Term,
+    pub predicate: Term,
+    pub object: Term
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum Value<'a> {
+    Object(ObjectValue<'a>),
+    Predicate(Predicate)
+}
+
+impl<'a> fmt::Display for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Object(ObjectValue::Id(id)) => write!(f, ":n{}", id),
+            Value::Object(ObjectValue::Literal(LiteralValue::Text(s))) => write!(f, "\"{}\"", s),
+            Value::Object(ObjectValue::Literal(LiteralValue::LangText(s, language))) => write!(f, "\"{}\"@{}", s, language),
+            Value::Object(ObjectValue::Literal(LiteralValue::Typed(lexical, datatype))) => write!(f, "\"{}\"^^<{}>", lexical, datatype),
+            Value::Object(ref object) => write!(f, "{}", object_to_string(object)),
+            Value::Predicate(p) => write!(f, ":{}", predicate_name(p))
+        }
+    }
+}
+
+pub type Binding<'a> = HashMap<String, Value<'a>>;
+
+fn term_matches(term: &Term, value: Value) -> bool {
+    return match (term, value) {
+        (Term::Variable(_), _) => true,
+        (Term::Id(a), Value::Object(ObjectValue::Id(b))) => *a == b,
+        (Term::Predicate(a), Value::Predicate(b)) => *a == b,
+        (Term::Predicates(family), Value::Predicate(b)) => family.contains(&b),
+        (Term::Integer(a), Value::Object(ObjectValue::Literal(LiteralValue::Integer(b)))) => *a == b,
+        (Term::Float(a), Value::Object(ObjectValue::Literal(LiteralValue::Float(b)))) => *a == b,
+        (Term::Text(a), Value::Object(ObjectValue::Literal(LiteralValue::Text(b)))) => a == b,
+        (Term::Boolean(a), Value::Object(ObjectValue::Literal(LiteralValue::Boolean(b)))) => *a == b,
+        (Term::Date(a), Value::Object(ObjectValue::Literal(LiteralValue::Date(b)))) => *a == b,
+        (Term::DateTime(a), Value::Object(ObjectValue::Literal(LiteralValue::DateTime(b)))) => *a == b,
+        (Term::LangText(a, x), Value::Object(ObjectValue::Literal(LiteralValue::LangText(b, y)))) => a == b && x == y,
+        (Term::Typed(a, x), Value::Object(ObjectValue::Literal(LiteralValue::Typed(b, y)))) => a == b && x == y,
+        (Term::Range(range), Value::Object(object)) => range.contains_object(object),
+        _ => false
+    }
+}
+
+// Binds `term` to `value` within `binding`, failing on a conflicting earlier binding.
+fn unify<'a>(term: &Term, value: Value<'a>, binding: &mut Binding<'a>) -> bool {
+    if let Term::Variable(ref name) = *term {
+        if let Some(bound) = binding.get(name) {
+            return *bound == value;
+        }
+        binding.insert(name.clone(), value);
+        return true;
+    }
+    return term_matches(term, value);
+}
+
+fn match_fact<'a>(pattern: &Pattern, fact: &Fact<'a>, binding: &Binding<'a>) -> Option<Binding<'a>> {
+    let mut extended = binding.clone();
+    if unify(&pattern.subject, Value::Object(ObjectValue::Id(fact.subject)), &mut extended)
+        && unify(&pattern.predicate, Value::Predicate(fact.predicate), &mut extended)
+        && unify(&pattern.object, Value::Object(fact.object), &mut extended) {
+        return Some(extended);
+    }
+    return None;
+}
+
+fn bound_id(term: &Term, binding: &Binding) -> Option<i64> {
+    return match *term {
+        Term::Id(id) => Some(id),
+        Term::Variable(ref name) => match binding.get(name) {
+            Some(&Value::Object(ObjectValue::Id(id))) => Some(id),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+fn bound_predicate(term: &Term, binding: &Binding) -> Option<Predicate> {
+    return match *term {
+        Term::Predicate(p) => Some(p),
+        Term::Variable(ref name) => match binding.get(name) {
+            Some(&Value::Predicate(p)) => Some(p),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+// Extends every binding with the facts of `store` that match `pattern`. Whatever part
+// of the pattern is already known is handed to the store, so indexed backends avoid a
+// full scan; a family of predicates is asked for one predicate at a time.
+pub fn extend<'a, S: FactStore<'a>>(store: &S, pattern: &Pattern, bindings: &[Binding<'a>]) -> Vec<Binding<'a>> {
+    let mut next = Vec::new();
+    for binding in bindings.iter() {
+        let predicates = match pattern.predicate {
+            Term::Predicates(ref family) => family.iter().cloned().map(Some).collect(),
+            _ => vec![bound_predicate(&pattern.predicate, binding)]
+        };
+        for predicate in predicates {
+            let known = FactPattern {
+                subject: bound_id(&pattern.subject, binding),
+                predicate: predicate,
+                object: bound_id(&pattern.object, binding).map(ObjectValue::Id),
+                range: match pattern.object {
+                    Term::Range(range) => Some(range),
+                    _ => None
+                }
+            };
+            for fact in store.match_pattern(&known).iter() {
+                if let Some(extended) = match_fact(pattern, fact, binding) {
+                    next.push(extended);
+                }
+            }
+        }
+    }
+    return next;
+}
+
+// Evaluates a conjunction of patterns in pattern order.
+pub fn evaluate<'a, S: FactStore<'a>>(store: &S, patterns: &[Pattern]) -> Vec<Binding<'a>> {
+    let mut bindings: Vec<Binding<'a>> = vec![Binding::new()];
+    for pattern in patterns {
+        bindings = extend(store, pattern, &bindings);
+    }
+    return bindings;
+}
+
+pub fn variables(patterns: &[Pattern]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for pattern in patterns {
+        for term in [&pattern.subject, &pattern.predicate, &pattern.object].iter() {
+            if let Term::Variable(ref name) = **term {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+    }
+    return names;
+}
+
+// `low..high` with both ends included, or `<x`, `<=x`, `>x`, `>=x`.
+fn parse_range(token: &str) -> Option<NumericRange> {
+    let number = |text: &str| text.parse::<f64>().ok().filter(|x| !x.is_nan());
+    for &(prefix, make) in [
+        ("<=", NumericRange::at_most as fn(f64) -> NumericRange),
+        (">=", NumericRange::at_least),
+        ("<", NumericRange::less_than),
+        (">", NumericRange::greater_than)
+    ].iter() {
+        if let Some(rest) = token.strip_prefix(prefix) {
+            return number(rest).map(make);
+        }
+    }
+    let dots = token.find("..")?;
+    return Some(NumericRange::between(number(&token[..dots])?, number(&token[dots + 2..])?));
+}
+
+fn parse_term(token: &str, line: usize, hierarchy: &PredicateHierarchy) -> Result<Term, ParseError> {
+    let error = |message: String| Err(ParseError { line: line, message: message });
+
+    if token.starts_with('?') && token.len() > 1 {
+        return Ok(Term::Variable(token[1..].to_string()));
+    }
+    if token.starts_with(":n") && token[2..].parse::<i64>().is_ok() {
+        return Ok(Term::Id(token[2..].parse().unwrap()));
+    }
+    if token.starts_with("_:n") && token[3..].parse::<i64>().is_ok() {
+        return Ok(Term::Id(token[3..].parse().unwrap()));
+    }
+    if let Some(name) = token.strip_prefix(':') {
+        return match hierarchy.family(name) {
+            Some(ref family) if family.len() == 1 => Ok(Term::Predicate(family[0])),
+            Some(family) => Ok(Term::Predicates(family)),
+            None => error(format!("unknown name '{}'", token))
+        }
+    }
+    if let Some(quoted) = token.strip_prefix('"') {
+        let end = match quoted.find('"') {
+            Some(end) => end,
+            None => { return error(format!("unterminated string {}", token)); }
+        };
+        let (text, suffix) = (&quoted[..end], &quoted[end + 1..]);
+        if suffix.is_empty() {
+            return Ok(Term::Text(text.to_string()));
+        }
+        if suffix.starts_with('@') && suffix.len() > 1 {
+            return Ok(Term::LangText(text.to_string(), suffix[1..].to_string()));
+        }
+        if suffix.starts_with("^^<") && suffix.ends_with('>') {
+            return match literal::typed_literal(text, &suffix[3..suffix.len() - 1]) {
+                Ok(LiteralValue::Boolean(b)) => Ok(Term::Boolean(b)),
+                Ok(LiteralValue::Date(days)) => Ok(Term::Date(days)),
+                Ok(LiteralValue::DateTime(millis)) => Ok(Term::DateTime(millis)),
+                Ok(_) => Ok(Term::Typed(text.to_string(), suffix[3..suffix.len() - 1].to_string())),
+                Err(message) => error(message)
+            }
+        }
+        return error(format!("cannot read literal {}", token));
+    }
+    if token == "true" || token == "false" {
+        return Ok(Term::Boolean(token == "true"));
+    }
+    if let Some(range) = parse_range(token) {
+        return Ok(Term::Range(range));
+    }
+    if let Ok(i) = token.parse::<i64>() {
+        return Ok(Term::Integer(i));
+    }
+    if let Ok(f) = token.parse::<f64>() {
+        return Ok(Term::Float(f));
+    }
+    return error(format!("cannot read term '{}'", token));
+}
+
+// The first `#` outside a string or an `<iri>`.
+fn comment_start(line: &str) -> Option<usize> {
+    let (mut quoted, mut bracketed) = (false, false);
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => { quoted = !quoted; }
+            '<' if !quoted && line[..i].ends_with("^^") => { bracketed = true; }
+            '>' if !quoted => { bracketed = false; }
+            '#' if !quoted && !bracketed => { return Some(i); }
+            _ => {}
+        }
+    }
+    return None;
+}
+
+// Splits on whitespace, keeping double-quoted strings (which may contain spaces) whole.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if c.is_whitespace() && !quoted {
+            if !current.is_empty() {
+                tokens.push(current.clone());
+                current.clear();
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    return tokens;
+}
+
+// Reads patterns written as `subject predicate object .`, one or more per line.
+// `#` starts a comment; the final `.` of a line may be omitted.
+pub fn parse_patterns(text: &str) -> Result<Vec<Pattern>, ParseError> {
+    return parse_patterns_in(text, &PredicateHierarchy::new());
+}
+
+// `parse_patterns`, with a predicate name such as `:link` standing for every predicate
+// at or below it in `hierarchy`.
+pub fn parse_patterns_in(text: &str, hierarchy: &PredicateHierarchy) -> Result<Vec<Pattern>, ParseError> {
+    let mut patterns = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = match comment_start(line) {
+            Some(comment) => &line[..comment],
+            None => line
+        };
+        let mut terms: Vec<Term> = Vec::new();
+        for token in tokenize(line) {
+            if token == "." {
+                if !terms.is_empty() {
+                    return Err(ParseError { line: index + 1, message: "pattern needs three terms".to_string() });
+                }
+                continue;
+            }
+            let closes = token.len() > 1 && token.ends_with('.');
+            let token = if closes { &token[..token.len() - 1] } else { &token[..] };
+            terms.push(parse_term(token, index + 1, hierarchy)?);
+            if terms.len() == 3 {
+                patterns.push(Pattern { object: terms.pop().unwrap(), predicate: terms.pop().unwrap(), subject: terms.pop().unwrap() });
+            } else if closes {
+                return Err(ParseError { line: index + 1, message: "pattern needs three terms".to_string() });
+            }
+        }
+        if !terms.is_empty() {
+            return Err(ParseError { line: index + 1, message: "pattern needs three terms".to_string() });
+        }
+    }
+    return Ok(patterns);
+}
+
+#[test]
+fn finds_word_following_contrasts() {
+    let facts = ::parse(::EXAMPLE);
+    let patterns = parse_patterns("
+        ?a :word \"contrasts\" .
+        ?a :dist 0 .
+        ?a :node ?island .
+        ?b :node ?island .
+        ?b :dist 1 .
+        ?b :word ?next .
+    ").unwrap();
+
+    let results = evaluate(&facts, &patterns);
+
+    assert!(!results.is_empty());
+    for result in results.iter() {
+        assert_eq!("\"with\"", result["next"].to_string());
+    }
+    assert_eq!(vec!["a", "island", "b", "next"], variables(&patterns));
+}
+
+#[test]
+fn variables_join_on_quoted_facts() {
+    let quoted = |subject: i64, id: i64| Fact { subject: subject, predicate: Predicate::Follows, object: ObjectValue::Quoted(id), graph: None };
+    let facts = vec![quoted(1, 100), quoted(2, 100), quoted(3, 200), Fact::new_object_fact(4, Predicate::Follows, 100)];
+    let results = evaluate(&facts, &parse_patterns("?a :follows ?q . ?b :follows ?q").unwrap());
+
+    // 1 and 2 pair up both ways and with themselves; 3 and 4 only with themselves, as
+    // the id 100 is not the quoted fact 100
+    assert_eq!(6, results.len());
+    assert!(results.iter().any(|b| b["a"] != b["b"] && b["q"] == Value::Object(ObjectValue::Quoted(100))));
+}
+
+#[test]
+fn rejects_incomplete_pattern() {
+    let err = parse_patterns("?a :word \"x\" .\n?a :dist").err().unwrap();
+    assert_eq!(2, err.line);
+}
+
+#[test]
+fn matches_booleans_dates_and_language_tags() {
+    let facts = vec![
+        Fact::new_literal_fact(1, Predicate::Word, LiteralValue::Boolean(true)),
+        Fact::new_literal_fact(2, Predicate::Word, LiteralValue::Date(18322)),
+        Fact::new_literal_fact(3, Predicate::Word, LiteralValue::LangText("dad", "en")),
+        Fact::new_text_fact(4, Predicate::Word, "dad")
+    ];
+    let query = |text: &str| evaluate(&facts, &parse_patterns(text).unwrap()).len();
+
+    assert_eq!(1, query("?s :word true"));
+    assert_eq!(1, query("?s :word \"2020-03-01\"^^<http://www.w3.org/2001/XMLSchema#date> # a date"));
+    assert_eq!(1, query("?s :word \"dad\"@en"));
+    assert_eq!(1, query("?s :word \"dad\""));
+    assert!(parse_patterns("?s :word \"soon\"^^<http://www.w3.org/2001/XMLSchema#date>").is_err());
+}
+
+#[test]
+fn range_terms_use_the_numeric_index() {
+    let facts = ::parse("dad went fishing on sunday");
+    let store = ::store::IndexedStore::from_facts(facts.clone());
+    let count = |text: &str| evaluate(&store, &parse_patterns(text).unwrap()).len();
+
+    let near = facts.iter().filter(|f| match f.object {
+        ObjectValue::Literal(LiteralValue::Integer(d)) => d.abs() <= 1,
+        _ => false
+    }).count();
+    assert_eq!(near, count("?s :dist -1..1"));
+    assert_eq!(count("?s :dist -2..2"), count("?s :dist >=-2 # all of them"));
+    assert_eq!(count("?s :dist 1..2"), count("?s :dist >0"));
+    assert_eq!(count("?s :dist <0"), count("?s :dist <=-1."));
+    assert_eq!(0, count("?s :word >0"));
+}
+
+#[test]
+fn names_match_their_sub_predicates() {
+    let facts = ::parse("dad went fishing on sunday");
+    let store = ::store::IndexedStore::from_facts(facts.clone());
+    let count = |text: &str| evaluate(&store, &parse_patterns(text).unwrap()).len();
+
+    assert_eq!(count("?s :node ?o"), count("?s :link ?o"));
+    assert_eq!(count("?s :dist ?o"), count("?s :position.dist ?o"));
+    assert!(parse_patterns("?s :link.dist ?o").is_err());
+
+    let mut hierarchy = PredicateHierarchy::new();
+    hierarchy.place(Predicate::Distance, "link.dist");
+    let links = evaluate(&store, &parse_patterns_in("?s :link ?o", &hierarchy).unwrap());
+    assert_eq!(count("?s :node ?o") + count("?s :dist ?o"), links.len());
+    assert!(links.iter().any(|b| b["o"] == Value::Object(ObjectValue::Literal(LiteralValue::Integer(0)))));
+}
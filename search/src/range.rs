@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::ops::Bound;
+
+use {Fact, LiteralValue, ObjectValue, Predicate};
+
+// Bounds on a numeric literal. Integers and floats are both compared as f64.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct NumericRange {
+    pub low: Bound<f64>,
+    pub high: Bound<f64>
+}
+
+impl NumericRange {
+    // Both ends included.
+    pub fn between(low: f64, high: f64) -> NumericRange {
+        return NumericRange { low: Bound::Included(low), high: Bound::Included(high) };
+    }
+
+    pub fn less_than(value: f64) -> NumericRange {
+        return NumericRange { low: Bound::Unbounded, high: Bound::Excluded(value) };
+    }
+
+    pub fn at_most(value: f64) -> NumericRange {
+        return NumericRange { low: Bound::Unbounded, high: Bound::Included(value) };
+    }
+
+    pub fn greater_than(value: f64) -> NumericRange {
+        return NumericRange { low: Bound::Excluded(value), high: Bound::Unbounded };
+    }
+
+    pub fn at_least(value: f64) -> NumericRange {
+        return NumericRange { low: Bound::Included(value), high: Bound::Unbounded };
+    }
+
+    fn above_low(&self, value: f64) -> bool {
+        return match self.low {
+            Bound::Included(low) => value >= low,
+            Bound::Excluded(low) => value > low,
+            Bound::Unbounded => true
+        }
+    }
+
+    fn below_high(&self, value: f64) -> bool {
+        return match self.high {
+            Bound::Included(high) => value <= high,
+            Bound::Excluded(high) => value < high,
+            Bound::Unbounded => true
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        return self.above_low(value) && self.below_high(value);
+    }
+
+    pub fn contains_object(&self, object: ObjectValue) -> bool {
+        return numeric_key(object).is_some_and(|key| self.contains(key));
+    }
+}
+
+// The sort key of an integer or float object. NaN has no place in a range and gets none.
+pub fn numeric_key(object: ObjectValue) -> Option<f64> {
+    let key = match object {
+        ObjectValue::Literal(LiteralValue::Integer(i)) => i as f64,
+        ObjectValue::Literal(LiteralValue::Float(f)) if !f.is_nan() => f,
+        _ => { return None; }
+    };
+    // folds -0.0 into 0.0
+    return Some(key + 0.0);
+}
+
+// Numeric facts per predicate, kept sorted by value so a range is two binary searches
+// and a slice. Positions refer to the owner's fact storage.
+struct Column {
+    keys: Vec<f64>,
+    positions: Vec<usize>
+}
+
+impl Column {
+    fn slot(&self, key: f64, position: usize) -> Result<usize, usize> {
+        let start = self.keys.partition_point(|k| *k < key);
+        let end = start + self.keys[start..].partition_point(|k| *k == key);
+        return match self.positions[start..end].binary_search(&position) {
+            Ok(i) => Ok(start + i),
+            Err(i) => Err(start + i)
+        }
+    }
+}
+
+pub struct RangeIndex {
+    columns: HashMap<Predicate, Column>
+}
+
+impl RangeIndex {
+    pub fn new() -> RangeIndex {
+        return RangeIndex { columns: HashMap::new() };
+    }
+
+    // Builds the index in one sort instead of one insertion per fact.
+    pub fn from_positions<'f, 'a: 'f, I>(facts: I) -> RangeIndex where I: IntoIterator<Item = (usize, &'f Fact<'a>)> {
+        let mut entries: HashMap<Predicate, Vec<(f64, usize)>> = HashMap::new();
+        for (position, fact) in facts {
+            if let Some(key) = numeric_key(fact.object) {
+                entries.entry(fact.predicate).or_default().push((key, position));
+            }
+        }
+        let mut index = RangeIndex::new();
+        for (predicate, mut list) in entries {
+            list.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            index.columns.insert(predicate, Column {
+                keys: list.iter().map(|e| e.0).collect(),
+                positions: list.iter().map(|e| e.1).collect()
+            });
+        }
+        return index;
+    }
+
+    pub fn insert(&mut self, fact: &Fact, position: usize) {
+        let key = match numeric_key(fact.object) {
+            Some(key) => key,
+            None => { return; }
+        };
+        let column = self.columns.entry(fact.predicate).or_insert_with(|| Column { keys: Vec::new(), positions: Vec::new() });
+        if let Err(slot) = column.slot(key, position) {
+            column.keys.insert(slot, key);
+            column.positions.insert(slot, position);
+        }
+    }
+
+    pub fn remove(&mut self, fact: &Fact, position: usize) {
+        let key = match numeric_key(fact.object) {
+            Some(key) => key,
+            None => { return; }
+        };
+        if let Some(column) = self.columns.get_mut(&fact.predicate) {
+            if let Ok(slot) = column.slot(key, position) {
+                column.keys.remove(slot);
+                column.positions.remove(slot);
+            }
+        }
+    }
+
+    // Positions of the facts of `predicate` whose value lies in `range`, in value order.
+    pub fn range(&self, predicate: Predicate, range: &NumericRange) -> &[usize] {
+        let column = match self.columns.get(&predicate) {
+            Some(column) => column,
+            None => { return &[]; }
+        };
+        let start = column.keys.partition_point(|k| !range.above_low(*k));
+        let end = start + column.keys[start..].partition_point(|k| range.below_high(*k));
+        return &column.positions[start..end];
+    }
+
+    pub fn less_than(&self, predicate: Predicate, value: f64) -> &[usize] {
+        return self.range(predicate, &NumericRange::less_than(value));
+    }
+
+    pub fn greater_than(&self, predicate: Predicate, value: f64) -> &[usize] {
+        return self.range(predicate, &NumericRange::greater_than(value));
+    }
+
+    pub fn len(&self) -> usize {
+        return self.columns.values().map(|c| c.keys.len()).sum();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.columns.values().all(|c| c.keys.is_empty());
+    }
+}
+
+#[test]
+fn ranges_are_contiguous_slices_of_the_sorted_values() {
+    let facts = [
+        Fact::new_integer_fact(1, Predicate::Distance, 2),
+        Fact::new_integer_fact(2, Predicate::Distance, -1),
+        Fact::new_float_fact(3, Predicate::Distance, 0.5),
+        Fact::new_float_fact(4, Predicate::Distance, f64::NAN),
+        Fact::new_integer_fact(5, Predicate::Node, 0),
+        Fact::new_text_fact(6, Predicate::Distance, "1"),
+        Fact::new_float_fact(7, Predicate::Distance, -0.0)
+    ];
+    let mut index = RangeIndex::from_positions(facts.iter().enumerate());
+    assert_eq!(5, index.len());
+
+    assert_eq!(&[1, 6, 2], index.range(Predicate::Distance, &NumericRange::between(-1.0, 1.0)));
+    assert_eq!(&[6, 2, 0], index.range(Predicate::Distance, &NumericRange::at_least(0.0)));
+    assert_eq!(&[1], index.less_than(Predicate::Distance, 0.0));
+    assert_eq!(&[0], index.greater_than(Predicate::Distance, 0.5));
+    assert!(index.range(Predicate::Word, &NumericRange::at_least(0.0)).is_empty());
+
+    index.remove(&facts[6], 6);
+    index.insert(&Fact::new_integer_fact(8, Predicate::Distance, 0), 8);
+    assert_eq!(&[8, 2], index.range(Predicate::Distance, &NumericRange::between(0.0, 1.0)));
+}
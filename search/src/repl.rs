@@ -0,0 +1,204 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use format::predicate_name;
+use predict::Islands;
+use query;
+use store::{FactStore, IndexedStore};
+use {Fact, Predicate};
+
+const HELP: &'static str = "\
+commands:
+  subject <id>          show all facts about a subject
+  islands <word>        list islands containing a word
+  next <words...>       predict the word after <words>
+  prev <words...>       predict the word before <words>
+  query <patterns>      evaluate triple patterns, e.g. query ?a :word \"union\" . ?a :dist ?d
+  stats                 print counts for the loaded facts
+  history               list previous commands
+  help                  show this message
+  quit                  leave the shell";
+
+pub struct Repl<'a> {
+    facts: &'a [Fact<'a>],
+    islands: Islands<'a>,
+    store: IndexedStore<'a>,
+    history: Vec<String>,
+    history_file: Option<PathBuf>
+}
+
+impl<'a> Repl<'a> {
+    pub fn new(facts: &'a [Fact<'a>]) -> Repl<'a> {
+        let store = IndexedStore::from_facts(facts.iter().cloned());
+        return Repl {
+            facts: facts,
+            islands: Islands::from_store(&store),
+            store: store,
+            history: Vec::new(),
+            history_file: None
+        }
+    }
+
+    // Loads earlier commands from `path` and appends every new one to it.
+    pub fn with_history_file(mut self, path: PathBuf) -> Repl<'a> {
+        let mut text = String::new();
+        if let Ok(mut file) = ::std::fs::File::open(&path) {
+            let _ = file.read_to_string(&mut text);
+        }
+        self.history = text.lines().map(|l| l.to_string()).collect();
+        self.history_file = Some(path);
+        return self;
+    }
+
+    fn remember(&mut self, line: &str) {
+        self.history.push(line.to_string());
+        if let Some(ref path) = self.history_file {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn show_subject<W: Write>(&self, subject: i64, out: &mut W) -> io::Result<()> {
+        let facts = self.store.subject_facts(subject);
+        if facts.is_empty() {
+            return writeln!(out, "no facts about {}", subject);
+        }
+        for fact in facts.iter() {
+            writeln!(out, "{}", fact)?;
+        }
+        return Ok(());
+    }
+
+    fn show_islands<W: Write>(&self, word: &str, out: &mut W) -> io::Result<()> {
+        let found = self.islands.containing(word);
+        if found.is_empty() {
+            return writeln!(out, "no island contains '{}'", word);
+        }
+        for (island, distance) in found {
+            let words: Vec<String> = self.islands.members(island).iter()
+                .map(|slot| format!("{}({})", slot.word, slot.distance))
+                .collect();
+            writeln!(out, "island {} at {}: {}", island, distance, words.join(" "))?;
+        }
+        return Ok(());
+    }
+
+    fn show_predictions<W: Write>(&self, words: &[&str], previous: bool, out: &mut W) -> io::Result<()> {
+        let ranked = match previous {
+            true => self.islands.previous_words(words, 10),
+            false => self.islands.next_words(words, 10)
+        };
+        if ranked.is_empty() {
+            return writeln!(out, "no prediction for '{}'", words.join(" "));
+        }
+        for (word, count) in ranked {
+            writeln!(out, "{}\t{}", word, count)?;
+        }
+        return Ok(());
+    }
+
+    fn show_query<W: Write>(&self, text: &str, out: &mut W) -> io::Result<()> {
+        let patterns = match query::parse_patterns(text) {
+            Ok(patterns) => patterns,
+            Err(err) => { return writeln!(out, "query error: {}", err.message); }
+        };
+        let variables = query::variables(&patterns);
+        let results = query::evaluate(&self.store, &patterns);
+        for binding in results.iter() {
+            let row: Vec<String> = variables.iter().map(|v| format!("?{} = {}", v, binding[v])).collect();
+            writeln!(out, "{}", row.join(", "))?;
+        }
+        return writeln!(out, "{} result(s)", results.len());
+    }
+
+    fn show_stats<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "facts: {}", self.facts.len())?;
+        for predicate in [Predicate::Word, Predicate::Distance, Predicate::Node].iter() {
+            let count = self.facts.iter().filter(|f| f.predicate == *predicate).count();
+            writeln!(out, "  :{} {}", predicate_name(*predicate), count)?;
+        }
+        writeln!(out, "subjects: {}", self.store.subject_count())?;
+        writeln!(out, "islands: {}", self.islands.len())?;
+        return writeln!(out, "vocabulary: {}", self.islands.vocabulary());
+    }
+
+    // Runs one command line; returns false when the user asked to leave.
+    pub fn execute<W: Write>(&mut self, line: &str, out: &mut W) -> io::Result<bool> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(true);
+        }
+        self.remember(line);
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let argument = line[words[0].len()..].trim();
+        match words[0] {
+            "subject" | "s" => match argument.trim_start_matches(":n").parse() {
+                Ok(subject) => self.show_subject(subject, out)?,
+                Err(_) => writeln!(out, "usage: subject <id>")?
+            },
+            "islands" | "i" if words.len() == 2 => self.show_islands(words[1], out)?,
+            "next" | "n" if words.len() > 1 => self.show_predictions(&words[1..], false, out)?,
+            "prev" | "p" if words.len() > 1 => self.show_predictions(&words[1..], true, out)?,
+            "query" | "q" => self.show_query(argument, out)?,
+            "stats" => self.show_stats(out)?,
+            "history" => for (index, entry) in self.history.iter().enumerate() {
+                writeln!(out, "{:5}  {}", index + 1, entry)?;
+            },
+            "help" | "?" => writeln!(out, "{}", HELP)?,
+            "quit" | "exit" => { return Ok(false); }
+            _ => writeln!(out, "unknown command '{}', try help", line)?
+        }
+        return Ok(true);
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: &mut R, out: &mut W) -> io::Result<()> {
+        let mut line = String::new();
+        loop {
+            write!(out, "> ")?;
+            out.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            if !self.execute(&line, out)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[test]
+fn repl_answers_commands_in_fact_display_format() {
+    let facts = ::parse("dad went fishing with his son on a sunny day");
+    let mut repl = Repl::new(&facts);
+    let mut out: Vec<u8> = Vec::new();
+    let mut input = "islands fishing\nnext with\nsubject 999999999\nhistory\nquit\nstats\n".as_bytes();
+
+    repl.run(&mut input, &mut out).unwrap();
+
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("island "));
+    assert!(out.contains("his\t1"));
+    assert!(out.contains("no facts about 999999999"));
+    assert!(out.contains("    2  next with"));
+    assert!(!out.contains("vocabulary"));
+}
+
+#[test]
+fn repl_prints_subject_facts_and_query_results() {
+    let facts = ::parse("dad went fishing with his son");
+    let subject = facts[0].subject;
+    let mut repl = Repl::new(&facts);
+    let mut out: Vec<u8> = Vec::new();
+
+    repl.execute(&format!("subject {}", subject), &mut out).unwrap();
+    repl.execute("query ?a :word \"son\" . ?a :dist ?d", &mut out).unwrap();
+
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains(&format!("{}", facts[0])));
+    assert!(out.contains("?d = "));
+}
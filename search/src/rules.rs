@@ -0,0 +1,382 @@
+use std::collections::HashSet;
+
+use n3::{self, Namespaces, Statement};
+
+use format::{ParseError, NAMES, NAMESPACE};
+use hierarchy::PredicateHierarchy;
+use literal::{self, XSD_DOUBLE, XSD_INTEGER};
+use pool::StringPool;
+use query::{self, Binding, Pattern, Term, Value};
+use store::{FactStore, IndexedStore};
+use {Fact, FactSpace, LiteralValue, ObjectValue};
+
+// `{ body } => { head } .`: whenever every body pattern matches, each head pattern with
+// the body's bindings filled in is a new fact.
+#[derive(Clone)]
+pub struct Rule {
+    pub body: Vec<Pattern>,
+    pub head: Vec<Pattern>
+}
+
+// A fact a rule produced, with the index of that rule and the round it first appeared in.
+#[derive(Copy, Clone, Debug)]
+pub struct Derived<'a> {
+    pub fact: Fact<'a>,
+    pub rule: usize,
+    pub round: usize
+}
+
+pub struct Inference<'a> {
+    pub derived: Vec<Derived<'a>>,
+    // rounds that derived at least one fact
+    pub rounds: usize,
+    // false when the round limit stopped chaining before a fixpoint
+    pub complete: bool
+}
+
+impl<'a> Inference<'a> {
+    pub fn facts(&self) -> Vec<Fact<'a>> {
+        return self.derived.iter().map(|d| d.fact).collect();
+    }
+
+    pub fn from_rule(&self, rule: usize) -> Vec<Fact<'a>> {
+        return self.derived.iter().filter(|d| d.rule == rule).map(|d| d.fact).collect();
+    }
+}
+
+pub struct Reasoner {
+    rules: Vec<Rule>,
+    max_rounds: Option<usize>
+}
+
+fn error<T>(line: usize, message: &str) -> Result<T, ParseError> {
+    return Err(ParseError { line: line, message: message.to_string() });
+}
+
+// Rules may only put bound variables into facts; otherwise a recursive rule could keep
+// inventing new subjects and never reach a fixpoint.
+fn check_rule(rule: &Rule, line: usize) -> Result<(), ParseError> {
+    let bound = query::variables(&rule.body);
+    for pattern in rule.head.iter() {
+        for term in [&pattern.subject, &pattern.predicate, &pattern.object].iter() {
+            match **term {
+                Term::Variable(ref name) if !bound.contains(name) => {
+                    return error(line, &format!("?{} in the head is not bound by the body", name));
+                }
+                Term::Range(_) => { return error(line, "a range cannot be a fact"); }
+                _ => {}
+            }
+        }
+        match pattern.subject {
+            Term::Variable(_) | Term::Id(_) => {}
+            _ => { return error(line, "the subject of a fact must be a node"); }
+        }
+        match pattern.predicate {
+            Term::Variable(_) | Term::Predicate(_) => {}
+            _ => { return error(line, "the predicate of a fact must be a name"); }
+        }
+    }
+    return Ok(());
+}
+
+// Turns the terms of parsed n3 rules into pattern terms. `quantified` holds the names
+// @forAll and @forSome declare, which are variables like ?x.
+struct RuleReader {
+    namespaces: Namespaces,
+    hierarchy: PredicateHierarchy
+}
+
+impl RuleReader {
+    fn term(&self, term: &n3::Term, quantified: &[n3::Term], in_body: bool) -> Result<Term, String> {
+        match *term {
+            n3::Term::Variable(ref name) => { return Ok(Term::Variable(name.clone())); }
+            _ if quantified.contains(term) => { return Ok(Term::Variable(term.to_string())); }
+            n3::Term::Blank(ref label) => {
+                if label.starts_with('n') && label[1..].parse::<i64>().is_ok() {
+                    return Ok(Term::Id(label[1..].parse().unwrap()));
+                }
+                // a blank node in the body matches any node, as a variable would; in the
+                // head it would be a new node
+                return match in_body {
+                    true => Ok(Term::Variable(term.to_string())),
+                    false => Err(format!("{} in the head would be a new node", term))
+                }
+            }
+            n3::Term::Integer(i) => { return Ok(Term::Integer(i)); }
+            n3::Term::Decimal(f) => { return Ok(Term::Float(f)); }
+            n3::Term::Boolean(b) => { return Ok(Term::Boolean(b)); }
+            n3::Term::Text(ref s, None) => { return Ok(Term::Text(s.clone())); }
+            n3::Term::Text(ref s, Some(ref language)) => { return Ok(Term::LangText(s.clone(), language.clone())); }
+            n3::Term::Typed(ref lexical, ref datatype) => {
+                let datatype = match self.namespaces.term(datatype) {
+                    Some(datatype) => datatype,
+                    None => { return Err(format!("unknown prefix in datatype {}", datatype)); }
+                };
+                if datatype == XSD_INTEGER {
+                    return lexical.parse().map(Term::Integer).map_err(|_| "bad integer literal".to_string());
+                }
+                if datatype == XSD_DOUBLE {
+                    return lexical.parse().map(Term::Float).map_err(|_| "bad double literal".to_string());
+                }
+                return match literal::typed_literal(lexical, &datatype)? {
+                    LiteralValue::Boolean(b) => Ok(Term::Boolean(b)),
+                    LiteralValue::Date(days) => Ok(Term::Date(days)),
+                    LiteralValue::DateTime(millis) => Ok(Term::DateTime(millis)),
+                    _ => Ok(Term::Typed(lexical.clone(), datatype.clone()))
+                }
+            }
+            _ => {}
+        }
+        let iri = match self.namespaces.term(term) {
+            Some(ref iri) if iri.starts_with(NAMESPACE) && !iri.starts_with(NAMES) => iri[NAMESPACE.len()..].to_string(),
+            Some(ref iri) if iri.starts_with(NAMES) => { return Err(format!("{} is named; rules only use numbered nodes", term)); }
+            _ => { return Err(format!("{} cannot be used in a rule", term)); }
+        };
+        if iri.starts_with('n') && iri[1..].parse::<i64>().is_ok() {
+            return Ok(Term::Id(iri[1..].parse().unwrap()));
+        }
+        return match self.hierarchy.family(&iri) {
+            Some(ref family) if family.len() == 1 => Ok(Term::Predicate(family[0])),
+            Some(family) => Ok(Term::Predicates(family)),
+            None => Err(format!("unknown name {}", term))
+        }
+    }
+
+    fn patterns(&self, formula: &n3::Formula, quantified: &[n3::Term], in_body: bool) -> Result<Vec<Pattern>, String> {
+        let mut patterns = Vec::new();
+        for triple in formula.triples() {
+            patterns.push(Pattern {
+                subject: self.term(&triple.subject, quantified, in_body)?,
+                predicate: self.term(&triple.predicate, quantified, in_body)?,
+                object: self.term(&triple.object, quantified, in_body)?
+            });
+        }
+        return Ok(patterns);
+    }
+
+    fn rule(&self, rule: &n3::Rule) -> Result<Rule, String> {
+        let mut quantified = rule.universals.clone();
+        for formula in [&rule.body, &rule.head].iter() {
+            quantified.extend(formula.universals().into_iter().cloned());
+            quantified.extend(formula.existentials().into_iter().cloned());
+        }
+        return Ok(Rule {
+            body: self.patterns(&rule.body, &quantified, true)?,
+            head: self.patterns(&rule.head, &quantified, false)?
+        });
+    }
+}
+
+// Reads N3 rules, `{ body } => { head } .`, through the n3 parser. ?variables, names
+// declared with @forAll or @forSome and blank nodes in the body are variables. Names in
+// the search namespace, which `:` stands for unless a @prefix says otherwise, are
+// predicates or, as `:n5`, numbered nodes. Anything but rules is an error.
+pub fn parse_rules(text: &str) -> Result<Vec<Rule>, ParseError> {
+    let document = n3::parse(text).map_err(|err| ParseError { line: err.span.line, message: err.message })?;
+    let mut namespaces = Namespaces::from_document(&document);
+    if namespaces.namespace("").is_none() {
+        namespaces.register("", NAMESPACE);
+    }
+    let reader = RuleReader { namespaces: namespaces, hierarchy: PredicateHierarchy::new() };
+
+    for (statement, span) in document.formula.statements.iter().zip(document.formula.spans.iter()) {
+        if let Statement::Graph(..) = *statement {
+            return error(span.line, "a rules file cannot hold named graphs");
+        }
+    }
+    let mut lines = Vec::new();
+    for (triple, span) in document.formula.triples_with_spans() {
+        match (&triple.subject, &triple.object) {
+            (&n3::Term::Formula(_), &n3::Term::Formula(_)) if triple.predicate.is_implication() => { lines.push(span.line); }
+            _ => { return error(span.line, "expected '{ ... } => { ... }'"); }
+        }
+    }
+    // `Document::rules` keeps the order the implications were written in
+    let mut rules = Vec::new();
+    for (rule, line) in document.rules().iter().zip(lines) {
+        let rule = reader.rule(rule).map_err(|message| ParseError { line: line, message: message })?;
+        check_rule(&rule, line)?;
+        rules.push(rule);
+    }
+    return Ok(rules);
+}
+
+fn bound<'a>(term: &Term, binding: &Binding<'a>) -> Option<Value<'a>> {
+    return match *term {
+        Term::Variable(ref name) => binding.get(name).cloned(),
+        _ => None
+    }
+}
+
+// Fills a head pattern in. Nothing comes out when a variable holds something that cannot
+// take its place, such as a literal in subject position.
+fn instantiate<'a>(pattern: &Pattern, binding: &Binding<'a>, pool: &'a StringPool) -> Option<Fact<'a>> {
+    let subject = match (&pattern.subject, bound(&pattern.subject, binding)) {
+        (&Term::Id(id), _) | (_, Some(Value::Object(ObjectValue::Id(id)))) => id,
+        _ => { return None; }
+    };
+    let predicate = match (&pattern.predicate, bound(&pattern.predicate, binding)) {
+        (&Term::Predicate(p), _) | (_, Some(Value::Predicate(p))) => p,
+        _ => { return None; }
+    };
+    let literal = |l: LiteralValue<'a>| Some(ObjectValue::Literal(l));
+    let object = match pattern.object {
+        Term::Variable(_) => match bound(&pattern.object, binding) {
+            Some(Value::Object(object)) => Some(object),
+            _ => None
+        },
+        Term::Id(id) => Some(ObjectValue::Id(id)),
+        Term::Integer(i) => literal(LiteralValue::Integer(i)),
+        Term::Float(f) => literal(LiteralValue::Float(f)),
+        Term::Text(ref s) => literal(LiteralValue::Text(pool.intern(s))),
+        Term::Boolean(b) => literal(LiteralValue::Boolean(b)),
+        Term::Date(days) => literal(LiteralValue::Date(days)),
+        Term::DateTime(millis) => literal(LiteralValue::DateTime(millis)),
+        Term::LangText(ref s, ref language) => literal(LiteralValue::LangText(pool.intern(s), pool.intern(language))),
+        Term::Typed(ref lexical, ref datatype) => literal(LiteralValue::Typed(pool.intern(lexical), pool.intern(datatype))),
+        Term::Predicate(_) | Term::Predicates(_) | Term::Range(_) => None
+    };
+    // derived facts go in the default graph
+    return Some(Fact { subject: subject, predicate: predicate, object: object?, graph: None });
+}
+
+impl Reasoner {
+    pub fn new(rules: Vec<Rule>) -> Reasoner {
+        return Reasoner { rules: rules, max_rounds: None };
+    }
+
+    // Stops chaining after `rounds` rounds even if more facts could follow.
+    pub fn max_rounds(mut self, rounds: usize) -> Reasoner {
+        self.max_rounds = Some(rounds);
+        return self;
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        return &self.rules;
+    }
+
+    // Semi-naive forward chaining: after the first round, a rule only fires for bindings
+    // that use at least one fact derived in the round before, so old matches are not
+    // redone. Rules cannot create nodes, so the facts they can make are finite and
+    // chaining stops at a fixpoint even for recursive rules. Head strings go into `pool`.
+    pub fn infer<'a>(&self, asserted: &[Fact<'a>], pool: &'a StringPool) -> Inference<'a> {
+        let mut all = IndexedStore::from_facts(asserted.iter().cloned());
+        let mut known: HashSet<Fact<'a>> = asserted.iter().cloned().collect();
+        let mut derived: Vec<Derived<'a>> = Vec::new();
+        let mut delta: Option<IndexedStore<'a>> = None;
+        let mut round = 0;
+
+        loop {
+            if self.max_rounds == Some(round) {
+                return Inference { derived: derived, rounds: round, complete: false };
+            }
+            round = round + 1;
+            let mut fresh: Vec<Fact<'a>> = Vec::new();
+            for (index, rule) in self.rules.iter().enumerate() {
+                let mut results: Vec<Binding<'a>> = Vec::new();
+                match delta {
+                    None => { results = query::evaluate(&all, &rule.body); }
+                    Some(ref delta) => for (i, first) in rule.body.iter().enumerate() {
+                        let mut bindings = query::extend(delta, first, &[Binding::new()]);
+                        for (j, pattern) in rule.body.iter().enumerate() {
+                            if j != i {
+                                bindings = query::extend(&all, pattern, &bindings);
+                            }
+                        }
+                        results.extend(bindings);
+                    }
+                }
+                for binding in results.iter() {
+                    for pattern in rule.head.iter() {
+                        if let Some(fact) = instantiate(pattern, binding, pool) {
+                            if known.insert(fact) {
+                                fresh.push(fact);
+                                derived.push(Derived { fact: fact, rule: index, round: round });
+                            }
+                        }
+                    }
+                }
+            }
+            if fresh.is_empty() {
+                return Inference { derived: derived, rounds: round - 1, complete: true };
+            }
+            for fact in fresh.iter() {
+                all.insert(*fact).unwrap();
+            }
+            delta = Some(IndexedStore::from_facts(fresh));
+        }
+    }
+}
+
+impl<'a> FactSpace<'a> {
+    // Derives what `rules` imply from the facts here; the space itself is left as it is.
+    pub fn infer(&self, rules: &[Rule], pool: &'a StringPool) -> Inference<'a> {
+        return Reasoner::new(rules.to_vec()).infer(self.facts(), pool);
+    }
+}
+
+#[test]
+fn derives_follows_edges_from_islands() {
+    let space = FactSpace::with_facts(::parse("a dog saw the cat and the cat saw the mat today too"));
+    let rules = parse_rules("
+        # the word one step after an island's centre follows it
+        { ?a :node ?i . ?a :dist 0 .
+          ?b :node ?i . ?b :dist 1 } => { ?a :follows ?b } .
+    ").unwrap();
+    let pool = StringPool::new();
+    let inference = space.infer(&rules, &pool);
+
+    assert!(inference.complete);
+    assert_eq!(1, inference.rounds);
+    let follows = inference.from_rule(0);
+    assert!(!follows.is_empty());
+    assert!(follows.iter().all(|f| f.predicate == ::Predicate::Follows));
+    assert_eq!(follows.len(), follows.iter().collect::<HashSet<_>>().len());
+    assert!(space.facts().iter().all(|f| f.predicate != ::Predicate::Follows));
+}
+
+#[test]
+fn recursive_rules_chain_to_a_fixpoint() {
+    let chain: Vec<Fact> = (1..5).map(|i| Fact::new_object_fact(i, ::Predicate::Follows, i + 1)).collect();
+    let rules = parse_rules("{ ?a :follows ?b . ?b :follows ?c } => { ?a :follows ?c } .").unwrap();
+    let pool = StringPool::new();
+
+    let inference = Reasoner::new(rules.clone()).infer(&chain, &pool);
+    assert!(inference.complete);
+    // 1..5 is a path of 5 nodes with 10 ordered pairs, 4 of them asserted
+    assert_eq!(6, inference.derived.len());
+    assert_eq!(2, inference.rounds);
+    assert!(inference.derived.iter().any(|d| d.round == 2 && d.fact == Fact::new_object_fact(1, ::Predicate::Follows, 5)));
+
+    let cut = Reasoner::new(rules).max_rounds(1).infer(&chain, &pool);
+    assert!(!cut.complete);
+    assert_eq!(3, cut.derived.len());
+}
+
+#[test]
+fn rejects_unbound_head_variables() {
+    let err = parse_rules("\n{ ?a :follows ?b } => { ?a :follows ?c } .").err().unwrap();
+    assert_eq!(2, err.line);
+    assert!(parse_rules("{ ?a :follows ?b } { ?b :follows ?a }").is_err());
+    assert!(parse_rules("{ ?a :follows ?b } => { ?a :follows <2 }").is_err());
+    assert!(parse_rules("{ ?a :follows ?b } => { [] :follows ?a } .").is_err());
+    assert_eq!(3, parse_rules("{ ?a :follows ?b } => { ?b :follows ?a } .\n\n:n1 :follows :n2 .").err().unwrap().line);
+}
+
+#[test]
+fn reads_prefixes_quantifiers_and_blank_nodes() {
+    let rules = parse_rules("
+        @prefix s: <urn:search:> .
+        @forAll s:a .
+        { s:a s:follows _:b . _:b s:follows [ s:word \"cat\" ] } => { s:a s:follows s:a } .
+        { @forSome :x . :n1 :follows :x } <= { :x :follows :n1 } .
+    ").unwrap();
+    assert_eq!(2, rules.len());
+    assert_eq!(3, rules[0].body.len());
+    assert_eq!(query::variables(&rules[0].head), query::variables(&rules[0].body)[..1].to_vec());
+
+    let chain = vec![Fact::new_object_fact(2, ::Predicate::Follows, 1)];
+    let pool = StringPool::new();
+    let inference = Reasoner::new(rules).infer(&chain, &pool);
+    assert_eq!(vec![Fact::new_object_fact(1, ::Predicate::Follows, 2)], inference.facts());
+}
@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+
+use literal::{self, encode_object, decode_object};
+use n3::Literal;
+use hierarchy::{PredicateHierarchy, PREDICATES};
+use pool::StringPool;
+use {Document, Fact, FactSpace, Predicate};
+
+// Layout, all integers little endian:
+//   magic "SRCHSNAP", version u32, last id i64,
+//   string count u32, then per string: byte length u32 and UTF-8 bytes,
+//   predicate count u32, then per predicate: byte length u32 and its `root.a.b` path,
+//   document count u32, then per document: id i64, subject count u32 and the subjects i64,
+//   fact count u64, then per fact: subject i64, predicate u8 (its place among the paths),
+//   object tag u8, payload u64,
+//   graph u32 (a string number, or NO_GRAPH for the default graph),
+//   fact id count u64, then per fact id: the id i64 and its fact as above without a graph,
+//   name count u64, then per named subject: the id i64, byte length u32 and `root.a.b`,
+//   FNV-1a 64 checksum of everything before it.
+// Objects with strings store string numbers in the payload, the first in the low 32 bits
+// and the second in the high 32 bits. Version 1 only had the first four object tags,
+// versions before 3 had no graphs and versions before 4 had no fact ids. Before version
+// 5 a document was its id and last subject, standing for every subject in between,
+// versions before 6 had no subject names and before 7 the predicate byte was the code
+// `predicate_code` gives, with no paths.
+pub const MAGIC: &'static [u8; 8] = b"SRCHSNAP";
+pub const VERSION: u32 = 7;
+const NO_GRAPH: u32 = 0xffffffff;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    ChecksumMismatch,
+    Corrupt(&'static str)
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::Io(ref err) => write!(f, "{}", err),
+            SnapshotError::BadMagic => write!(f, "not a snapshot file"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {}", v),
+            SnapshotError::ChecksumMismatch => write!(f, "snapshot checksum mismatch"),
+            SnapshotError::Corrupt(what) => write!(f, "corrupt snapshot: {}", what)
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        return match err.kind() {
+            io::ErrorKind::UnexpectedEof => SnapshotError::Corrupt("truncated"),
+            _ => SnapshotError::Io(err)
+        }
+    }
+}
+
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+pub fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    let mut hash = hash;
+    for byte in bytes {
+        hash = (hash ^ *byte as u64).wrapping_mul(FNV_PRIME);
+    }
+    return hash;
+}
+
+struct HashingWriter<W: Write> {
+    inner: W,
+    hash: u64
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn put(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.hash = fnv1a(self.hash, bytes);
+        return self.inner.write_all(bytes);
+    }
+}
+
+struct HashingReader<R: Read> {
+    inner: R,
+    hash: u64
+}
+
+impl<R: Read> HashingReader<R> {
+    fn take(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(bytes)?;
+        self.hash = fnv1a(self.hash, bytes);
+        return Ok(());
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.take(&mut buf)?;
+        return Ok(buf[0]);
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        self.take(&mut buf)?;
+        return Ok(u32::from_le_bytes(buf));
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut buf = [0u8; 8];
+        self.take(&mut buf)?;
+        return Ok(u64::from_le_bytes(buf));
+    }
+
+    fn i64(&mut self) -> io::Result<i64> {
+        return Ok(self.u64()? as i64);
+    }
+
+    // `len` bytes, growing the buffer only as they arrive so a corrupt length runs into
+    // the end of the input rather than allocating it all up front.
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(preallocation(len as u64));
+        (&mut self.inner).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated"));
+        }
+        self.hash = fnv1a(self.hash, &bytes);
+        return Ok(bytes);
+    }
+}
+
+// Counts are read before the checksum can vouch for them, so room for at most this many
+// items is set aside ahead of reading them.
+const PREALLOCATION_LIMIT: u64 = 1 << 16;
+
+fn preallocation(count: u64) -> usize {
+    return ::std::cmp::min(count, PREALLOCATION_LIMIT) as usize;
+}
+
+// The byte the log and column files store for `predicate`: its place in `PREDICATES`.
+pub fn predicate_code(predicate: Predicate) -> u8 {
+    return PREDICATES.iter().position(|&p| p == predicate).unwrap() as u8;
+}
+
+pub fn predicate_from_code(code: u8) -> Option<Predicate> {
+    return PREDICATES.get(code as usize).cloned();
+}
+
+// A fact's subject, predicate and object.
+fn put_fact<W: Write>(out: &mut HashingWriter<W>, fact: &Fact, string_index: &HashMap<&str, u32>) -> io::Result<()> {
+    let (tag, payload, texts) = encode_object(fact.object);
+    let payload = match literal::tag_strings(tag) {
+        0 => payload,
+        1 => string_index[texts[0]] as u64,
+        _ => string_index[texts[0]] as u64 | (string_index[texts[1]] as u64) << 32
+    };
+    out.put(&fact.subject.to_le_bytes())?;
+    // snapshots list the predicate paths in `PREDICATES` order, so the code is the place
+    out.put(&[predicate_code(fact.predicate), tag])?;
+    return out.put(&payload.to_le_bytes());
+}
+
+fn take_fact<'a, R: Read>(input: &mut HashingReader<R>, predicates: &[Predicate], strings: &[&'a str]) -> Result<Fact<'a>, SnapshotError> {
+    let subject = input.i64()?;
+    let predicate = match predicates.get(input.u8()? as usize) {
+        Some(&predicate) => predicate,
+        None => { return Err(SnapshotError::Corrupt("unknown predicate")); }
+    };
+    let tag = input.u8()?;
+    let payload = input.u64()?;
+    let mut texts = ["", ""];
+    for i in 0..literal::tag_strings(tag) {
+        texts[i] = match strings.get((payload >> (32 * i)) as u32 as usize) {
+            Some(s) => s,
+            None => { return Err(SnapshotError::Corrupt("string index out of range")); }
+        };
+    }
+    return match decode_object(tag, payload, texts) {
+        Some(object) => Ok(Fact { subject: subject, predicate: predicate, object: object, graph: None }),
+        None => Err(SnapshotError::Corrupt("unknown object tag"))
+    }
+}
+
+pub fn write_snapshot<W: Write>(writer: W, space: &FactSpace) -> io::Result<()> {
+    let fact_ids = space.fact_ids.iter();
+    let mut strings: Vec<&str> = Vec::new();
+    let mut string_index: HashMap<&str, u32> = HashMap::new();
+    for fact in space.facts().iter().chain(fact_ids.iter().map(|(_, fact)| fact)) {
+        let (tag, _, texts) = encode_object(fact.object);
+        for s in texts[..literal::tag_strings(tag)].iter().chain(fact.graph.iter()) {
+            if !string_index.contains_key(s) {
+                string_index.insert(s, strings.len() as u32);
+                strings.push(s);
+            }
+        }
+    }
+
+    let mut out = HashingWriter { inner: writer, hash: FNV_OFFSET };
+    out.put(MAGIC)?;
+    out.put(&VERSION.to_le_bytes())?;
+    out.put(&Fact::last_id().to_le_bytes())?;
+
+    out.put(&(strings.len() as u32).to_le_bytes())?;
+    for s in strings.iter() {
+        out.put(&(s.len() as u32).to_le_bytes())?;
+        out.put(s.as_bytes())?;
+    }
+
+    let hierarchy = PredicateHierarchy::new();
+    out.put(&(PREDICATES.len() as u32).to_le_bytes())?;
+    for &predicate in PREDICATES.iter() {
+        let path = hierarchy.path(predicate).unwrap();
+        out.put(&(path.len() as u32).to_le_bytes())?;
+        out.put(path.as_bytes())?;
+    }
+
+    out.put(&(space.documents().len() as u32).to_le_bytes())?;
+    for document in space.documents().iter() {
+        out.put(&document.id.to_le_bytes())?;
+        out.put(&(document.subjects.len() as u32).to_le_bytes())?;
+        for subject in document.subjects.iter() {
+            out.put(&subject.to_le_bytes())?;
+        }
+    }
+
+    out.put(&(space.facts().len() as u64).to_le_bytes())?;
+    for fact in space.facts().iter() {
+        put_fact(&mut out, fact, &string_index)?;
+        out.put(&fact.graph.map_or(NO_GRAPH, |graph| string_index[graph]).to_le_bytes())?;
+    }
+
+    out.put(&(fact_ids.len() as u64).to_le_bytes())?;
+    for &(id, ref fact) in fact_ids.iter() {
+        out.put(&id.to_le_bytes())?;
+        put_fact(&mut out, fact, &string_index)?;
+    }
+
+    let names = space.names.iter();
+    out.put(&(names.len() as u64).to_le_bytes())?;
+    for &(id, ref literal) in names.iter() {
+        let name = literal.to_string();
+        out.put(&id.to_le_bytes())?;
+        out.put(&(name.len() as u32).to_le_bytes())?;
+        out.put(name.as_bytes())?;
+    }
+
+    let checksum = out.hash;
+    out.inner.write_all(&checksum.to_le_bytes())?;
+    return out.inner.flush();
+}
+
+// Loads a snapshot into an indexed space. Strings go into `pool`; the global id counter
+// is moved past every id in the snapshot so later ingests do not reuse them.
+pub fn read_snapshot<'a, R: Read>(reader: R, pool: &'a StringPool) -> Result<FactSpace<'a>, SnapshotError> {
+    let mut input = HashingReader { inner: reader, hash: FNV_OFFSET };
+
+    let mut magic = [0u8; 8];
+    input.take(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(SnapshotError::BadMagic);
+    }
+    let version = input.u32()?;
+    if version == 0 || version > VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let last_id = input.i64()?;
+
+    let string_count = input.u32()? as usize;
+    let mut strings: Vec<&'a str> = Vec::with_capacity(preallocation(string_count as u64));
+    for _ in 0..string_count {
+        let len = input.u32()? as usize;
+        let bytes = input.bytes(len)?;
+        match ::std::str::from_utf8(&bytes) {
+            Ok(s) => strings.push(pool.intern(s)),
+            Err(_) => { return Err(SnapshotError::Corrupt("string is not UTF-8")); }
+        }
+    }
+
+    let mut predicates: Vec<Predicate> = Vec::new();
+    if version < 7 {
+        predicates.extend_from_slice(&PREDICATES);
+    } else {
+        let hierarchy = PredicateHierarchy::new();
+        let predicate_count = input.u32()?;
+        for _ in 0..predicate_count {
+            let len = input.u32()? as usize;
+            let bytes = input.bytes(len)?;
+            match ::std::str::from_utf8(&bytes).ok().and_then(|path| hierarchy.predicate(path)) {
+                Some(predicate) => predicates.push(predicate),
+                None => { return Err(SnapshotError::Corrupt("unknown predicate")); }
+            }
+        }
+    }
+
+    let document_count = input.u32()? as usize;
+    let mut documents = Vec::with_capacity(preallocation(document_count as u64));
+    // the last subjects of documents from before version 5
+    let mut last_subjects = Vec::new();
+    for _ in 0..document_count {
+        let id = input.i64()?;
+        if version < 5 {
+            last_subjects.push(input.i64()?);
+            documents.push(Document { id: id, subjects: Vec::new() });
+            continue;
+        }
+        let subject_count = input.u32()?;
+        let mut subjects = Vec::with_capacity(preallocation(subject_count as u64));
+        for _ in 0..subject_count {
+            subjects.push(input.i64()?);
+        }
+        if subjects.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(SnapshotError::Corrupt("document subjects out of order"));
+        }
+        documents.push(Document { id: id, subjects: subjects });
+    }
+
+    let fact_count = input.u64()?;
+    let mut facts = Vec::with_capacity(preallocation(fact_count));
+    for _ in 0..fact_count {
+        let fact = take_fact(&mut input, &predicates, &strings)?;
+        let graph = match version {
+            1 | 2 => NO_GRAPH,
+            _ => input.u32()?
+        };
+        let graph = match graph {
+            NO_GRAPH => None,
+            number => match strings.get(number as usize) {
+                Some(s) => Some(*s),
+                None => { return Err(SnapshotError::Corrupt("graph string out of range")); }
+            }
+        };
+        facts.push(fact.in_graph(graph));
+    }
+
+    let fact_id_count = match version {
+        1..=3 => 0,
+        _ => input.u64()?
+    };
+    let mut fact_ids = Vec::with_capacity(preallocation(fact_id_count));
+    for _ in 0..fact_id_count {
+        let id = input.i64()?;
+        fact_ids.push((id, take_fact(&mut input, &predicates, &strings)?));
+    }
+
+    let name_count = match version {
+        1..=5 => 0,
+        _ => input.u64()?
+    };
+    let mut names = Vec::with_capacity(preallocation(name_count));
+    for _ in 0..name_count {
+        let id = input.i64()?;
+        let len = input.u32()? as usize;
+        let bytes = input.bytes(len)?;
+        match ::std::str::from_utf8(&bytes).ok().and_then(Literal::parse) {
+            Some(literal) => names.push((id, literal)),
+            None => { return Err(SnapshotError::Corrupt("bad subject name")); }
+        }
+    }
+
+    let expected = input.hash;
+    let mut stored = [0u8; 8];
+    input.inner.read_exact(&mut stored)?;
+    if u64::from_le_bytes(stored) != expected {
+        return Err(SnapshotError::ChecksumMismatch);
+    }
+
+    Fact::reserve_ids(last_id);
+    if !last_subjects.is_empty() {
+        let mut subjects: Vec<i64> = facts.iter().map(|f: &Fact| f.subject).collect();
+        subjects.sort();
+        subjects.dedup();
+        for (document, last) in documents.iter_mut().zip(last_subjects) {
+            document.subjects = subjects.iter().cloned().filter(|&s| s > document.id && s <= last).collect();
+        }
+    }
+    let mut space = FactSpace::with_facts(facts);
+    for document in documents {
+        space.add_document(document);
+    }
+    for (id, fact) in fact_ids {
+        if !space.fact_ids.assign(fact, id) {
+            return Err(SnapshotError::Corrupt("fact id given twice"));
+        }
+    }
+    for (id, literal) in names {
+        if !space.names.assign(&literal, id) {
+            return Err(SnapshotError::Corrupt("name given twice"));
+        }
+    }
+    return Ok(space);
+}
+
+pub fn is_snapshot(bytes: &[u8]) -> bool {
+    return bytes.starts_with(MAGIC);
+}
+
+#[test]
+fn snapshot_round_trips_every_literal_exactly() {
+    let odd_nan = f64::from_bits(0x7ff8_0000_dead_beef);
+    let mut space = FactSpace::new();
+    let document = space.ingest("dad went fishing on sunday");
+    space.insert(Fact::new_float_fact(7, Predicate::Distance, odd_nan));
+    space.insert(Fact::new_float_fact(7, Predicate::Distance, -0.0));
+    space.insert(Fact::new_integer_fact(7, Predicate::Distance, i64::MIN));
+    space.insert(Fact::new_literal_fact(7, Predicate::Word, ::LiteralValue::Date(-719468)));
+    space.insert(Fact::new_literal_fact(7, Predicate::Word, ::LiteralValue::LangText("went", "en")));
+    space.insert(Fact::new_literal_fact(7, Predicate::Word, ::LiteralValue::Typed("7", "urn:x:odd")));
+    space.insert(Fact::new_text_fact(8, Predicate::Word, "went").in_graph(Some("went")));
+    let quoted = Fact::new_text_fact(9, Predicate::Word, "fished");
+    let id = space.annotate(quoted, Predicate::Confidence, ::ObjectValue::Literal(::LiteralValue::Float(0.5)));
+    space.insert(Fact { subject: 10, predicate: Predicate::Node, object: ::ObjectValue::Quoted(id), graph: None });
+    let dad = space.names.id(&Literal::parse("people.dad").unwrap());
+
+    let mut bytes = Vec::new();
+    write_snapshot(&mut bytes, &space).unwrap();
+    let pool = StringPool::new();
+    let mut loaded = read_snapshot(&bytes[..], &pool).unwrap();
+
+    assert_eq!(space.facts().len(), loaded.facts().len());
+    for (original, restored) in space.facts().iter().zip(loaded.facts().iter()) {
+        assert_eq!(original.subject, restored.subject);
+        assert_eq!(original.graph, restored.graph);
+        assert_eq!(predicate_code(original.predicate), predicate_code(restored.predicate));
+        match (original.object, restored.object) {
+            (::ObjectValue::Literal(::LiteralValue::Float(a)), ::ObjectValue::Literal(::LiteralValue::Float(b))) => assert_eq!(a.to_bits(), b.to_bits()),
+            (a, b) => assert_eq!(::object_to_string(&a), ::object_to_string(&b))
+        }
+    }
+    assert_eq!(space.word_count("went"), loaded.word_count("went"));
+    assert_eq!(Some(quoted), loaded.fact_ids.fact(id));
+    assert_eq!(Some(id), loaded.fact_ids.find(&quoted));
+    assert_eq!(space.documents()[0].subjects, loaded.documents()[0].subjects);
+    assert_eq!(Some(dad), loaded.names.find("people.dad"));
+    assert_eq!(Some("people.dad".to_string()), loaded.names.name(dad));
+    assert!(loaded.retract(document));
+    assert!(Fact::new_id() > document);
+}
+
+#[test]
+fn snapshot_rejects_corruption() {
+    let space = FactSpace::with_facts(::parse("dad went fishing on sunday"));
+    let mut bytes = Vec::new();
+    write_snapshot(&mut bytes, &space).unwrap();
+    let pool = StringPool::new();
+
+    let mut flipped = bytes.clone();
+    flipped[40] ^= 1;
+    match read_snapshot(&flipped[..], &pool) {
+        Err(SnapshotError::ChecksumMismatch) | Err(SnapshotError::Corrupt(_)) => {}
+        _ => panic!("corruption not detected")
+    }
+    match read_snapshot(&bytes[..bytes.len() - 3], &pool) {
+        Err(SnapshotError::Corrupt("truncated")) => {}
+        _ => panic!("truncation not detected")
+    }
+    match read_snapshot(&b"N3 text, not a snapshot"[..], &pool) {
+        Err(SnapshotError::BadMagic) => {}
+        _ => panic!("bad magic not detected")
+    }
+}
+
+#[test]
+fn snapshot_rejects_corrupt_counts_without_allocating_them() {
+    // no strings, documents or names: the string count is at 20, the predicate count at
+    // 24 and the eight paths take 8 × 4 + 126 bytes, so the document count is at 186, the
+    // fact count at 190, the one 22 byte fact at 198, the fact id count at 220, the name
+    // count at 228 and the checksum at 236; the last byte of each count is flipped
+    let space = FactSpace::with_facts(vec![Fact::new_integer_fact(1, Predicate::Distance, 2)]);
+    let mut bytes = Vec::new();
+    write_snapshot(&mut bytes, &space).unwrap();
+    let pool = StringPool::new();
+    assert_eq!(244, bytes.len());
+    assert_eq!(&8u32.to_le_bytes(), &bytes[24..28]);
+    assert_eq!(&1u64.to_le_bytes(), &bytes[190..198]);
+    assert_eq!(&1i64.to_le_bytes(), &bytes[198..206]);
+    assert_eq!(1, read_snapshot(&bytes[..], &pool).unwrap().facts().len());
+
+    for &at in [23, 27, 189, 197, 227, 235].iter() {
+        let mut flipped = bytes.clone();
+        flipped[at] ^= 0x40;
+        match read_snapshot(&flipped[..], &pool) {
+            Err(SnapshotError::ChecksumMismatch) | Err(SnapshotError::Corrupt(_)) => {}
+            _ => panic!("corrupt count at {} not detected", at)
+        }
+    }
+
+    // a string whose length says it runs far past the end of the file
+    let mut space = FactSpace::new();
+    space.insert(Fact::new_text_fact(1, Predicate::Word, "dad"));
+    let mut bytes = Vec::new();
+    write_snapshot(&mut bytes, &space).unwrap();
+    bytes[27] ^= 0x40;
+    match read_snapshot(&bytes[..], &pool) {
+        Err(SnapshotError::Corrupt("truncated")) => {}
+        _ => panic!("corrupt string length not detected")
+    }
+}
+
+#[test]
+fn snapshot_predicates_are_read_by_their_paths() {
+    let mut space = FactSpace::new();
+    space.insert(Fact::new_object_fact(1, Predicate::Node, 2));
+    let mut bytes = Vec::new();
+    write_snapshot(&mut bytes, &space).unwrap();
+    let pool = StringPool::new();
+    let resealed = |bytes: &mut Vec<u8>| {
+        let end = bytes.len() - 8;
+        let checksum = fnv1a(FNV_OFFSET, &bytes[..end]);
+        bytes[end..].copy_from_slice(&checksum.to_le_bytes());
+    };
+    let at = |bytes: &[u8], path: &[u8]| bytes.windows(path.len()).position(|w| w == path).unwrap();
+
+    // swapping two paths of the same length swaps the predicates their codes stand for
+    let mut swapped = bytes.clone();
+    let (node, rule) = (at(&bytes, b"root.link.node"), at(&bytes, b"root.meta.rule"));
+    swapped[node..node + 14].copy_from_slice(b"root.meta.rule");
+    swapped[rule..rule + 14].copy_from_slice(b"root.link.node");
+    resealed(&mut swapped);
+    assert_eq!(Predicate::Rule, read_snapshot(&swapped[..], &pool).unwrap().facts()[0].predicate);
+
+    let mut unknown = bytes.clone();
+    unknown[node..node + 14].copy_from_slice(b"root.link.nope");
+    resealed(&mut unknown);
+    match read_snapshot(&unknown[..], &pool) {
+        Err(SnapshotError::Corrupt("unknown predicate")) => {}
+        _ => panic!("unknown predicate path not detected")
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::prelude::*;
+use std::mem::size_of;
+
+use identity::FactIds;
+use island::{IslandBuilder, IslandGeometry};
+use naming::SubjectNames;
+use pool::StringPool;
+use stream::{self, FnSink, ParseOptions, Progress};
+use view::FactView;
+use {Fact, LiteralValue, ObjectValue, Predicate};
+
+/// The subjects one `ingest` produced, in ascending order.
+pub struct Document {
+    pub id: i64,
+    pub subjects: Vec<i64>
+}
+
+/// Facts held in memory, with the documents they came from, counts of their words,
+/// the names of named subjects and the ids of facts that other facts are about.
+pub struct FactSpace<'a>{
+    facts: Vec<Fact<'a>>,
+    documents: Vec<Document>,
+    word_counts: HashMap<&'a str, usize>,
+    pub names: SubjectNames,
+    pub fact_ids: FactIds<'a>
+}
+
+impl<'a> FactSpace<'a> {
+
+    pub fn new() -> FactSpace<'a> {
+        return FactSpace::with_facts(Vec::new());
+    }
+
+    pub fn with_facts(facts: Vec<Fact<'a>>) -> FactSpace<'a> {
+        let mut space = FactSpace {
+            facts: Vec::with_capacity(facts.len()),
+            documents: Vec::new(),
+            word_counts: HashMap::new(),
+            names: SubjectNames::new(),
+            fact_ids: FactIds::new()
+        };
+        for fact in facts {
+            space.insert(fact);
+        }
+        return space;
+    }
+
+    pub fn insert(&mut self, fact: Fact<'a>) {
+        if let (Predicate::Word, ObjectValue::Literal(LiteralValue::Text(word))) = (fact.predicate, fact.object) {
+            *self.word_counts.entry(word).or_insert(0) += 1;
+        }
+        self.facts.push(fact);
+    }
+
+    /// Adds islands for `text` next to the facts already in the space and returns the document id.
+    /// Subjects are taken from the global id counter, so existing facts keep their numbering.
+    pub fn ingest(&mut self, text: &'a str) -> i64 {
+        let document = Fact::new_id();
+        let first = self.facts.len();
+        let mut builder = IslandBuilder::new(IslandGeometry::default());
+        for word in text.split(" ") {
+            builder.push(word, self);
+        }
+        self.record_document(document, first);
+        return document;
+    }
+
+    // Records the subjects of the facts from `first` on as those of `document`. They are
+    // listed one by one, as ids other threads take meanwhile fall between them.
+    fn record_document(&mut self, document: i64, first: usize) {
+        let mut subjects: Vec<i64> = self.facts[first..].iter().map(|f| f.subject).collect();
+        subjects.sort();
+        subjects.dedup();
+        self.documents.push(Document { id: document, subjects: subjects });
+    }
+
+    /// Adds a document whose facts are already in the space, as when loading a snapshot.
+    pub fn add_document(&mut self, document: Document) {
+        self.documents.push(document);
+    }
+
+    pub fn ingest_reader<R: BufRead>(&mut self, reader: &mut R, pool: &'a StringPool, options: ParseOptions) -> io::Result<(i64, Progress)> {
+        return self.ingest_reader_into(reader, pool, options, None);
+    }
+
+    /// `ingest_reader`, putting the facts in `graph`, or in the default graph for None.
+    pub fn ingest_reader_into<R: BufRead>(&mut self, reader: &mut R, pool: &'a StringPool, options: ParseOptions, graph: Option<&'a str>) -> io::Result<(i64, Progress)> {
+        let document = Fact::new_id();
+        let first = self.facts.len();
+        let status = {
+            let mut sink = FnSink(|fact: Fact<'a>| self.insert(fact.in_graph(graph)));
+            stream::parse_stream(reader, pool, options, &mut sink, |_| {})?
+        };
+        self.record_document(document, first);
+        return Ok((document, status));
+    }
+
+    /// Removes every fact produced by a previous `ingest`; returns false for unknown documents.
+    pub fn retract(&mut self, document: i64) -> bool {
+        let position = match self.documents.iter().position(|d| d.id == document) {
+            Some(position) => position,
+            None => { return false; }
+        };
+        let removed = self.documents.remove(position);
+        self.remove_where(|f| removed.subjects.binary_search(&f.subject).is_ok());
+        return true;
+    }
+
+    // Drops the facts selected by `f`, keeping derived counts in step; returns how many went.
+    pub fn remove_where<F>(&mut self, f: F) -> usize where F: Fn(&Fact<'a>) -> bool {
+        let before = self.facts.len();
+        let word_counts = &mut self.word_counts;
+        self.facts.retain(|fact| {
+            if !f(fact) {
+                return true;
+            }
+            if let (Predicate::Word, ObjectValue::Literal(LiteralValue::Text(word))) = (fact.predicate, fact.object) {
+                let remove_word = match word_counts.get_mut(word) {
+                    Some(count) => { *count -= 1; *count == 0 }
+                    None => false
+                };
+                if remove_word {
+                    word_counts.remove(word);
+                }
+            }
+            return false;
+        });
+        return before - self.facts.len();
+    }
+
+    // Drops repeated facts, keeping the first of each; returns how many went.
+    pub fn dedup(&mut self) -> usize {
+        let facts = ::std::mem::take(&mut self.facts);
+        let before = facts.len();
+        let mut seen: HashSet<Fact<'a>> = HashSet::with_capacity(before);
+        self.word_counts.clear();
+        for fact in facts {
+            if seen.insert(fact) {
+                self.insert(fact);
+            }
+        }
+        return before - self.facts.len();
+    }
+
+    // Puts the facts in canonical order: by subject, predicate, then object.
+    pub fn sort(&mut self) {
+        self.facts.sort();
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        return self.facts.windows(2).all(|w| w[0] <= w[1]);
+    }
+
+    // Merges two canonically sorted spaces into a sorted space holding each fact once.
+    pub fn merge_sorted(&self, other: &FactSpace<'a>) -> FactSpace<'a> {
+        debug_assert!(self.is_sorted() && other.is_sorted());
+        let mut merged: Vec<Fact<'a>> = Vec::with_capacity(self.facts.len() + other.facts.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.facts.len() || j < other.facts.len() {
+            let next = if j == other.facts.len() || (i < self.facts.len() && self.facts[i] <= other.facts[j]) {
+                i += 1;
+                self.facts[i - 1]
+            } else {
+                j += 1;
+                other.facts[j - 1]
+            };
+            if merged.last() != Some(&next) {
+                merged.push(next);
+            }
+        }
+        let mut space = FactSpace::with_facts(merged);
+        space.fact_ids.extend(&self.fact_ids);
+        space.fact_ids.extend(&other.fact_ids);
+        return space;
+    }
+
+    pub fn facts(&self) -> &[Fact<'a>] {
+        return &self.facts;
+    }
+
+    pub fn into_facts(self) -> Vec<Fact<'a>> {
+        return self.facts;
+    }
+
+    pub fn documents(&self) -> &[Document] {
+        return &self.documents;
+    }
+
+    pub fn word_counts(&self) -> &HashMap<&'a str, usize> {
+        return &self.word_counts;
+    }
+
+    // Estimated bytes held, by part.
+    pub fn memory(&self) -> Vec<(&'static str, usize)> {
+        let text_bytes: usize = self.word_counts.keys().map(|w| w.len()).sum();
+        let subjects: usize = self.documents.iter().map(|d| d.subjects.capacity()).sum();
+        return vec![
+            ("facts", self.facts.capacity() * size_of::<Fact>()),
+            ("word index", self.word_counts.capacity() * (size_of::<&str>() + size_of::<usize>())),
+            ("word text", text_bytes),
+            ("documents", self.documents.capacity() * size_of::<Document>() + subjects * size_of::<i64>()),
+            ("subject names", self.names.estimated_bytes()),
+            ("fact ids", self.fact_ids.estimated_bytes())
+        ];
+    }
+
+    pub fn word_count(&self, word: &str) -> usize {
+        return *self.word_counts.get(word).unwrap_or(&0);
+    }
+
+    pub fn document_ids(&self) -> Vec<i64> {
+        return self.documents.iter().map(|d| d.id).collect();
+    }
+
+    /// The named graphs that hold facts, in the order they first appear.
+    pub fn graphs(&self) -> Vec<&'a str> {
+        let mut graphs: Vec<&'a str> = Vec::new();
+        for graph in self.facts.iter().filter_map(|f| f.graph) {
+            if !graphs.contains(&graph) {
+                graphs.push(graph);
+            }
+        }
+        return graphs;
+    }
+
+    /// A view of the facts in any of `graphs`, where None stands for the default graph;
+    /// every view operation is then scoped to those graphs.
+    pub fn in_graphs<'s>(&'s self, graphs: &[Option<&'a str>]) -> FactView<'s, 'a> {
+        return self.view().match_graphs(graphs.to_vec());
+    }
+
+    /// Drops every fact in `graph`; returns how many went.
+    pub fn drop_graph(&mut self, graph: Option<&str>) -> usize {
+        return self.remove_where(|f| f.graph == graph);
+    }
+
+    /// Makes `facts` the whole content of `graph`, whatever graph they were in before;
+    /// returns how many facts the graph lost.
+    pub fn replace_graph(&mut self, graph: Option<&'a str>, facts: Vec<Fact<'a>>) -> usize {
+        let removed = self.drop_graph(graph);
+        for fact in facts {
+            self.insert(fact.in_graph(graph));
+        }
+        return removed;
+    }
+
+    /// Says `object` about `fact` through `predicate`, as `<< fact >> :confidence 0.9`,
+    /// and returns the fact id it was said of. `fact` itself need not be in the space.
+    /// Fact ids ignore graphs, so what is said goes in the default graph whatever graph
+    /// `fact` is in, and stays when that graph is dropped.
+    pub fn annotate(&mut self, fact: Fact<'a>, predicate: Predicate, object: ObjectValue<'a>) -> i64 {
+        let id = self.fact_ids.id(fact);
+        self.insert(Fact { subject: id, predicate: predicate, object: object, graph: None });
+        return id;
+    }
+
+    /// The facts said about `fact`; empty when it has no fact id.
+    pub fn about<'s>(&'s self, fact: &Fact<'a>) -> FactView<'s, 'a> {
+        let id = self.fact_ids.find(fact);
+        return self.view().filter_facts(move |x| Some(x.subject) == id);
+    }
+
+    /// Facts about a fact, or quoting one as their object, where that fact passes `f`.
+    pub fn match_quoted<'s, F>(&'s self, f: F) -> FactView<'s, 'a>
+        where F: Fn(&Fact<'a>) -> bool + 's
+    {
+        let ids = &self.fact_ids;
+        return self.view().filter_facts(move |x| {
+            let quoted = match x.object {
+                ObjectValue::Quoted(id) => ids.fact(id),
+                _ => None
+            };
+            return ids.fact(x.subject).into_iter().chain(quoted).any(|fact| f(&fact));
+        });
+    }
+
+    pub fn view<'s>(&'s self) -> FactView<'s, 'a> {
+        return FactView::all(&self.facts)
+    }
+
+    // A lazy view over facts the caller keeps, rather than a copy of them.
+    pub fn from_facts<'s> (facts: &'s [Fact<'a>]) -> FactView<'s, 'a> {
+        return FactView::all(facts)
+    }
+
+    #[allow(clippy::boxed_local)]
+    pub fn from_heap (facts: Box<Vec<Fact<'a>>>) -> FactSpace<'a> {
+        return FactSpace::with_facts(*facts)
+    }
+}
+
+#[test]
+fn can_enumerate_subjects() {
+    let facts: Vec<Fact> = vec![
+        Fact::new_text_fact(1, Predicate::Word, "a"),
+        Fact::new_text_fact(1, Predicate::Distance, "b"),
+        Fact::new_text_fact(2, Predicate::Word, "c"),
+        Fact::new_text_fact(3, Predicate::Distance, "d"),
+        Fact::new_text_fact(3, Predicate::Word, "e"),
+        Fact::new_text_fact(3, Predicate::Distance, "f")
+    ];
+
+    let space = FactSpace::from_facts(&facts);
+
+    let subjects = space.get_subjects();
+
+    assert_eq!(vec![1,2,3], subjects);
+}
+
+#[test]
+fn can_open_subjects() {
+    let facts: Vec<Fact> = vec![
+        Fact::new_text_fact(1, Predicate::Word, "a"),
+        Fact::new_text_fact(1, Predicate::Distance, "b"),
+        Fact::new_text_fact(2, Predicate::Word, "c"),
+        Fact::new_text_fact(3, Predicate::Distance, "d"),
+        Fact::new_text_fact(3, Predicate::Word, "e"),
+        Fact::new_text_fact(3, Predicate::Distance, "f"),
+        Fact::new_text_fact(3, Predicate::Word, "g")
+    ];
+
+    let space = FactSpace::from_facts(&facts);
+
+    let open_subjects = space.open_subjects(vec![1, 3]);
+
+    assert_eq!(6, open_subjects.len());
+}
+
+#[test]
+fn can_ingest_and_retract_documents() {
+    let mut space = FactSpace::new();
+    let first = space.ingest("dad went fishing on sunday");
+    let first_subjects = space.view().get_subjects();
+
+    let second = space.ingest("mom went shopping on monday morning");
+    assert!(second > first);
+    assert_eq!(vec![first, second], space.document_ids());
+    assert_eq!(7, space.word_count("went"));
+    assert_eq!(space.view().match_subject_with_text(|_, t| t == "went").len(), space.word_count("went"));
+    assert!(space.view().get_subjects().starts_with(&first_subjects));
+
+    assert!(space.retract(first));
+    assert!(!space.retract(first));
+    assert_eq!(0, space.word_count("dad"));
+    assert!(space.word_count("mom") > 0);
+    assert!(space.view().get_subjects().iter().all(|s| !first_subjects.contains(s)));
+}
+
+#[test]
+fn retract_keeps_subjects_between_a_documents_own() {
+    let mut space = FactSpace::new();
+    let document = space.ingest("dad went fishing on sunday");
+    // island ids lie among the document's subjects without being any of them, like ids
+    // another ingest takes while this one runs
+    let island = space.facts().iter().rev().find(|f| f.predicate == Predicate::Node).unwrap().get_object_id();
+    let subjects = space.documents()[0].subjects.clone();
+    assert!(subjects[0] < island && island < subjects[subjects.len() - 1]);
+    space.insert(Fact::new_text_fact(island, Predicate::Word, "elsewhere"));
+
+    assert!(space.retract(document));
+    assert_eq!(vec![Fact::new_text_fact(island, Predicate::Word, "elsewhere")], space.facts().to_vec());
+    assert_eq!(1, space.word_counts().len());
+}
+
+#[test]
+fn facts_have_a_total_order_across_object_types() {
+    let nan = f64::NAN;
+    let mut objects = [
+        ObjectValue::Id(1),
+        ObjectValue::Literal(LiteralValue::Text("a")),
+        ObjectValue::Literal(LiteralValue::Float(nan)),
+        ObjectValue::Literal(LiteralValue::Float(0.0)),
+        ObjectValue::Literal(LiteralValue::Float(-0.0)),
+        ObjectValue::Literal(LiteralValue::Integer(7))
+    ];
+    objects.sort();
+    assert_eq!(ObjectValue::Literal(LiteralValue::Integer(7)), objects[0]);
+    assert_eq!(ObjectValue::Literal(LiteralValue::Float(-0.0)), objects[1]);
+    assert!(objects[1] != objects[2]);
+    assert_eq!(ObjectValue::Literal(LiteralValue::Float(nan)), objects[3]);
+    assert_eq!(ObjectValue::Id(1), objects[5]);
+
+    let mut space = FactSpace::with_facts(::parse("dad went fishing on sunday"));
+    let copy = space.facts.clone();
+    for fact in copy.iter() {
+        space.insert(*fact);
+    }
+    let words = space.word_count("went");
+    assert_eq!(copy.len(), space.dedup());
+    assert_eq!(words / 2, space.word_count("went"));
+
+    let mut other = FactSpace::with_facts(::parse("mom went shopping"));
+    other.insert(copy[0]);
+    space.sort();
+    other.sort();
+    let merged = space.merge_sorted(&other);
+    assert!(merged.is_sorted());
+    assert_eq!(space.facts.len() + other.facts.len() - 1, merged.facts.len());
+}
+
+#[test]
+fn can_create_derived_fact_space() {
+    let facts = ::parse(::EXAMPLE);
+    let space = FactSpace::from_facts(&facts);
+
+    let small_space = space.match_objects(|s, p| s == facts[0].subject && p.order() == facts[0].predicate.order());
+
+    for fact0 in small_space.iter() {
+        println!("{}", fact0);
+    }
+}
+
+#[test]
+fn can_cast_space_to_literals() {
+    let facts = ::parse(::EXAMPLE);
+    let space = FactSpace::from_facts(&facts);
+
+    let small_space = space.match_predicates(|p| matches!(p, Predicate::Word));
+
+    for fact0 in small_space.iter() {
+        println!("{}", fact0);
+    }
+}
+
+#[test]
+fn graphs_scope_views_and_can_be_dropped_or_replaced() {
+    let pool = StringPool::new();
+    let mut space = FactSpace::new();
+    space.ingest_reader(&mut "dad went fishing".as_bytes(), &pool, ParseOptions::default()).unwrap();
+    space.ingest_reader_into(&mut "mom went shopping".as_bytes(), &pool, ParseOptions::default(), Some("mom.txt")).unwrap();
+    space.ingest_reader_into(&mut "kid went home".as_bytes(), &pool, ParseOptions::default(), Some("kid.txt")).unwrap();
+
+    assert_eq!(vec!["mom.txt", "kid.txt"], space.graphs());
+    let went = |view: FactView| view.match_subject_with_text(|_, t| t == "went").len();
+    assert_eq!(6, went(space.view()));
+    assert_eq!(2, went(space.in_graphs(&[Some("mom.txt")])));
+    assert_eq!(4, went(space.in_graphs(&[None, Some("kid.txt")])));
+    assert_eq!(0, space.in_graphs(&[Some("mom.txt")]).match_subject_with_text(|_, t| t == "dad").len());
+
+    let kid = space.in_graphs(&[Some("kid.txt")]).len();
+    assert_eq!(kid, space.drop_graph(Some("kid.txt")));
+    assert_eq!(vec!["mom.txt"], space.graphs());
+    assert_eq!(4, space.word_count("went"));
+
+    let replacement = vec![Fact::new_text_fact(Fact::new_id(), Predicate::Word, "aunt")];
+    let mom = space.in_graphs(&[Some("mom.txt")]).len();
+    assert_eq!(mom, space.replace_graph(Some("mom.txt"), replacement));
+    assert_eq!(1, space.in_graphs(&[Some("mom.txt")]).len());
+    assert_eq!(1, space.word_count("aunt"));
+    assert_eq!(0, space.word_count("shopping"));
+}
+
+#[test]
+fn facts_about_facts_match_through_the_fact_they_quote() {
+    let mut space = FactSpace::new();
+    let went = Fact::new_text_fact(Fact::new_id(), Predicate::Word, "went");
+    let fishing = Fact::new_text_fact(Fact::new_id(), Predicate::Word, "fishing");
+    space.insert(went);
+    space.insert(fishing);
+
+    let id = space.annotate(went, Predicate::Confidence, ObjectValue::Literal(LiteralValue::Float(0.9)));
+    assert_eq!(id, space.annotate(went, Predicate::Offset, ObjectValue::Literal(LiteralValue::Integer(4))));
+    let follows = Fact::new_object_fact(fishing.subject, Predicate::Follows, went.subject);
+    let quoted = ObjectValue::Quoted(space.fact_ids.id(follows));
+    space.insert(Fact { subject: Fact::new_id(), predicate: Predicate::Node, object: quoted, graph: None });
+
+    assert_eq!(Some(went), space.fact_ids.fact(id));
+    assert_eq!(2, space.about(&went).len());
+    assert_eq!(0, space.about(&fishing).len());
+    assert_eq!(0.9, space.about(&went).match_predicates(|p| p == Predicate::Confidence).single().get_float_literal());
+    // quoting a fact does not assert it
+    assert!(!space.facts.contains(&follows));
+    let node = space.facts().iter().find(|f| f.object == quoted).unwrap();
+    assert_eq!(Some(follows), space.fact_ids.fact(node.get_quoted_id()));
+
+    assert_eq!(2, space.match_quoted(|f| f.subject == went.subject).len());
+    assert_eq!(1, space.match_quoted(|f| f.predicate == Predicate::Follows).len());
+    assert_eq!(3, space.match_quoted(|_| true).len());
+    assert_eq!(0, space.match_quoted(|f| f.subject == fishing.subject && f.predicate == Predicate::Word).len());
+}
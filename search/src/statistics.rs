@@ -0,0 +1,154 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use format::predicate_name;
+use mapped::MappedStore;
+use {Fact, FactSpace, LiteralValue, ObjectValue, Predicate};
+
+// A summary of a parsed space. Words are counted as island centres, the word at distance 0,
+// so a word counts once no matter how many islands it falls into. The last `radius` words
+// of each text are never a centre and are left out of `centres`, `vocabulary` and `top_words`.
+pub struct Statistics<'a> {
+    pub facts: usize,
+    pub facts_per_predicate: Vec<(Predicate, usize)>,
+    pub islands: usize,
+    pub vocabulary: usize,
+    pub centres: usize,
+    pub top_words: Vec<(&'a str, usize)>,
+    // (least, most, words): how many distinct words occur between least and most times
+    pub histogram: Vec<(usize, usize, usize)>,
+    pub average_fill: f64,
+    pub memory: Vec<(&'static str, usize)>
+}
+
+impl<'a> Statistics<'a> {
+    // Distinct centre words over centre words.
+    pub fn type_token_ratio(&self) -> f64 {
+        if self.centres == 0 {
+            return 0.0;
+        }
+        return self.vocabulary as f64 / self.centres as f64;
+    }
+
+    pub fn memory_total(&self) -> usize {
+        return self.memory.iter().map(|&(_, bytes)| bytes).sum();
+    }
+}
+
+// Counts everything in one pass over `facts`; `top` bounds the frequent word list. The
+// memory estimate is left for the caller, which knows how the facts are held.
+fn count<'a, I: Iterator<Item = Fact<'a>>>(facts: I, top: usize) -> Statistics<'a> {
+    let mut fact_count = 0;
+    let mut per_predicate: HashMap<Predicate, usize> = HashMap::new();
+    let mut islands: HashMap<i64, usize> = HashMap::new();
+    let mut word_of: HashMap<i64, &'a str> = HashMap::new();
+    let mut centers: HashSet<i64> = HashSet::new();
+
+    for fact in facts {
+        fact_count += 1;
+        *per_predicate.entry(fact.predicate).or_insert(0) += 1;
+        match (fact.predicate, fact.object) {
+            (Predicate::Node, ObjectValue::Id(island)) => { *islands.entry(island).or_insert(0) += 1; }
+            (Predicate::Word, ObjectValue::Literal(LiteralValue::Text(word))) => { word_of.insert(fact.subject, word); }
+            (Predicate::Distance, ObjectValue::Literal(LiteralValue::Integer(0))) => { centers.insert(fact.subject); }
+            _ => {}
+        }
+    }
+
+    let mut frequencies: HashMap<&'a str, usize> = HashMap::new();
+    for subject in centers.iter() {
+        if let Some(word) = word_of.get(subject) {
+            *frequencies.entry(*word).or_insert(0) += 1;
+        }
+    }
+    let centres: usize = frequencies.values().sum();
+
+    let mut ranked: Vec<(&'a str, usize)> = frequencies.iter().map(|(w, c)| (*w, *c)).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut histogram: Vec<(usize, usize, usize)> = Vec::new();
+    for &(_, count) in ranked.iter().rev() {
+        let least = count.next_power_of_two() / if count.is_power_of_two() { 1 } else { 2 };
+        match histogram.last_mut() {
+            Some(bucket) if bucket.0 == least => { bucket.2 += 1; }
+            _ => histogram.push((least, least * 2 - 1, 1))
+        }
+    }
+    ranked.truncate(top);
+
+    let mut facts_per_predicate: Vec<(Predicate, usize)> = per_predicate.into_iter().collect();
+    facts_per_predicate.sort();
+
+    let members: usize = islands.values().sum();
+    return Statistics {
+        facts: fact_count,
+        facts_per_predicate: facts_per_predicate,
+        islands: islands.len(),
+        vocabulary: frequencies.len(),
+        centres: centres,
+        top_words: ranked,
+        histogram: histogram,
+        average_fill: if islands.is_empty() { 0.0 } else { members as f64 / islands.len() as f64 },
+        memory: Vec::new()
+    };
+}
+
+impl<'a> FactSpace<'a> {
+    pub fn statistics(&self, top: usize) -> Statistics<'a> {
+        let mut statistics = count(self.facts().iter().cloned(), top);
+        statistics.memory = self.memory();
+        return statistics;
+    }
+}
+
+impl MappedStore {
+    // `FactSpace::statistics`, reading the facts from the mapped columns; the file is
+    // mapped rather than held, so it is the whole memory estimate.
+    pub fn statistics(&self, top: usize) -> Statistics<'_> {
+        let mut statistics = count(self.iter(), top);
+        statistics.memory = vec![("mapped file", self.mapped_bytes())];
+        return statistics;
+    }
+}
+
+impl<'a> fmt::Display for Statistics<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "facts\t{}", self.facts)?;
+        for &(predicate, count) in self.facts_per_predicate.iter() {
+            writeln!(f, "facts :{}\t{}", predicate_name(predicate), count)?;
+        }
+        writeln!(f, "islands\t{}", self.islands)?;
+        writeln!(f, "average island fill\t{:.2}", self.average_fill)?;
+        writeln!(f, "centre words\t{}", self.centres)?;
+        writeln!(f, "vocabulary\t{}", self.vocabulary)?;
+        writeln!(f, "type/token ratio\t{:.4}", self.type_token_ratio())?;
+        for &(word, count) in self.top_words.iter() {
+            writeln!(f, "top word {}\t{}", word, count)?;
+        }
+        for &(least, most, words) in self.histogram.iter() {
+            writeln!(f, "words seen {}-{} times\t{}", least, most, words)?;
+        }
+        for &(component, bytes) in self.memory.iter() {
+            writeln!(f, "memory {}\t{}", component, bytes)?;
+        }
+        return write!(f, "memory total\t{}", self.memory_total());
+    }
+}
+
+#[test]
+fn statistics_count_each_centre_once() {
+    let space = FactSpace::with_facts(::parse("a dog saw the cat and the cat saw the mat today too"));
+    let statistics = space.statistics(2);
+
+    assert_eq!(space.facts().len(), statistics.facts);
+    assert_eq!(Predicate::Word, statistics.facts_per_predicate[0].0);
+    assert_eq!(vec![("the", 3), ("cat", 2)], statistics.top_words);
+    assert_eq!(statistics.centres, statistics.islands);
+    assert_eq!(statistics.vocabulary, statistics.histogram.iter().map(|b| b.2).sum::<usize>());
+    assert_eq!((1, 1), (statistics.histogram[0].0, statistics.histogram[0].1));
+    assert!(statistics.average_fill > 4.0 && statistics.average_fill <= 5.0);
+    assert!(statistics.to_string().contains("type/token ratio"));
+    assert!(statistics.memory.iter().any(|&(component, _)| component == "fact ids"));
+    // the last radius words are never centres
+    assert!(space.statistics(20).top_words.iter().all(|&(word, _)| word != "too"));
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::io;
+
+use mapped::MappedStore;
+use range::{NumericRange, RangeIndex};
+use wal::DurableStore;
+use {Fact, FactSpace, LiteralValue, ObjectValue, Predicate};
+
+// A triple pattern where `None` matches anything. A range further restricts the object
+// to integers and floats within it.
+#[derive(Copy, Clone, Default)]
+pub struct FactPattern<'a> {
+    pub subject: Option<i64>,
+    pub predicate: Option<Predicate>,
+    pub object: Option<ObjectValue<'a>>,
+    pub range: Option<NumericRange>
+}
+
+impl<'a> FactPattern<'a> {
+    pub fn matches(&self, fact: &Fact) -> bool {
+        return self.subject.is_none_or(|s| s == fact.subject)
+            && self.predicate.is_none_or(|p| p == fact.predicate)
+            && self.object.is_none_or(|o| o == fact.object)
+            && self.range.is_none_or(|r| r.contains_object(fact.object));
+    }
+}
+
+// Storage for facts that the prediction and query code is written against, so the
+// backend (plain vector, indexed memory, log-backed or mapped file) can be swapped.
+pub trait FactStore<'a> {
+    fn insert(&mut self, fact: Fact<'a>) -> io::Result<()>;
+
+    // Removes every fact equal to `fact` and returns how many there were.
+    fn remove(&mut self, fact: &Fact<'a>) -> io::Result<usize>;
+
+    fn scan<'s>(&'s self) -> Box<dyn Iterator<Item = Fact<'a>> + 's>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    fn match_pattern(&self, pattern: &FactPattern<'a>) -> Vec<Fact<'a>> {
+        return self.scan().filter(|f| pattern.matches(f)).collect();
+    }
+
+    fn subject_facts(&self, subject: i64) -> Vec<Fact<'a>> {
+        return self.match_pattern(&FactPattern { subject: Some(subject), predicate: None, object: None, range: None });
+    }
+}
+
+impl<'a> FactStore<'a> for Vec<Fact<'a>> {
+    fn insert(&mut self, fact: Fact<'a>) -> io::Result<()> {
+        self.push(fact);
+        return Ok(());
+    }
+
+    fn remove(&mut self, fact: &Fact<'a>) -> io::Result<usize> {
+        let before = self.len();
+        self.retain(|f| *f != *fact);
+        return Ok(before - self.len());
+    }
+
+    fn scan<'s>(&'s self) -> Box<dyn Iterator<Item = Fact<'a>> + 's> {
+        return Box::new(self.iter().cloned());
+    }
+
+    fn len(&self) -> usize {
+        return Vec::len(self);
+    }
+}
+
+impl<'a> FactStore<'a> for FactSpace<'a> {
+    fn insert(&mut self, fact: Fact<'a>) -> io::Result<()> {
+        FactSpace::insert(self, fact);
+        return Ok(());
+    }
+
+    fn remove(&mut self, fact: &Fact<'a>) -> io::Result<usize> {
+        return Ok(self.remove_where(|f| *f == *fact));
+    }
+
+    fn scan<'s>(&'s self) -> Box<dyn Iterator<Item = Fact<'a>> + 's> {
+        return Box::new(self.facts().iter().cloned());
+    }
+
+    fn len(&self) -> usize {
+        return self.facts().len();
+    }
+}
+
+// Keeps facts in memory with hash indexes on subject, predicate, object id and text and a
+// sorted index on numbers, so pattern matches only touch candidate facts. Removed slots
+// are left empty.
+pub struct IndexedStore<'a> {
+    facts: Vec<Option<Fact<'a>>>,
+    live: usize,
+    by_subject: HashMap<i64, Vec<usize>>,
+    by_predicate: HashMap<Predicate, Vec<usize>>,
+    by_object_id: HashMap<i64, Vec<usize>>,
+    by_text: HashMap<&'a str, Vec<usize>>,
+    by_number: RangeIndex
+}
+
+impl<'a> IndexedStore<'a> {
+    pub fn new() -> IndexedStore<'a> {
+        return IndexedStore {
+            facts: Vec::new(),
+            live: 0,
+            by_subject: HashMap::new(),
+            by_predicate: HashMap::new(),
+            by_object_id: HashMap::new(),
+            by_text: HashMap::new(),
+            by_number: RangeIndex::new()
+        }
+    }
+
+    pub fn from_facts<I: IntoIterator<Item = Fact<'a>>>(facts: I) -> IndexedStore<'a> {
+        let mut store = IndexedStore::new();
+        for fact in facts {
+            store.add(fact);
+        }
+        store.by_number = RangeIndex::from_positions(store.facts.iter().enumerate().filter_map(|(p, f)| f.as_ref().map(|f| (p, f))));
+        return store;
+    }
+
+    pub fn subject_count(&self) -> usize {
+        return self.by_subject.values().filter(|l| !l.is_empty()).count();
+    }
+
+    // Adds `fact` to every index but the numeric one and returns its position.
+    fn add(&mut self, fact: Fact<'a>) -> usize {
+        let position = self.facts.len();
+        self.by_subject.entry(fact.subject).or_default().push(position);
+        self.by_predicate.entry(fact.predicate).or_default().push(position);
+        match fact.object {
+            ObjectValue::Id(id) => self.by_object_id.entry(id).or_default().push(position),
+            ObjectValue::Literal(LiteralValue::Text(s)) => self.by_text.entry(s).or_default().push(position),
+            _ => {}
+        }
+        self.facts.push(Some(fact));
+        self.live = self.live + 1;
+        return position;
+    }
+
+    // The shortest posting list that every match must appear in, if any index applies.
+    fn candidates(&self, pattern: &FactPattern<'a>) -> Option<&[usize]> {
+        let mut lists: Vec<&[usize]> = Vec::new();
+        if let Some(subject) = pattern.subject {
+            lists.push(self.by_subject.get(&subject).map_or(&[], |l| &l[..]));
+        }
+        match pattern.object {
+            Some(ObjectValue::Id(id)) => lists.push(self.by_object_id.get(&id).map_or(&[], |l| &l[..])),
+            Some(ObjectValue::Literal(LiteralValue::Text(s))) => lists.push(self.by_text.get(s).map_or(&[], |l| &l[..])),
+            _ => {}
+        }
+        if let Some(predicate) = pattern.predicate {
+            lists.push(self.by_predicate.get(&predicate).map_or(&[], |l| &l[..]));
+            if let Some(ref range) = pattern.range {
+                lists.push(self.by_number.range(predicate, range));
+            }
+        }
+        return lists.into_iter().min_by_key(|l| l.len());
+    }
+}
+
+impl<'a> FactStore<'a> for IndexedStore<'a> {
+    fn insert(&mut self, fact: Fact<'a>) -> io::Result<()> {
+        let position = self.add(fact);
+        self.by_number.insert(&fact, position);
+        return Ok(());
+    }
+
+    fn remove(&mut self, fact: &Fact<'a>) -> io::Result<usize> {
+        let positions: Vec<usize> = match self.by_subject.get(&fact.subject) {
+            Some(list) => list.iter().cloned().filter(|p| self.facts[*p].as_ref() == Some(fact)).collect(),
+            None => { return Ok(0); }
+        };
+        if positions.is_empty() {
+            return Ok(0);
+        }
+        for position in positions.iter() {
+            self.facts[*position] = None;
+            self.by_number.remove(fact, *position);
+        }
+        let facts = &self.facts;
+        let mut lists = vec![self.by_subject.get_mut(&fact.subject), self.by_predicate.get_mut(&fact.predicate)];
+        match fact.object {
+            ObjectValue::Id(id) => lists.push(self.by_object_id.get_mut(&id)),
+            ObjectValue::Literal(LiteralValue::Text(s)) => lists.push(self.by_text.get_mut(s)),
+            _ => {}
+        }
+        for list in lists.into_iter().flatten() {
+            list.retain(|p| facts[*p].is_some());
+        }
+        self.live = self.live - positions.len();
+        return Ok(positions.len());
+    }
+
+    fn scan<'s>(&'s self) -> Box<dyn Iterator<Item = Fact<'a>> + 's> {
+        return Box::new(self.facts.iter().filter_map(|f| *f));
+    }
+
+    fn len(&self) -> usize {
+        return self.live;
+    }
+
+    fn match_pattern(&self, pattern: &FactPattern<'a>) -> Vec<Fact<'a>> {
+        return match self.candidates(pattern) {
+            Some(positions) => positions.iter()
+                .filter_map(|p| self.facts[*p])
+                .filter(|f| pattern.matches(f))
+                .collect(),
+            None => self.scan().filter(|f| pattern.matches(f)).collect()
+        }
+    }
+}
+
+impl<'a> FactStore<'a> for DurableStore<'a> {
+    fn insert(&mut self, fact: Fact<'a>) -> io::Result<()> {
+        return DurableStore::insert(self, fact);
+    }
+
+    fn remove(&mut self, fact: &Fact<'a>) -> io::Result<usize> {
+        return self.retract(fact);
+    }
+
+    fn scan<'s>(&'s self) -> Box<dyn Iterator<Item = Fact<'a>> + 's> {
+        return Box::new(self.space().facts().iter().cloned());
+    }
+
+    fn len(&self) -> usize {
+        return self.space().facts().len();
+    }
+}
+
+fn read_only() -> io::Error {
+    return io::Error::new(io::ErrorKind::PermissionDenied, "mapped fact stores are read-only");
+}
+
+impl<'m> FactStore<'m> for &'m MappedStore {
+    fn insert(&mut self, _: Fact<'m>) -> io::Result<()> {
+        return Err(read_only());
+    }
+
+    fn remove(&mut self, _: &Fact<'m>) -> io::Result<usize> {
+        return Err(read_only());
+    }
+
+    fn scan<'s>(&'s self) -> Box<dyn Iterator<Item = Fact<'m>> + 's> {
+        return MappedStore::iter(self);
+    }
+
+    fn len(&self) -> usize {
+        return MappedStore::len(self);
+    }
+
+    fn match_pattern(&self, pattern: &FactPattern<'m>) -> Vec<Fact<'m>> {
+        let candidates = match (pattern.subject, pattern.object) {
+            (Some(subject), _) => self.open_subject(subject).into_facts(),
+            (None, Some(ObjectValue::Literal(LiteralValue::Text(word)))) if pattern.predicate.is_none_or(|p| p == Predicate::Word) => self.word_facts(word).into_facts(),
+            _ => { return MappedStore::iter(self).filter(|f| pattern.matches(f)).collect(); }
+        };
+        return candidates.into_iter().filter(|f| pattern.matches(f)).collect();
+    }
+}
+
+#[cfg(test)]
+fn check_backend<'a, S: FactStore<'a>>(store: &mut S, word: &'a str) {
+    let total = store.len();
+    let pattern = FactPattern { subject: None, predicate: Some(Predicate::Word), object: Some(ObjectValue::Literal(LiteralValue::Text(word))), range: None };
+    let found = store.match_pattern(&pattern);
+    assert_eq!(::ISLAND_SIZE, found.len());
+
+    let subject = found[0].subject;
+    assert_eq!(3, store.subject_facts(subject).len());
+
+    assert_eq!(1, store.remove(&found[0]).unwrap());
+    assert_eq!(total - 1, store.len());
+    assert_eq!(::ISLAND_SIZE - 1, store.match_pattern(&pattern).len());
+    store.insert(found[0]).unwrap();
+    assert_eq!(total, store.scan().count());
+
+    let near = FactPattern { subject: None, predicate: Some(Predicate::Distance), object: None, range: Some(NumericRange::between(-1.0, 1.0)) };
+    let expected = store.scan().filter(|f| match (f.predicate, f.object) {
+        (Predicate::Distance, ObjectValue::Literal(LiteralValue::Integer(d))) => d.abs() <= 1,
+        _ => false
+    }).count();
+    assert!(expected > 0);
+    assert_eq!(expected, store.match_pattern(&near).len());
+
+    // patterns no index applies to still have to be matched fact by fact
+    let anywhere_near = FactPattern { range: Some(NumericRange::between(-1.0, 1.0)), ..FactPattern::default() };
+    assert_eq!(expected, store.match_pattern(&anywhere_near).len());
+    let centre = FactPattern { object: Some(ObjectValue::Literal(LiteralValue::Integer(0))), ..FactPattern::default() };
+    let centres = store.scan().filter(|f| f.object == ObjectValue::Literal(LiteralValue::Integer(0))).count();
+    assert!(centres > 0 && centres < total);
+    assert_eq!(centres, store.match_pattern(&centre).len());
+}
+
+#[test]
+fn every_backend_answers_the_same_patterns() {
+    let facts = ::parse(::EXAMPLE);
+    check_backend(&mut facts.clone(), "contrasts");
+    check_backend(&mut FactSpace::with_facts(facts.clone()), "contrasts");
+    check_backend(&mut IndexedStore::from_facts(facts.clone()), "contrasts");
+}
+
+#[test]
+fn mapped_backend_is_read_only() {
+    let facts = ::parse(::EXAMPLE);
+    let path = ::std::env::temp_dir().join(format!("search-store-{}", ::std::process::id()));
+    ::mapped::write_columns(&mut ::std::fs::File::create(&path).unwrap(), &facts).unwrap();
+    let mapped = MappedStore::open(&path).unwrap();
+    let mut store = &mapped;
+
+    let pattern = FactPattern { subject: None, predicate: None, object: Some(ObjectValue::Literal(LiteralValue::Text("contrasts"))), range: None };
+    assert_eq!(::ISLAND_SIZE, store.match_pattern(&pattern).len());
+    assert_eq!(facts.len(), FactStore::len(&store));
+    assert!(store.insert(facts[0]).is_err());
+
+    ::std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,169 @@
+use std::io;
+use std::io::prelude::*;
+
+use island::{IslandBuilder, IslandGeometry};
+use pool::StringPool;
+use tokenize::Tokenizer;
+use {Fact, FactSpace};
+
+#[derive(Copy, Clone, Default)]
+pub struct ParseOptions {
+    pub geometry: IslandGeometry,
+    pub tokenizer: Tokenizer
+}
+
+// Anything that can receive facts while a corpus is being parsed.
+pub trait FactSink<'a> {
+    fn accept(&mut self, fact: Fact<'a>);
+}
+
+impl<'a> FactSink<'a> for Vec<Fact<'a>> {
+    fn accept(&mut self, fact: Fact<'a>) {
+        self.push(fact);
+    }
+}
+
+impl<'a> FactSink<'a> for FactSpace<'a> {
+    fn accept(&mut self, fact: Fact<'a>) {
+        self.insert(fact);
+    }
+}
+
+pub struct FnSink<F>(pub F);
+
+impl<'a, F> FactSink<'a> for FnSink<F> where F: FnMut(Fact<'a>) {
+    fn accept(&mut self, fact: Fact<'a>) {
+        (self.0)(fact);
+    }
+}
+
+// Writes every fact in its display form, one per line, so nothing is kept in memory.
+pub struct WriterSink<W: Write> {
+    writer: W,
+    error: Option<io::Error>
+}
+
+impl<W: Write> WriterSink<W> {
+    pub fn new(writer: W) -> WriterSink<W> {
+        return WriterSink { writer: writer, error: None };
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()?;
+        return Ok(self.writer);
+    }
+}
+
+impl<'a, W: Write> FactSink<'a> for WriterSink<W> {
+    fn accept(&mut self, fact: Fact<'a>) {
+        if self.error.is_none() {
+            if let Err(err) = writeln!(self.writer, "{}", fact) {
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Progress {
+    pub bytes: u64,
+    pub words: u64,
+    pub facts: u64
+}
+
+// Reads words from `reader` as split by the tokenizer and feeds islands into `sink`.
+// Only the current island and the current token are buffered; word strings are
+// interned into `pool`, so memory grows with the vocabulary, not with the corpus.
+pub fn parse_stream<'a, R, S, P>(reader: &mut R, pool: &'a StringPool, options: ParseOptions, sink: &mut S, mut progress: P) -> io::Result<Progress>
+    where R: BufRead, S: FactSink<'a>, P: FnMut(&Progress)
+{
+    let mut builder = IslandBuilder::new(options.geometry);
+    let mut status = Progress::default();
+    let mut token: Vec<u8> = Vec::new();
+
+    loop {
+        let consumed = {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            for byte in chunk.iter() {
+                if options.tokenizer.is_separator(*byte) {
+                    if !token.is_empty() {
+                        let word = pool.intern(&options.tokenizer.normalize(&token));
+                        status.facts = status.facts + builder.push(word, sink) as u64;
+                        status.words = status.words + 1;
+                        token.clear();
+                    }
+                } else {
+                    token.push(*byte);
+                }
+            }
+            chunk.len()
+        };
+        reader.consume(consumed);
+        status.bytes = status.bytes + consumed as u64;
+        progress(&status);
+    }
+
+    if !token.is_empty() {
+        let word = pool.intern(&options.tokenizer.normalize(&token));
+        status.facts = status.facts + builder.push(word, sink) as u64;
+        status.words = status.words + 1;
+        progress(&status);
+    }
+
+    return Ok(status);
+}
+
+#[test]
+fn stream_matches_in_memory_parse() {
+    let pool = StringPool::new();
+    let mut facts: Vec<Fact> = Vec::new();
+    let mut reader = io::BufReader::with_capacity(16, ::EXAMPLE.as_bytes());
+
+    let status = parse_stream(&mut reader, &pool, ParseOptions::default(), &mut facts, |_| {}).unwrap();
+
+    let expected = ::parse(::EXAMPLE);
+    assert_eq!(expected.len(), facts.len());
+    assert_eq!(expected.len() as u64, status.facts);
+    assert_eq!(::EXAMPLE.len() as u64, status.bytes);
+    for (stream_fact, parsed_fact) in facts.iter().zip(expected.iter()) {
+        assert_eq!(parsed_fact.predicate.order(), stream_fact.predicate.order());
+        match parsed_fact.predicate {
+            ::Predicate::Node => {}
+            _ => assert_eq!(::object_to_string(&parsed_fact.object), ::object_to_string(&stream_fact.object))
+        }
+    }
+}
+
+#[test]
+fn stream_reports_progress_and_writes_to_sink() {
+    let pool = StringPool::new();
+    let mut sink = WriterSink::new(Vec::new());
+    let mut reports = 0;
+
+    parse_stream(&mut "dad went fishing\nyesterday at noon".as_bytes(), &pool, ParseOptions::default(), &mut sink, |_| reports = reports + 1).unwrap();
+
+    let written = String::from_utf8(sink.finish().unwrap()).unwrap();
+    assert!(reports > 0);
+    assert!(written.contains(":word, yesterday)"));
+    assert_eq!(6, pool.len());
+}
+
+#[test]
+fn words_tokenizer_strips_punctuation_and_case() {
+    let pool = StringPool::new();
+    let mut facts: Vec<Fact> = Vec::new();
+    let options = ParseOptions { geometry: IslandGeometry::default(), tokenizer: Tokenizer::Words };
+
+    let status = parse_stream(&mut "The UNION, the (safety) of it's parts.".as_bytes(), &pool, options, &mut facts, |_| {}).unwrap();
+
+    assert_eq!(7, status.words);
+    assert_eq!(6, pool.len());
+    assert_eq!("it's", pool.intern("it's"));
+    assert_eq!(6, pool.len());
+}
@@ -0,0 +1,84 @@
+use island::ISLAND_RADIUS;
+
+/// How a byte stream is cut into words.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Tokenizer {
+    // words are runs of non-whitespace, kept verbatim (what `parse` does for single spaces)
+    Whitespace,
+    // words are runs of letters, digits and apostrophes, lowercased
+    Words
+}
+
+impl Tokenizer {
+    pub fn from_name(name: &str) -> Option<Tokenizer> {
+        return match name {
+            "whitespace" => Some(Tokenizer::Whitespace),
+            "words" => Some(Tokenizer::Words),
+            _ => None
+        }
+    }
+
+    pub fn is_separator(&self, byte: u8) -> bool {
+        return match *self {
+            Tokenizer::Whitespace => byte.is_ascii_whitespace(),
+            Tokenizer::Words => byte.is_ascii() && !byte.is_ascii_alphanumeric() && byte != b'\''
+        }
+    }
+
+    pub fn normalize(&self, token: &[u8]) -> String {
+        let text = String::from_utf8_lossy(token);
+        return match *self {
+            Tokenizer::Whitespace => text.into_owned(),
+            Tokenizer::Words => text.to_lowercase()
+        }
+    }
+}
+
+impl Default for Tokenizer {
+    fn default() -> Tokenizer {
+        return Tokenizer::Whitespace;
+    }
+}
+
+/// A sentence cut into the words that lead into its first full island, the words
+/// in the middle and the words trailing after its last one.
+pub struct SentenceSplit<'a> {
+    pub all_words: Vec<&'a str>,
+    pub initial_words: Vec<&'a str>,
+    pub middle_words: Vec<&'a str>,
+    pub tail_words: Vec<&'a str>
+}
+
+pub fn split_sentence<'a>(text: &'a str) -> SentenceSplit<'a> {
+    let all_words:Vec<&str> = text.split(" ").collect();
+    let (initial_words, non_initial_words) = all_words.split_at(ISLAND_RADIUS);
+    let (middle_words, tail_words) = non_initial_words.split_at(non_initial_words.len() - ISLAND_RADIUS);
+
+    return SentenceSplit {
+        all_words: all_words.to_vec(),
+        initial_words: initial_words.to_vec(),
+        middle_words: middle_words.to_vec(),
+        tail_words: tail_words.to_vec(),
+    }
+}
+
+#[test]
+fn it_can_split_to_sentence_epochs() {
+    let split = split_sentence("A great day to actually die");
+
+    assert_eq!(["A", "great"].to_vec(), split.initial_words);
+    assert_eq!(["actually", "die"].to_vec(), split.tail_words);
+}
+
+#[test]
+#[allow(clippy::get_first)]
+fn vec_is0based() {
+    assert_eq!(["A"].to_vec().get(0).unwrap(), &"A");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn vec_lenIsActual() {
+    assert_eq!(["A"].to_vec().len(), 1);
+}
+
@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use n3::NameId;
+
+use hierarchy::PredicateHierarchy;
+use {Fact, FactSpace, LiteralValue, ObjectValue, Predicate};
+
+// A lazily filtered window onto facts owned elsewhere. Matching composes the filters
+// without touching the facts; nothing is copied until a caller asks for a `FactSpace`.
+#[derive(Clone)]
+pub struct FactView<'s, 'a: 's> {
+    facts: &'s [Fact<'a>],
+    filter: Rc<dyn Fn(&Fact<'a>) -> bool + 's>
+}
+
+impl<'s, 'a: 's> FactView<'s, 'a> {
+    pub fn all(facts: &'s [Fact<'a>]) -> FactView<'s, 'a> {
+        return FactView { facts: facts, filter: Rc::new(|_| true) };
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &'s Fact<'a>> + 's> {
+        let filter = self.filter.clone();
+        return Box::new(self.facts.iter().filter(move |f| filter(f)));
+    }
+
+    pub fn len(&self) -> usize {
+        return self.iter().count();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.iter().next().is_none();
+    }
+
+    // Positions of the matching facts in the underlying slice.
+    pub fn positions(&self) -> Vec<usize> {
+        return self.facts.iter().enumerate().filter(|&(_, f)| (self.filter)(f)).map(|(i, _)| i).collect();
+    }
+
+    pub fn to_vec(&self) -> Vec<Fact<'a>> {
+        return self.iter().cloned().collect();
+    }
+
+    pub fn to_space(&self) -> FactSpace<'a> {
+        return FactSpace::with_facts(self.to_vec());
+    }
+
+    // Every match returns a view over the original storage, so chains like
+    // `space.view().match_predicates(..).match_subject_with_integer(..)` stay lazy.
+    pub fn match_any<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(i64, Predicate, ObjectValue) -> bool + 's
+    {
+        return self.filter_facts(move |x| f(x.subject, x.predicate, x.object));
+    }
+
+    pub fn filter_facts<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(&Fact<'a>) -> bool + 's
+    {
+        let parent = self.filter.clone();
+        return FactView { facts: self.facts, filter: Rc::new(move |x| parent(x) && f(x)) };
+    }
+
+    pub fn match_objects<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(i64, Predicate) -> bool + 's
+    {
+        return self.match_any(move |s, p, _| f(s, p));
+    }
+
+    pub fn match_predicates<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate) -> bool + 's
+    {
+        return self.match_any(move |_, p, _| f(p));
+    }
+
+    // Facts in any of `graphs`, where None stands for the default graph.
+    pub fn match_graphs(&self, graphs: Vec<Option<&'a str>>) -> FactView<'s, 'a> {
+        return self.filter_facts(move |x| graphs.contains(&x.graph));
+    }
+
+    // Facts whose predicate sits at `name` in `hierarchy` or anywhere below it.
+    pub fn match_sub_predicates(&self, hierarchy: &PredicateHierarchy, name: NameId) -> FactView<'s, 'a> {
+        let family = hierarchy.below(name);
+        return self.match_predicates(move |p| family.contains(&p));
+    }
+
+    pub fn match_subjects<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, ObjectValue) -> bool + 's
+    {
+        return self.match_any(move |_, p, o| f(p, o));
+    }
+
+    pub fn match_subject_with_literal<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, LiteralValue) -> bool + 's
+    {
+        return self.match_any(
+            move |_, p, o| match o {
+                    ObjectValue::Id(_) | ObjectValue::Quoted(_) => false,
+                    ObjectValue::Literal(literal) => f(p, literal)
+                });
+    }
+
+    pub fn match_subject_with_integer<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, i64) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::Integer(i) => f(p, i),
+                    _ => false
+                }
+        )
+    }
+
+    pub fn match_subject_with_text<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, &str) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::Text(s) => f(p, s),
+                    _ => false
+                }
+        )
+    }
+
+    pub fn match_subject_with_float<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, f64) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::Float(x) => f(p, x),
+                    _ => false
+                }
+        )
+    }
+
+    pub fn match_subject_with_boolean<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, bool) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::Boolean(b) => f(p, b),
+                    _ => false
+                }
+        )
+    }
+
+    // Days since 1970-01-01.
+    pub fn match_subject_with_date<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, i64) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::Date(days) => f(p, days),
+                    _ => false
+                }
+        )
+    }
+
+    // Milliseconds since the epoch, UTC.
+    pub fn match_subject_with_date_time<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, i64) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::DateTime(millis) => f(p, millis),
+                    _ => false
+                }
+        )
+    }
+
+    // Text and language tag.
+    pub fn match_subject_with_lang_text<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, &str, &str) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::LangText(s, language) => f(p, s, language),
+                    _ => false
+                }
+        )
+    }
+
+    // Lexical form and datatype IRI of literals with a datatype of their own.
+    pub fn match_subject_with_typed<F>(&self, f: F) -> FactView<'s, 'a>
+        where F: Fn(Predicate, &str, &str) -> bool + 's
+    {
+        return self.match_subject_with_literal(
+            move |p, l| match l {
+                    LiteralValue::Typed(lexical, datatype) => f(p, lexical, datatype),
+                    _ => false
+                }
+        )
+    }
+
+    pub fn as_literals(&self) -> Vec<LiteralValue<'a>> {
+        return self.iter()
+            .map(|x| match x.object {
+                ObjectValue::Id(_) | ObjectValue::Quoted(_) => { panic!("not a literal in space casted to literals") }
+                ObjectValue::Literal(literal) => literal
+            })
+            .collect();
+    }
+
+    pub fn single(&self) -> Fact<'a> {
+        let mut matches = self.iter();
+        match (matches.next(), matches.next()) {
+            (Some(fact), None) => { return *fact; }
+            _ => { panic!("fact in space is not single"); }
+        }
+    }
+
+    pub fn get_subjects(&self) -> Vec<i64> {
+        let mut sort: Vec<i64> = self.iter().map(|x| x.subject).collect();
+        sort.sort();
+        sort.dedup();
+        return sort;
+    }
+
+    pub fn contains_subject(&self, subject: i64) -> bool {
+        return self.iter().any(|x| x.subject == subject);
+    }
+
+    pub fn open_subject(&self, subject: i64) -> FactView<'s, 'a> {
+        return self.match_any(move |s, _, _| s == subject);
+    }
+
+    pub fn open_subjects(&self, subjects: Vec<i64>) -> FactView<'s, 'a> {
+        let subjects: HashSet<i64> = subjects.into_iter().collect();
+        return self.match_any(move |s, _, _| subjects.contains(&s));
+    }
+
+    pub fn subject_set(&self) -> HashSet<i64> {
+        return self.iter().map(|x| x.subject).collect();
+    }
+
+    pub fn fact_set(&self) -> HashSet<Fact<'a>> {
+        return self.iter().cloned().collect();
+    }
+
+    // Every distinct fact found in either view, in the order first seen. The views may
+    // look at different storage, so the result is an owned space.
+    pub fn union(&self, other: &FactView<'_, 'a>) -> FactSpace<'a> {
+        let mut seen: HashSet<Fact<'a>> = HashSet::new();
+        let facts: Vec<Fact<'a>> = self.iter().chain(other.iter())
+            .filter(|x| seen.insert(**x))
+            .cloned()
+            .collect();
+        return FactSpace::with_facts(facts);
+    }
+
+    pub fn intersection(&self, other: &FactView<'_, 'a>) -> FactView<'s, 'a> {
+        let keys = other.fact_set();
+        return self.filter_facts(move |x| keys.contains(x));
+    }
+
+    pub fn difference(&self, other: &FactView<'_, 'a>) -> FactView<'s, 'a> {
+        let keys = other.fact_set();
+        return self.filter_facts(move |x| !keys.contains(x));
+    }
+
+    // Facts whose subject is also a subject in `other`.
+    pub fn join_subjects(&self, other: &FactView<'_, 'a>) -> FactView<'s, 'a> {
+        let subjects = other.subject_set();
+        return self.match_any(move |s, _, _| subjects.contains(&s));
+    }
+
+    // Facts whose object is an id that appears as a subject in `other`. A quoted fact's
+    // id counts too, as the facts said about that fact have it as their subject.
+    pub fn join_objects(&self, other: &FactView<'_, 'a>) -> FactView<'s, 'a> {
+        let subjects = other.subject_set();
+        return self.match_any(move |_, _, o| match o {
+            ObjectValue::Id(id) | ObjectValue::Quoted(id) => subjects.contains(&id),
+            _ => false
+        });
+    }
+}
+
+#[test]
+fn chained_matches_share_the_parent_storage() {
+    let facts = ::parse(::EXAMPLE);
+    let space = FactSpace::from_facts(&facts);
+
+    let words = space.match_predicates(|p| matches!(p, Predicate::Word));
+    let contrasts = words.match_subject_with_text(|_, t| t == "contrasts");
+    assert_eq!(::ISLAND_SIZE, contrasts.len());
+
+    for position in contrasts.positions() {
+        assert!(::std::ptr::eq(&facts[position], contrasts.iter().find(|f| f.subject == facts[position].subject).unwrap()));
+    }
+    assert_eq!(contrasts.len(), contrasts.to_space().facts().len());
+}
+
+#[test]
+fn set_operations_use_whole_facts_and_joins_use_ids() {
+    let facts = ::parse("dad went fishing on sunday");
+    let all = FactSpace::from_facts(&facts);
+    let words = all.match_predicates(|p| matches!(p, Predicate::Word));
+    let went = all.match_subject_with_text(|_, t| t == "went");
+
+    assert_eq!(words.len(), words.union(&went).facts().len());
+    assert_eq!(went.len(), words.intersection(&went).len());
+    assert_eq!(words.len() - went.len(), words.difference(&went).len());
+    assert_eq!(0, went.difference(&words).len());
+    assert_eq!(3 * went.len(), all.join_subjects(&went).len());
+
+    let links = vec![
+        Fact::new_object_fact(1, Predicate::Node, 10),
+        Fact::new_object_fact(2, Predicate::Node, 20),
+        Fact::new_text_fact(10, Predicate::Word, "island"),
+        Fact { subject: 3, predicate: Predicate::Rule, object: ObjectValue::Quoted(10), graph: None }
+    ];
+    let links = FactSpace::from_facts(&links);
+    assert_eq!(vec![1, 3], links.join_objects(&links).get_subjects());
+}
+
+#[test]
+fn typed_literals_have_their_own_matchers() {
+    let facts = vec![
+        Fact::new_float_fact(1, Predicate::Distance, 0.5),
+        Fact::new_literal_fact(1, Predicate::Word, LiteralValue::Boolean(true)),
+        Fact::new_literal_fact(2, Predicate::Word, LiteralValue::Date(::literal::parse_date("2020-03-01").unwrap())),
+        Fact::new_literal_fact(2, Predicate::Word, LiteralValue::DateTime(0)),
+        Fact::new_literal_fact(3, Predicate::Word, LiteralValue::LangText("chat", "fr")),
+        Fact::new_literal_fact(3, Predicate::Word, LiteralValue::Typed("chat", "urn:x:animal"))
+    ];
+    let all = FactSpace::from_facts(&facts);
+
+    assert_eq!(vec![1], all.match_subject_with_float(|_, f| f > 0.0).get_subjects());
+    assert_eq!(vec![1], all.match_subject_with_boolean(|_, b| b).get_subjects());
+    assert_eq!(1, all.match_subject_with_date(|_, d| d > ::literal::parse_date("2020-01-01").unwrap()).len());
+    assert_eq!(1, all.match_subject_with_date_time(|_, t| t == 0).len());
+    assert_eq!(1, all.match_subject_with_lang_text(|_, _, l| l == "fr").len());
+    assert_eq!(1, all.match_subject_with_typed(|_, t, _| t == "chat").len());
+    assert_eq!(0, all.match_subject_with_text(|_, t| t == "chat").len());
+}
+
+#[test]
+fn sub_predicates_match_through_the_hierarchy() {
+    let facts = ::parse("dad went fishing on sunday");
+    let space = FactSpace::from_facts(&facts);
+    let hierarchy = PredicateHierarchy::new();
+
+    let links = space.match_sub_predicates(&hierarchy, hierarchy.lookup("link").unwrap());
+    assert_eq!(space.match_predicates(|p| p == Predicate::Node).len(), links.len());
+    let node = hierarchy.lookup("link.node").unwrap();
+    assert_eq!(links.len(), space.match_sub_predicates(&hierarchy, node).len());
+    assert_eq!(facts.len(), space.match_sub_predicates(&hierarchy, hierarchy.names().root()).len());
+}
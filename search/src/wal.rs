@@ -0,0 +1,542 @@
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use literal::{self, encode_object, decode_object};
+use n3::Literal;
+use pool::StringPool;
+use snapshot::{self, fnv1a, predicate_code, predicate_from_code};
+use stream::{self, ParseOptions, Progress};
+use {Fact, FactSpace, ObjectValue, Predicate};
+
+// A directory holding a snapshot plus a log of every change made after it was taken.
+// Log records: payload length u32, FNV-1a 64 of the payload, then the payload itself:
+//   operation u8 (1 insert, 2 retract, 3 identify), for identify the fact id i64,
+//   subject i64, predicate u8, object tag u8, and an u64 value, or for objects with
+//   strings a u32 length and UTF-8 bytes per string, then for facts in a named graph
+//   the graph as another u32 length and UTF-8 bytes. Name records (4) hold the subject
+//   id i64 and its `root.a.b` name as a u32 length and UTF-8 bytes instead of a fact.
+// Identify records give a fact its fact id; their fact is always in the default graph.
+const SNAPSHOT_FILE: &'static str = "snapshot";
+const LOG_FILE: &'static str = "log";
+const RECORD_HEADER: usize = 4 + 8;
+
+const INSERT: u8 = 1;
+const RETRACT: u8 = 2;
+const IDENTIFY: u8 = 3;
+const NAME: u8 = 4;
+
+pub struct DurableStore<'a> {
+    directory: PathBuf,
+    pool: &'a StringPool,
+    space: FactSpace<'a>,
+    log: BufWriter<File>,
+    log_records: usize,
+    // number of log records after which `insert` compacts on its own; 0 disables it
+    pub compact_every: usize,
+    // whether every write is forced to disk before returning
+    pub sync: bool,
+    // bytes dropped from the end of the log while opening
+    pub truncated: u64
+}
+
+// A record for `fact` whose payload starts with `prefix`: the operation and, for
+// identify, the fact id.
+fn encode(prefix: &[u8], fact: &Fact) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(32);
+    payload.extend_from_slice(prefix);
+    payload.extend_from_slice(&fact.subject.to_le_bytes());
+    payload.push(predicate_code(fact.predicate));
+    let (tag, value, texts) = encode_object(fact.object);
+    payload.push(tag);
+    if literal::tag_strings(tag) == 0 {
+        payload.extend_from_slice(&value.to_le_bytes());
+    }
+    for s in texts[..literal::tag_strings(tag)].iter().chain(fact.graph.iter()) {
+        payload.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        payload.extend_from_slice(s.as_bytes());
+    }
+    return framed(payload);
+}
+
+// A name record giving `id` the name `literal`.
+fn encode_name(id: i64, literal: &Literal) -> Vec<u8> {
+    let name = literal.to_string();
+    let mut payload = Vec::with_capacity(13 + name.len());
+    payload.push(NAME);
+    payload.extend_from_slice(&id.to_le_bytes());
+    payload.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    payload.extend_from_slice(name.as_bytes());
+    return framed(payload);
+}
+
+// `payload` behind its length and checksum.
+fn framed(payload: Vec<u8>) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&fnv1a(snapshot::FNV_OFFSET, &payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    return record;
+}
+
+// A u32 length and that many UTF-8 bytes at `at`, which is moved past them.
+fn decode_string<'a>(payload: &[u8], at: &mut usize, pool: &'a StringPool) -> Option<&'a str> {
+    let mut len = [0u8; 4];
+    len.copy_from_slice(payload.get(*at..*at + 4)?);
+    let end = *at + 4 + u32::from_le_bytes(len) as usize;
+    let s = pool.intern(::std::str::from_utf8(payload.get(*at + 4..end)?).ok()?);
+    *at = end;
+    return Some(s);
+}
+
+// The operation, the fact id for identify records (0 for others) and the fact.
+fn decode<'a>(payload: &[u8], pool: &'a StringPool) -> Option<(u8, i64, Fact<'a>)> {
+    let u64_at = |at: usize| payload.get(at..at + 8).map(|b| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(b);
+        u64::from_le_bytes(buf)
+    });
+    let operation = *payload.first()?;
+    let (id, start) = match operation {
+        IDENTIFY => (u64_at(1)? as i64, 9),
+        _ => (0, 1)
+    };
+    if payload.len() < start + 10 {
+        return None;
+    }
+    let subject = u64_at(start)? as i64;
+    let predicate = predicate_from_code(payload[start + 8])?;
+    let tag = payload[start + 9];
+    let mut texts = ["", ""];
+    let mut at = start + 10;
+    for i in 0..literal::tag_strings(tag) {
+        texts[i] = decode_string(payload, &mut at, pool)?;
+    }
+    let value = if literal::tag_strings(tag) == 0 { at = start + 18; u64_at(start + 10)? } else { 0 };
+    let graph = if at < payload.len() { Some(decode_string(payload, &mut at, pool)?) } else { None };
+    let object = decode_object(tag, value, texts)?;
+    return Some((operation, id, Fact { subject: subject, predicate: predicate, object: object, graph: graph }));
+}
+
+// The subject id and name of a name record.
+fn decode_name(payload: &[u8]) -> Option<(i64, Literal)> {
+    if payload.len() < 13 || payload[0] != NAME {
+        return None;
+    }
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&payload[1..9]);
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&payload[9..13]);
+    let name = ::std::str::from_utf8(payload.get(13..13 + u32::from_le_bytes(len) as usize)?).ok()?;
+    return Some((i64::from_le_bytes(id), Literal::parse(name)?));
+}
+
+fn highest_id(fact: &Fact) -> i64 {
+    return match fact.object {
+        ObjectValue::Id(id) | ObjectValue::Quoted(id) if id > fact.subject => id,
+        _ => fact.subject
+    }
+}
+
+// The snapshot in `directory`, or an empty space if none was taken yet.
+fn read_snapshot_file<'a>(directory: &Path, pool: &'a StringPool) -> io::Result<FactSpace<'a>> {
+    return match File::open(directory.join(SNAPSHOT_FILE)) {
+        Ok(file) => snapshot::read_snapshot(BufReader::new(file), pool)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string())),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(FactSpace::new()),
+        Err(err) => Err(err)
+    }
+}
+
+// Applies the records of `log` to `space`. Returns the length of the complete records and
+// their number; what follows them is a torn tail, a record cut short or with a checksum
+// that does not match. A record that is whole but cannot be decoded is an error.
+fn replay<'a>(log: &[u8], space: &mut FactSpace<'a>, pool: &'a StringPool) -> io::Result<(usize, usize)> {
+    let mut offset = 0;
+    let mut records = 0;
+    while offset + RECORD_HEADER <= log.len() {
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&log[offset..offset + 4]);
+        let len = u32::from_le_bytes(len) as usize;
+        let mut checksum = [0u8; 8];
+        checksum.copy_from_slice(&log[offset + 4..offset + 12]);
+        let end = match (offset + RECORD_HEADER).checked_add(len) {
+            Some(end) if end <= log.len() => end,
+            _ => break
+        };
+        let payload = &log[offset + RECORD_HEADER..end];
+        if fnv1a(snapshot::FNV_OFFSET, payload) != u64::from_le_bytes(checksum) {
+            break;
+        }
+        if payload.first() == Some(&NAME) {
+            match decode_name(payload) {
+                Some((id, literal)) if space.names.assign(&literal, id) => {}
+                _ => {
+                    let message = format!("log record {} at byte {} names a subject twice or badly", records + 1, offset);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                }
+            }
+            offset = end;
+            records = records + 1;
+            continue;
+        }
+        match decode(payload, pool) {
+            Some((INSERT, _, fact)) => {
+                Fact::reserve_ids(highest_id(&fact));
+                space.insert(fact);
+            }
+            Some((RETRACT, _, fact)) => {
+                space.remove_where(|f| *f == fact);
+            }
+            Some((IDENTIFY, id, fact)) => {
+                Fact::reserve_ids(highest_id(&fact));
+                space.fact_ids.assign(fact, id);
+            }
+            _ => {
+                let message = format!("log record {} at byte {} cannot be decoded", records + 1, offset);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        }
+        offset = end;
+        records = records + 1;
+    }
+    return Ok((offset, records));
+}
+
+// Loads the store in `directory` without changing it: the directory is not created and a
+// torn tail of the log is skipped rather than cut off.
+pub fn open_read_only<'a, P: AsRef<Path>>(directory: P, pool: &'a StringPool) -> io::Result<FactSpace<'a>> {
+    let directory = directory.as_ref();
+    if !fs::metadata(directory)?.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a directory", directory.display())));
+    }
+    let mut space = read_snapshot_file(directory, pool)?;
+    match fs::read(directory.join(LOG_FILE)) {
+        Ok(log) => { replay(&log, &mut space, pool)?; }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => { return Err(err); }
+    }
+    return Ok(space);
+}
+
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> io::Result<()> {
+    return File::open(directory)?.sync_all();
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_directory: &Path) -> io::Result<()> {
+    return Ok(());
+}
+
+impl<'a> DurableStore<'a> {
+    // Opens or creates a store in `directory`: loads the snapshot, replays the log on top of
+    // it and cuts off a final record that was only partly written.
+    pub fn open<P: AsRef<Path>>(directory: P, pool: &'a StringPool) -> io::Result<DurableStore<'a>> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+        let mut space = read_snapshot_file(&directory, pool)?;
+
+        let mut log = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(directory.join(LOG_FILE))?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+        let (offset, records) = replay(&bytes, &mut space, pool)?;
+
+        let truncated = (bytes.len() - offset) as u64;
+        if truncated > 0 {
+            log.set_len(offset as u64)?;
+            log.sync_data()?;
+        }
+        log.seek(SeekFrom::Start(offset as u64))?;
+
+        return Ok(DurableStore {
+            directory: directory,
+            pool: pool,
+            space: space,
+            log: BufWriter::new(log),
+            log_records: records,
+            compact_every: 100000,
+            sync: true,
+            truncated: truncated
+        });
+    }
+
+    pub fn space(&self) -> &FactSpace<'a> {
+        return &self.space;
+    }
+
+    pub fn into_space(self) -> FactSpace<'a> {
+        return self.space;
+    }
+
+    pub fn pool(&self) -> &'a StringPool {
+        return self.pool;
+    }
+
+    fn commit(&mut self) -> io::Result<()> {
+        self.log.flush()?;
+        if self.sync {
+            self.log.get_ref().sync_data()?;
+        }
+        if self.compact_every > 0 && self.log_records >= self.compact_every {
+            self.compact()?;
+        }
+        return Ok(());
+    }
+
+    fn append(&mut self, operation: u8, fact: &Fact) -> io::Result<()> {
+        self.log.write_all(&encode(&[operation], fact))?;
+        self.log_records = self.log_records + 1;
+        return Ok(());
+    }
+
+    pub fn insert(&mut self, fact: Fact<'a>) -> io::Result<()> {
+        return self.insert_all(vec![fact]);
+    }
+
+    // Logs all facts and forces them to disk once, then adds them to the space.
+    pub fn insert_all(&mut self, facts: Vec<Fact<'a>>) -> io::Result<()> {
+        for fact in facts.iter() {
+            self.append(INSERT, fact)?;
+        }
+        self.log.flush()?;
+        for fact in facts {
+            self.space.insert(fact);
+        }
+        return self.commit();
+    }
+
+    // Logs the removal of every fact equal to `fact`; returns how many were removed.
+    // Nothing is logged when no fact matches.
+    pub fn retract(&mut self, fact: &Fact) -> io::Result<usize> {
+        if !self.space.facts().contains(fact) {
+            return Ok(0);
+        }
+        self.append(RETRACT, fact)?;
+        self.log.flush()?;
+        let removed = self.space.remove_where(|f| *f == *fact);
+        self.commit()?;
+        return Ok(removed);
+    }
+
+    // The fact id of `fact`, logging it when it is handed out.
+    pub fn fact_id(&mut self, fact: Fact<'a>) -> io::Result<i64> {
+        if let Some(id) = self.space.fact_ids.find(&fact) {
+            return Ok(id);
+        }
+        let id = Fact::new_id();
+        let mut prefix = vec![IDENTIFY];
+        prefix.extend_from_slice(&id.to_le_bytes());
+        self.log.write_all(&encode(&prefix, &fact.in_graph(None)))?;
+        self.log_records = self.log_records + 1;
+        self.log.flush()?;
+        self.space.fact_ids.assign(fact, id);
+        self.commit()?;
+        return Ok(id);
+    }
+
+    // The subject id named by `literal`, logging the name when it is handed out.
+    pub fn name(&mut self, literal: &Literal) -> io::Result<i64> {
+        if let Some(id) = self.space.names.find(&literal.to_string()) {
+            return Ok(id);
+        }
+        let id = Fact::new_id();
+        self.log.write_all(&encode_name(id, literal))?;
+        self.log_records = self.log_records + 1;
+        self.log.flush()?;
+        self.space.names.assign(literal, id);
+        self.commit()?;
+        return Ok(id);
+    }
+
+    // `FactSpace::annotate`, logging the fact id and the fact said about it.
+    pub fn annotate(&mut self, fact: Fact<'a>, predicate: Predicate, object: ObjectValue<'a>) -> io::Result<i64> {
+        let id = self.fact_id(fact)?;
+        self.insert(Fact { subject: id, predicate: predicate, object: object, graph: None })?;
+        return Ok(id);
+    }
+
+    pub fn ingest_reader<R: BufRead>(&mut self, reader: &mut R, options: ParseOptions) -> io::Result<Progress> {
+        return self.ingest_reader_into(reader, options, None);
+    }
+
+    // `ingest_reader`, putting the facts in `graph`, or in the default graph for None.
+    pub fn ingest_reader_into<R: BufRead>(&mut self, reader: &mut R, options: ParseOptions, graph: Option<&str>) -> io::Result<Progress> {
+        let graph = graph.map(|graph| self.pool.intern(graph));
+        let mut facts: Vec<Fact<'a>> = Vec::new();
+        let status = stream::parse_stream(reader, self.pool, options, &mut facts, |_| {})?;
+        self.insert_all(facts.into_iter().map(|fact| fact.in_graph(graph)).collect())?;
+        return Ok(status);
+    }
+
+    // Logs the removal of every fact in `graph`; returns how many were removed.
+    pub fn drop_graph(&mut self, graph: Option<&str>) -> io::Result<usize> {
+        let mut facts: Vec<Fact<'a>> = self.space.in_graphs(&[graph.map(|graph| self.pool.intern(graph))]).to_vec();
+        facts.sort();
+        facts.dedup();
+        for fact in facts.iter() {
+            self.append(RETRACT, fact)?;
+        }
+        self.log.flush()?;
+        let removed = self.space.drop_graph(graph);
+        self.commit()?;
+        return Ok(removed);
+    }
+
+    // Makes `facts` the whole content of `graph`, logging the old facts' removal first.
+    pub fn replace_graph(&mut self, graph: Option<&str>, facts: Vec<Fact<'a>>) -> io::Result<usize> {
+        let removed = self.drop_graph(graph)?;
+        let graph = graph.map(|graph| self.pool.intern(graph));
+        self.insert_all(facts.into_iter().map(|fact| fact.in_graph(graph)).collect())?;
+        return Ok(removed);
+    }
+
+    // Writes the current space as a new snapshot and empties the log. The snapshot is
+    // written to a temporary file and renamed, so a crash leaves either the old or the new one;
+    // the directory is synced so the rename itself survives a crash before the log is emptied.
+    pub fn compact(&mut self) -> io::Result<()> {
+        let temporary = self.directory.join(format!("{}.tmp", SNAPSHOT_FILE));
+        {
+            let file = File::create(&temporary)?;
+            let mut writer = BufWriter::new(file);
+            snapshot::write_snapshot(&mut writer, &self.space)?;
+            writer.get_ref().sync_all()?;
+        }
+        fs::rename(&temporary, self.directory.join(SNAPSHOT_FILE))?;
+        sync_directory(&self.directory)?;
+
+        self.log.flush()?;
+        self.log.get_mut().set_len(0)?;
+        self.log.get_mut().seek(SeekFrom::Start(0))?;
+        self.log.get_ref().sync_data()?;
+        self.log_records = 0;
+        return Ok(());
+    }
+}
+
+pub fn is_store(path: &Path) -> bool {
+    return path.is_dir() && (path.join(LOG_FILE).exists() || path.join(SNAPSHOT_FILE).exists());
+}
+
+#[cfg(test)]
+fn temp_directory(name: &str) -> PathBuf {
+    let path = ::std::env::temp_dir().join(format!("search-wal-{}-{}", ::std::process::id(), name));
+    let _ = fs::remove_dir_all(&path);
+    return path;
+}
+
+#[test]
+fn store_replays_log_and_compacts() {
+    let directory = temp_directory("replay");
+    let pool = StringPool::new();
+    // fresh ids, so these subjects are not among the ingested islands
+    let (five, six, seven) = (Fact::new_id(), Fact::new_id(), Fact::new_id());
+    {
+        let mut store = DurableStore::open(&directory, &pool).unwrap();
+        store.ingest_reader(&mut "dad went fishing on sunday".as_bytes(), ParseOptions::default()).unwrap();
+        store.insert(Fact::new_float_fact(five, ::Predicate::Distance, 0.5)).unwrap();
+        assert_eq!(1, store.retract(&Fact::new_float_fact(five, ::Predicate::Distance, 0.5)).unwrap());
+        store.insert(Fact::new_literal_fact(six, ::Predicate::Word, ::LiteralValue::LangText("père", "fr"))).unwrap();
+        store.insert(Fact::new_integer_fact(seven, ::Predicate::Distance, 1).in_graph(Some("extra"))).unwrap();
+        store.insert(Fact::new_text_fact(seven, ::Predicate::Word, "dropped").in_graph(Some("dropped"))).unwrap();
+        assert_eq!(1, store.drop_graph(Some("dropped")).unwrap());
+        let id = store.annotate(Fact::new_text_fact(seven, ::Predicate::Word, "sure"), ::Predicate::Confidence, ::ObjectValue::Literal(::LiteralValue::Float(0.9))).unwrap();
+        assert_eq!(id, store.fact_id(Fact::new_text_fact(seven, ::Predicate::Word, "sure")).unwrap());
+        let dad = store.name(&Literal::parse("people.dad").unwrap()).unwrap();
+        assert_eq!(dad, store.name(&Literal::parse("root.people.dad").unwrap()).unwrap());
+    }
+
+    let reopened_pool = StringPool::new();
+    let mut store = DurableStore::open(&directory, &reopened_pool).unwrap();
+    assert_eq!(0, store.truncated);
+    assert_eq!(3, store.space().word_count("went"));
+    assert_eq!(0, store.space().view().open_subjects(vec![five]).len());
+    assert_eq!(::ObjectValue::Literal(::LiteralValue::LangText("père", "fr")), store.space().view().open_subject(six).single().object);
+    assert_eq!(Some("extra"), store.space().view().open_subject(seven).single().graph);
+    assert_eq!(vec!["extra"], store.space().graphs());
+    let sure = Fact::new_text_fact(seven, ::Predicate::Word, "sure");
+    assert_eq!(0.9, store.space().about(&sure).single().get_float_literal());
+    let dad = store.space().names.find("people.dad").unwrap();
+    assert_eq!(Some("people.dad".to_string()), store.space().names.name(dad));
+    let count = store.space().facts().len();
+
+    store.compact().unwrap();
+    assert_eq!(0, fs::metadata(directory.join(LOG_FILE)).unwrap().len());
+    drop(store);
+    let compacted = DurableStore::open(&directory, &reopened_pool).unwrap();
+    assert_eq!(count, compacted.space().facts().len());
+    assert_eq!(1, compacted.space().about(&sure).len());
+    assert_eq!(Some(dad), compacted.space().names.find("people.dad"));
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn store_truncates_torn_final_record() {
+    let directory = temp_directory("torn");
+    let pool = StringPool::new();
+    {
+        let mut store = DurableStore::open(&directory, &pool).unwrap();
+        store.insert(Fact::new_text_fact(1, ::Predicate::Word, "kept")).unwrap();
+        store.insert(Fact::new_text_fact(2, ::Predicate::Word, "torn")).unwrap();
+    }
+    let log = directory.join(LOG_FILE);
+    let full = fs::metadata(&log).unwrap().len();
+    OpenOptions::new().write(true).open(&log).unwrap().set_len(full - 3).unwrap();
+
+    let store = DurableStore::open(&directory, &pool).unwrap();
+    assert!(store.truncated > 0);
+    assert_eq!(1, store.space().facts().len());
+    assert_eq!(1, store.space().word_count("kept"));
+    assert_eq!(full - store.truncated - 3, fs::metadata(&log).unwrap().len());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn store_rejects_whole_records_that_do_not_decode() {
+    let directory = temp_directory("undecodable");
+    let pool = StringPool::new();
+    {
+        let mut store = DurableStore::open(&directory, &pool).unwrap();
+        store.insert(Fact::new_text_fact(1, ::Predicate::Word, "kept")).unwrap();
+        assert_eq!(0, store.retract(&Fact::new_text_fact(1, ::Predicate::Word, "absent")).unwrap());
+    }
+    let log = directory.join(LOG_FILE);
+    let before = fs::metadata(&log).unwrap().len();
+    let mut record = encode(&[INSERT], &Fact::new_text_fact(2, ::Predicate::Word, "bad"));
+    record[RECORD_HEADER] = 9;
+    let checksum = fnv1a(snapshot::FNV_OFFSET, &record[RECORD_HEADER..]);
+    record[4..RECORD_HEADER].copy_from_slice(&checksum.to_le_bytes());
+    OpenOptions::new().append(true).open(&log).unwrap().write_all(&record).unwrap();
+
+    assert_eq!(io::ErrorKind::InvalidData, DurableStore::open(&directory, &pool).err().unwrap().kind());
+    assert_eq!(io::ErrorKind::InvalidData, open_read_only(&directory, &pool).err().unwrap().kind());
+    assert_eq!(before + record.len() as u64, fs::metadata(&log).unwrap().len());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn read_only_open_changes_nothing() {
+    let directory = temp_directory("read-only");
+    let pool = StringPool::new();
+    assert!(open_read_only(&directory, &pool).is_err());
+    assert!(!directory.exists());
+    {
+        let mut store = DurableStore::open(&directory, &pool).unwrap();
+        store.insert(Fact::new_text_fact(1, ::Predicate::Word, "kept")).unwrap();
+        store.insert(Fact::new_text_fact(2, ::Predicate::Word, "torn")).unwrap();
+    }
+    let log = directory.join(LOG_FILE);
+    let full = fs::metadata(&log).unwrap().len();
+    OpenOptions::new().write(true).open(&log).unwrap().set_len(full - 3).unwrap();
+
+    let space = open_read_only(&directory, &pool).unwrap();
+    assert_eq!(1, space.facts().len());
+    assert_eq!(full - 3, fs::metadata(&log).unwrap().len());
+
+    fs::remove_dir_all(&directory).unwrap();
+}